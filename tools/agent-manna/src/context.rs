@@ -1 +1,778 @@
-// TODO: implement context blob generation for hooks
+//! Context blob generation for AI agent prompts.
+//!
+//! The blob is a markdown document listing issues grouped into sections,
+//! truncated to a rough token budget (1 token ≈ 4 chars).
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::issue::{Issue, IssueStatus};
+
+/// Rough characters per token used to apply the token budget.
+pub const CHARS_PER_TOKEN: usize = 4;
+
+/// A section of the context blob.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContextSection {
+    Open,
+    InProgress,
+    Blocked,
+}
+
+impl ContextSection {
+    /// Section order used when none is specified.
+    pub const DEFAULT_ORDER: [ContextSection; 3] = [
+        ContextSection::Open,
+        ContextSection::InProgress,
+        ContextSection::Blocked,
+    ];
+
+    /// Parse a section name (open, in_progress, blocked).
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s.trim().to_lowercase().as_str() {
+            "open" => Ok(ContextSection::Open),
+            "in_progress" => Ok(ContextSection::InProgress),
+            "blocked" => Ok(ContextSection::Blocked),
+            _ => Err(format!(
+                "Invalid section '{}'. Valid options: open, in_progress, blocked",
+                s
+            )),
+        }
+    }
+
+    /// Status of the issues listed in this section.
+    pub fn status(self) -> IssueStatus {
+        match self {
+            ContextSection::Open => IssueStatus::Open,
+            ContextSection::InProgress => IssueStatus::InProgress,
+            ContextSection::Blocked => IssueStatus::Blocked,
+        }
+    }
+
+    /// Section heading text.
+    fn heading(self) -> &'static str {
+        match self {
+            ContextSection::Open => "Open Issues",
+            ContextSection::InProgress => "In Progress Issues",
+            ContextSection::Blocked => "Blocked Issues",
+        }
+    }
+
+    /// Render one issue line for this section.
+    fn render_line(self, issue: &Issue) -> String {
+        match self {
+            ContextSection::Open => format!("- {}: {} [open]\n", issue.id, issue.title),
+            ContextSection::InProgress => {
+                let claimed = issue
+                    .claimed_by
+                    .as_ref()
+                    .map_or("".to_string(), |s| format!(", claimed by {}", s));
+                format!("- {}: {} [in_progress{}]\n", issue.id, issue.title, claimed)
+            }
+            ContextSection::Blocked => format!(
+                "- {}: {} [blocked by: {}]\n",
+                issue.id,
+                issue.title,
+                issue.blocked_by.join(", ")
+            ),
+        }
+    }
+}
+
+/// Parse a comma-separated section list such as `blocked,open`.
+///
+/// Duplicate names are an error, as is an empty list.
+pub fn parse_sections(spec: &str) -> Result<Vec<ContextSection>, String> {
+    let mut sections = Vec::new();
+    for name in spec.split(',').filter(|s| !s.trim().is_empty()) {
+        let section = ContextSection::parse(name)?;
+        if sections.contains(&section) {
+            return Err(format!("Section '{}' listed more than once", name.trim()));
+        }
+        sections.push(section);
+    }
+
+    if sections.is_empty() {
+        return Err("At least one section is required".to_string());
+    }
+
+    Ok(sections)
+}
+
+/// Options controlling context blob generation.
+#[derive(Debug, Clone)]
+pub struct ContextOptions {
+    /// Maximum tokens for the blob
+    pub max_tokens: usize,
+
+    /// Sections to include, in output order
+    pub sections: Vec<ContextSection>,
+
+    /// Move the blocked section last and list claimable issues first
+    /// within each section
+    pub sort_blocked_last: bool,
+
+    /// Reference time for marking overdue issues
+    pub now: DateTime<Utc>,
+}
+
+impl Default for ContextOptions {
+    fn default() -> Self {
+        ContextOptions {
+            max_tokens: 8000,
+            sections: ContextSection::DEFAULT_ORDER.to_vec(),
+            sort_blocked_last: false,
+            now: Utc::now(),
+        }
+    }
+}
+
+/// The next time after `now` that an issue becomes overdue, changing the
+/// blob without any write to the store.
+pub fn next_overdue_at(issues: &[Issue], now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    issues
+        .iter()
+        .filter(|i| i.status != IssueStatus::Done)
+        .filter_map(|i| i.due_at)
+        .filter(|due| *due >= now)
+        .min()
+}
+
+/// Sections in output order, honoring `sort_blocked_last`.
+fn ordered_sections(options: &ContextOptions) -> Vec<ContextSection> {
+    let mut sections = options.sections.clone();
+    if options.sort_blocked_last {
+        // Stable: other sections keep their requested order
+        sections.sort_by_key(|s| *s == ContextSection::Blocked);
+    }
+    sections
+}
+
+/// Issues listed in `section`, in output order.
+fn section_members<'a>(
+    issues: &'a [Issue],
+    section: ContextSection,
+    options: &ContextOptions,
+) -> Vec<&'a Issue> {
+    let mut members: Vec<&Issue> = issues
+        .iter()
+        .filter(|i| i.status == section.status())
+        .collect();
+    if options.sort_blocked_last {
+        members.sort_by_key(|i| !i.is_claimable(issues));
+    }
+    members
+}
+
+/// Token accounting for one section of the context blob.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SectionBudget {
+    /// Status the section lists
+    pub section: IssueStatus,
+
+    /// Estimated tokens the section takes in the blob, heading included
+    pub tokens: usize,
+
+    /// Issues listed in full
+    pub shown: usize,
+
+    /// Issues cut off by truncation
+    pub omitted: usize,
+}
+
+/// Token accounting for a context blob.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ContextBudget {
+    /// The token budget the blob was built for
+    pub max_tokens: usize,
+
+    /// Estimated tokens in the final blob
+    pub used_tokens: usize,
+
+    /// Whether the blob was truncated
+    pub truncated: bool,
+
+    /// Per-section accounting, in output order
+    pub sections: Vec<SectionBudget>,
+}
+
+/// Estimated tokens for `chars` characters of blob.
+fn estimate_tokens(chars: usize) -> usize {
+    chars.div_ceil(CHARS_PER_TOKEN)
+}
+
+/// Build the context blob for the given issues.
+pub fn build_context(issues: &[Issue], options: &ContextOptions) -> String {
+    build_context_with_budget(issues, options).0
+}
+
+/// Build the context blob along with an account of where its token budget
+/// went and how many issues each section lost to truncation.
+pub fn build_context_with_budget(
+    issues: &[Issue],
+    options: &ContextOptions,
+) -> (String, ContextBudget) {
+    let mut context = String::new();
+    context.push_str("# Manna Context\n\n");
+
+    // Each section's start offset and the end offset of each issue line
+    let mut spans: Vec<(ContextSection, usize, Vec<usize>)> = Vec::new();
+    for (idx, section) in ordered_sections(options).into_iter().enumerate() {
+        if idx > 0 {
+            context.push('\n');
+        }
+
+        let start = context.len();
+        let members = section_members(issues, section, options);
+        context.push_str(&format!("## {} ({})\n", section.heading(), members.len()));
+        let mut line_ends = Vec::new();
+        for issue in members {
+            let line = section.render_line(issue);
+            if issue.is_overdue(options.now) {
+                context.push_str(line.trim_end());
+                context.push_str(" [overdue]\n");
+            } else {
+                context.push_str(&line);
+            }
+            line_ends.push(context.len());
+        }
+        spans.push((section, start, line_ends));
+    }
+
+    // Truncate if needed (rough estimate: 1 token ≈ 4 chars)
+    let max_chars = options.max_tokens * CHARS_PER_TOKEN;
+    let truncated = context.len() > max_chars;
+    if truncated {
+        context.truncate(max_chars - 20);
+    }
+    let kept = context.len();
+    if truncated {
+        context.push_str("\n\n[truncated]");
+    }
+
+    let sections = spans
+        .into_iter()
+        .map(|(section, start, line_ends)| {
+            let end = line_ends.last().copied().unwrap_or(start);
+            let shown = line_ends.iter().filter(|e| **e <= kept).count();
+            SectionBudget {
+                section: section.status(),
+                tokens: estimate_tokens(end.min(kept) - start.min(kept)),
+                shown,
+                omitted: line_ends.len() - shown,
+            }
+        })
+        .collect();
+    let budget = ContextBudget {
+        max_tokens: options.max_tokens,
+        used_tokens: estimate_tokens(context.len()),
+        truncated,
+        sections,
+    };
+
+    (context, budget)
+}
+
+/// One issue in the structured context.
+#[derive(Debug, Clone, Serialize)]
+pub struct ContextIssue {
+    pub id: String,
+    pub title: String,
+    pub status: IssueStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub claimed_by: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub blocked_by: Vec<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub overdue: bool,
+}
+
+impl ContextIssue {
+    /// `issue` as the context shows it at `now`.
+    pub fn of(issue: &Issue, now: DateTime<Utc>) -> Self {
+        ContextIssue {
+            id: issue.id.clone(),
+            title: issue.title.clone(),
+            status: issue.status.clone(),
+            claimed_by: issue.claimed_by.clone(),
+            blocked_by: issue.blocked_by.clone(),
+            overdue: issue.is_overdue(now),
+        }
+    }
+}
+
+/// The context blob's content as structured sections.
+///
+/// `ready` lists claimable issues; the other sections mirror the blob and
+/// are absent unless selected in the options.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StructuredContext {
+    pub ready: Vec<ContextIssue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open: Option<Vec<ContextIssue>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub in_progress: Option<Vec<ContextIssue>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blocked: Option<Vec<ContextIssue>>,
+    /// Whether issues were left out to stay within the token budget
+    pub truncated: bool,
+}
+
+/// Build the structured context for the given issues.
+///
+/// The token budget is honored by limiting how many issues are included
+/// (rough estimate: 1 token ≈ 4 chars of JSON per issue), filling `ready`
+/// first and then each section in order.
+pub fn build_structured_context(issues: &[Issue], options: &ContextOptions) -> StructuredContext {
+    let mut budget = options.max_tokens * CHARS_PER_TOKEN;
+    let mut context = StructuredContext::default();
+
+    let mut take = |members: Vec<&Issue>, truncated: &mut bool| -> Vec<ContextIssue> {
+        let mut out = Vec::new();
+        for issue in members {
+            let entry = ContextIssue::of(issue, options.now);
+            let cost = serde_json::to_string(&entry).map_or(0, |j| j.len());
+            if cost > budget {
+                *truncated = true;
+                break;
+            }
+            budget -= cost;
+            out.push(entry);
+        }
+        out
+    };
+
+    let ready = issues.iter().filter(|i| i.is_claimable(issues)).collect();
+    context.ready = take(ready, &mut context.truncated);
+
+    for section in ordered_sections(options) {
+        let members = if context.truncated {
+            Vec::new()
+        } else {
+            section_members(issues, section, options)
+        };
+        let entries = Some(take(members, &mut context.truncated));
+        match section {
+            ContextSection::Open => context.open = entries,
+            ContextSection::InProgress => context.in_progress = entries,
+            ContextSection::Blocked => context.blocked = entries,
+        }
+    }
+
+    context
+}
+
+/// Render `listed` as an instruction-style prompt for an agent.
+///
+/// Claimable issues come first as a numbered list, each followed by the
+/// command that claims it; the rest are listed with their status after.
+/// `all` resolves blockers. Whole items are dropped once the token budget
+/// is spent, with a count of what was left out.
+pub fn build_agent_prompt(listed: &[Issue], all: &[Issue], max_tokens: usize) -> String {
+    let (ready, waiting): (Vec<&Issue>, Vec<&Issue>) =
+        listed.iter().partition(|i| i.is_claimable(all));
+
+    let mut lines = Vec::new();
+    if ready.is_empty() {
+        lines.push("No issues are ready to claim right now.".to_string());
+    } else {
+        lines.push(format!(
+            "{} issue(s) ready to work on. Claim exactly one before starting:",
+            ready.len()
+        ));
+    }
+    let mut items: Vec<String> = ready
+        .iter()
+        .enumerate()
+        .map(|(n, issue)| {
+            format!(
+                "{}. {}: {}\n   To claim, run: manna-core claim {}",
+                n + 1,
+                issue.id,
+                issue.title,
+                issue.id
+            )
+        })
+        .collect();
+    if !waiting.is_empty() {
+        items.push(format!("\nNot claimable yet ({}):", waiting.len()));
+        items.extend(waiting.iter().map(|issue| {
+            let state = match issue.status {
+                IssueStatus::InProgress => format!(
+                    "in_progress, claimed by {}",
+                    issue.claimed_by.as_deref().unwrap_or("unknown")
+                ),
+                IssueStatus::Blocked => format!("blocked by: {}", issue.blocked_by.join(", ")),
+                _ => issue.status.to_string(),
+            };
+            format!("- {}: {} [{}]", issue.id, issue.title, state)
+        }));
+    }
+
+    let mut budget = max_tokens.saturating_mul(CHARS_PER_TOKEN);
+    let mut prompt = lines.join("\n");
+    budget = budget.saturating_sub(prompt.len());
+    let total = items.len();
+    for (shown, item) in items.into_iter().enumerate() {
+        if item.len() + 1 > budget {
+            prompt.push_str(&format!("\n[{} more not shown]", total - shown));
+            break;
+        }
+        budget -= item.len() + 1;
+        prompt.push('\n');
+        prompt.push_str(&item);
+    }
+    prompt.push('\n');
+    prompt
+}
+
+/// Issues in the context's selected sections, in output order, before any
+/// truncation.
+pub fn context_members<'a>(issues: &'a [Issue], options: &ContextOptions) -> Vec<&'a Issue> {
+    ordered_sections(options)
+        .into_iter()
+        .flat_map(|section| section_members(issues, section, options))
+        .collect()
+}
+
+/// Issues listed in full in the blob that `build_context_with_budget` built
+/// from `issues` and `options`, given its `budget`.
+pub fn shown_members<'a>(
+    issues: &'a [Issue],
+    options: &ContextOptions,
+    budget: &ContextBudget,
+) -> Vec<&'a Issue> {
+    ordered_sections(options)
+        .into_iter()
+        .zip(&budget.sections)
+        .flat_map(|(section, spent)| {
+            let mut members = section_members(issues, section, options);
+            members.truncate(spent.shown);
+            members
+        })
+        .collect()
+}
+
+/// Build the agent prompt for the context's selected sections.
+pub fn build_agent_context(issues: &[Issue], options: &ContextOptions) -> String {
+    let listed: Vec<Issue> = context_members(issues, options)
+        .into_iter()
+        .cloned()
+        .collect();
+    build_agent_prompt(&listed, issues, options.max_tokens)
+}
+
+/// What changed in the context since a generation an agent already has.
+#[derive(Debug, Clone, Serialize)]
+pub struct ContextDiff {
+    /// Issues added to the context or changed since, in output order
+    pub changed: Vec<ContextIssue>,
+
+    /// IDs of issues that have since left the context, sorted
+    pub removed: Vec<String>,
+}
+
+/// Fingerprint each issue by ID: a short hash of what the context shows
+/// of it at `now`, so any change an agent would see gives a new fingerprint.
+pub fn issue_fingerprints(issues: &[&Issue], now: DateTime<Utc>) -> BTreeMap<String, String> {
+    issues
+        .iter()
+        .map(|issue| {
+            let record = serde_json::to_string(&ContextIssue::of(issue, now)).unwrap_or_default();
+            let hash = format!("{:x}", Sha256::digest(record.as_bytes()));
+            (issue.id.clone(), hash[..16].to_string())
+        })
+        .collect()
+}
+
+/// Diff the issues the context now selects (from `context_members`)
+/// against the fingerprints of those an agent was given earlier.
+pub fn context_diff(
+    since: &BTreeMap<String, String>,
+    selected: &[&Issue],
+    now: DateTime<Utc>,
+) -> ContextDiff {
+    let current = issue_fingerprints(selected, now);
+    ContextDiff {
+        changed: selected
+            .iter()
+            .filter(|i| since.get(&i.id) != current.get(&i.id))
+            .map(|i| ContextIssue::of(i, now))
+            .collect(),
+        removed: since
+            .keys()
+            .filter(|id| !current.contains_key(*id))
+            .cloned()
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::issue::SessionId;
+
+    fn sample_issues() -> Vec<Issue> {
+        let open = Issue::new("mn-ctx001".to_string(), "Open Issue".to_string()).unwrap();
+
+        let mut in_progress =
+            Issue::new("mn-ctx002".to_string(), "In Progress".to_string()).unwrap();
+        in_progress
+            .claim(SessionId::new("ses_test").unwrap())
+            .unwrap();
+
+        let mut blocked = Issue::new("mn-ctx003".to_string(), "Blocked Issue".to_string()).unwrap();
+        blocked.add_blocker("mn-ctx001".to_string());
+
+        vec![open, in_progress, blocked]
+    }
+
+    #[test]
+    fn test_context_diff_lists_only_changes() {
+        let now = Utc::now();
+        let mut issues = sample_issues();
+        let since = issue_fingerprints(&issues.iter().collect::<Vec<_>>(), now);
+        let selected: Vec<&Issue> = issues.iter().collect();
+        assert!(context_diff(&since, &selected, now).changed.is_empty());
+
+        issues[0].title = "Open Issue, retitled".to_string();
+        issues.remove(2);
+        issues.push(Issue::new("mn-ctx004".to_string(), "New".to_string()).unwrap());
+        let selected: Vec<&Issue> = issues.iter().collect();
+        let diff = context_diff(&since, &selected, now);
+
+        let changed: Vec<&str> = diff.changed.iter().map(|i| i.id.as_str()).collect();
+        assert_eq!(changed, vec!["mn-ctx001", "mn-ctx004"]);
+        assert_eq!(diff.removed, vec!["mn-ctx003"]);
+    }
+
+    #[test]
+    fn test_context_diff_follows_the_selection() {
+        let now = Utc::now();
+        let mut issues = sample_issues();
+        let options = ContextOptions {
+            sections: parse_sections("open,in_progress").unwrap(),
+            now,
+            ..ContextOptions::default()
+        };
+        let since = issue_fingerprints(&context_members(&issues, &options), now);
+        assert!(!since.contains_key("mn-ctx003"));
+
+        // A change to an unselected issue is not reported; an issue leaving
+        // the selected sections is removed
+        issues[2].title = "Blocked, retitled".to_string();
+        issues[0].status = IssueStatus::Done;
+        let diff = context_diff(&since, &context_members(&issues, &options), now);
+
+        assert!(diff.changed.is_empty());
+        assert_eq!(diff.removed, vec!["mn-ctx001"]);
+    }
+
+    #[test]
+    fn test_shown_members_stop_at_truncation() {
+        let issues: Vec<Issue> = (0..20)
+            .map(|n| {
+                Issue::new(
+                    format!("mn-5e0{:03x}", n),
+                    "A reasonably long issue title".to_string(),
+                )
+                .unwrap()
+            })
+            .collect();
+        let options = ContextOptions {
+            max_tokens: 100,
+            ..Default::default()
+        };
+
+        let (context, budget) = build_context_with_budget(&issues, &options);
+        let shown = shown_members(&issues, &options, &budget);
+
+        assert_eq!(shown.len(), budget.sections[0].shown);
+        assert!(shown.len() < issues.len());
+        assert!(shown
+            .iter()
+            .all(|i| context.contains(&format!("{}: ", i.id))));
+    }
+
+    #[test]
+    fn test_default_context_layout() {
+        let context = build_context(&sample_issues(), &ContextOptions::default());
+
+        assert_eq!(
+            context,
+            "# Manna Context\n\n\
+             ## Open Issues (1)\n\
+             - mn-ctx001: Open Issue [open]\n\n\
+             ## In Progress Issues (1)\n\
+             - mn-ctx002: In Progress [in_progress, claimed by ses_test]\n\n\
+             ## Blocked Issues (1)\n\
+             - mn-ctx003: Blocked Issue [blocked by: mn-ctx001]\n"
+        );
+    }
+
+    #[test]
+    fn test_overdue_marker() {
+        let now = Utc::now();
+        let mut issues = sample_issues();
+        issues[0].due_at = Some(now - chrono::Duration::days(1));
+        issues[1].due_at = Some(now + chrono::Duration::days(1));
+
+        let options = ContextOptions {
+            now,
+            ..ContextOptions::default()
+        };
+        let context = build_context(&issues, &options);
+
+        assert!(context.contains("- mn-ctx001: Open Issue [open] [overdue]\n"));
+        assert!(context.contains("claimed by ses_test]\n"));
+    }
+
+    #[test]
+    fn test_structured_context_shape() {
+        let options = ContextOptions {
+            sections: parse_sections("open,blocked").unwrap(),
+            ..ContextOptions::default()
+        };
+        let context = build_structured_context(&sample_issues(), &options);
+        let value = serde_json::to_value(&context).unwrap();
+
+        assert_eq!(value["ready"][0]["id"], "mn-ctx001");
+        assert_eq!(value["open"][0]["id"], "mn-ctx001");
+        assert_eq!(value["blocked"][0]["blocked_by"][0], "mn-ctx001");
+        assert!(value.get("in_progress").is_none());
+        assert_eq!(value["truncated"], false);
+        assert!(value["open"][0].get("blocked_by").is_none());
+    }
+
+    #[test]
+    fn test_structured_context_budget_limits_count() {
+        let options = ContextOptions {
+            max_tokens: 20,
+            ..ContextOptions::default()
+        };
+        let context = build_structured_context(&sample_issues(), &options);
+
+        // One entry fits in 80 chars, a second does not
+        assert_eq!(context.ready.len(), 1);
+        assert_eq!(context.open.as_deref().map(<[_]>::len), Some(0));
+        assert!(context.truncated);
+    }
+
+    #[test]
+    fn test_custom_section_order() {
+        let options = ContextOptions {
+            sections: parse_sections("blocked,open").unwrap(),
+            ..ContextOptions::default()
+        };
+        let context = build_context(&sample_issues(), &options);
+
+        let blocked_pos = context.find("## Blocked Issues").unwrap();
+        let open_pos = context.find("## Open Issues").unwrap();
+        assert!(blocked_pos < open_pos);
+        assert!(!context.contains("## In Progress Issues"));
+    }
+
+    #[test]
+    fn test_parse_sections_rejects_unknown_and_duplicates() {
+        let unknown = parse_sections("open,later");
+        assert!(unknown.unwrap_err().contains("Invalid section 'later'"));
+
+        let duplicate = parse_sections("open,open");
+        assert!(duplicate.unwrap_err().contains("more than once"));
+
+        assert!(parse_sections("").is_err());
+    }
+
+    #[test]
+    fn test_sort_blocked_last() {
+        let mut issues = sample_issues();
+        // An open issue whose blocker is unresolved is not claimable
+        let mut waiting = Issue::new("mn-ctx004".to_string(), "Waiting".to_string()).unwrap();
+        waiting.blocked_by.push("mn-ctx002".to_string());
+        issues.insert(0, waiting);
+
+        let options = ContextOptions {
+            sections: parse_sections("blocked,open").unwrap(),
+            sort_blocked_last: true,
+            ..ContextOptions::default()
+        };
+        let context = build_context(&issues, &options);
+
+        let open_pos = context.find("## Open Issues").unwrap();
+        let blocked_pos = context.find("## Blocked Issues").unwrap();
+        assert!(open_pos < blocked_pos);
+
+        let claimable_pos = context.find("mn-ctx001").unwrap();
+        let waiting_pos = context.find("mn-ctx004").unwrap();
+        assert!(claimable_pos < waiting_pos);
+    }
+
+    #[test]
+    fn test_truncation() {
+        let options = ContextOptions {
+            max_tokens: 20,
+            ..ContextOptions::default()
+        };
+        let context = build_context(&sample_issues(), &options);
+
+        assert!(context.ends_with("[truncated]"));
+        assert!(context.len() <= 20 * 4);
+    }
+
+    #[test]
+    fn test_budget_report_counts_omitted() {
+        let issues: Vec<Issue> = (0..20)
+            .map(|n| {
+                Issue::new(
+                    format!("mn-b0d{:03x}", n),
+                    "A reasonably long issue title".to_string(),
+                )
+                .unwrap()
+            })
+            .collect();
+        let options = ContextOptions {
+            max_tokens: 100,
+            ..Default::default()
+        };
+
+        let (context, budget) = build_context_with_budget(&issues, &options);
+
+        assert!(budget.truncated);
+        assert!(budget.used_tokens <= budget.max_tokens);
+        let open = &budget.sections[0];
+        assert_eq!(open.section, IssueStatus::Open);
+        assert!(open.shown > 0);
+        assert_eq!(open.shown + open.omitted, 20);
+        assert_eq!(
+            context.matches("[open]").count(),
+            open.shown,
+            "partially cut lines count as omitted"
+        );
+        // Sections pushed entirely past the cut take no tokens
+        let blocked = &budget.sections[2];
+        assert_eq!((blocked.tokens, blocked.omitted), (0, 0));
+
+        let (_, roomy) = build_context_with_budget(&issues, &ContextOptions::default());
+        assert!(!roomy.truncated);
+        assert!(roomy.sections.iter().all(|s| s.omitted == 0));
+    }
+
+    #[test]
+    fn test_agent_prompt_claim_hints() {
+        let issues = sample_issues();
+        let prompt = build_agent_prompt(&issues, &issues, 8000);
+
+        assert!(prompt
+            .contains("1. mn-ctx001: Open Issue\n   To claim, run: manna-core claim mn-ctx001\n"));
+        // Only ready issues get a hint
+        assert_eq!(prompt.matches("To claim, run:").count(), 1);
+        assert!(prompt.contains("- mn-ctx003: Blocked Issue [blocked by: mn-ctx001]"));
+
+        let tight = build_agent_prompt(&issues, &issues, 35);
+        assert!(tight.contains("To claim, run: manna-core claim mn-ctx001"));
+        assert!(tight.contains("more not shown]"));
+    }
+}
@@ -0,0 +1,165 @@
+//! Near-duplicate detection by title similarity.
+//!
+//! Titles are normalized to lowercase alphanumeric tokens and compared with
+//! Jaccard similarity. A token index limits comparisons to pairs sharing at
+//! least one token, which is exact for any threshold above zero.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use crate::issue::Issue;
+
+/// A group of issues whose titles are similar above a threshold.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateCluster {
+    /// Issue IDs in the cluster, in store order
+    pub issue_ids: Vec<String>,
+
+    /// Highest pairwise similarity within the cluster
+    pub max_similarity: f64,
+}
+
+/// Split a title into a set of lowercase alphanumeric tokens.
+pub fn title_tokens(title: &str) -> BTreeSet<String> {
+    title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_lowercase())
+        .collect()
+}
+
+/// Jaccard similarity of two token sets (0.0 - 1.0).
+pub fn jaccard(a: &BTreeSet<String>, b: &BTreeSet<String>) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 0.0;
+    }
+    let shared = a.intersection(b).count();
+    let total = a.union(b).count();
+    shared as f64 / total as f64
+}
+
+/// Find clusters of issues whose titles are at least `threshold` similar.
+///
+/// Pairs above the threshold are joined transitively, so a cluster may hold
+/// issues that are each similar to a third but not to each other.
+pub fn find_duplicate_clusters(issues: &[Issue], threshold: f64) -> Vec<DuplicateCluster> {
+    let tokens: Vec<BTreeSet<String>> = issues.iter().map(|i| title_tokens(&i.title)).collect();
+
+    // Token index: only pairs sharing a token can score above zero
+    let mut index: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (idx, set) in tokens.iter().enumerate() {
+        for token in set {
+            index.entry(token.as_str()).or_default().push(idx);
+        }
+    }
+
+    let mut candidates: HashSet<(usize, usize)> = HashSet::new();
+    for members in index.values() {
+        for (pos, &a) in members.iter().enumerate() {
+            for &b in &members[pos + 1..] {
+                candidates.insert((a, b));
+            }
+        }
+    }
+
+    // Union-find over pairs above the threshold
+    let mut parent: Vec<usize> = (0..issues.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    let mut best: HashMap<usize, f64> = HashMap::new();
+    let mut pairs: Vec<(usize, usize, f64)> = candidates
+        .into_iter()
+        .map(|(a, b)| (a, b, jaccard(&tokens[a], &tokens[b])))
+        .filter(|(_, _, score)| *score >= threshold)
+        .collect();
+    pairs.sort_by_key(|&(a, b, _)| (a, b));
+
+    for &(a, b, _) in &pairs {
+        let (ra, rb) = (root(&mut parent, a), root(&mut parent, b));
+        if ra != rb {
+            parent[rb.max(ra)] = ra.min(rb);
+        }
+    }
+    for &(a, _, score) in &pairs {
+        let r = root(&mut parent, a);
+        let entry = best.entry(r).or_insert(0.0);
+        if score > *entry {
+            *entry = score;
+        }
+    }
+
+    // Roots are the lowest index in each set, so BTreeMap order is store order
+    let mut groups: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+    for (idx, issue) in issues.iter().enumerate() {
+        let r = root(&mut parent, idx);
+        if best.contains_key(&r) {
+            groups.entry(r).or_default().push(issue.id.clone());
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(r, issue_ids)| DuplicateCluster {
+            issue_ids,
+            max_similarity: best[&r],
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issue(id: &str, title: &str) -> Issue {
+        Issue::new(id.to_string(), title.to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_title_tokens_normalize() {
+        let tokens = title_tokens("Fix  LOGIN-bug, now!");
+        let expected: BTreeSet<String> = ["fix", "login", "bug", "now"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(tokens, expected);
+    }
+
+    #[test]
+    fn test_jaccard() {
+        let a = title_tokens("fix login bug");
+        let b = title_tokens("fix login crash");
+        assert!((jaccard(&a, &b) - 0.5).abs() < 1e-9);
+        assert!((jaccard(&a, &a) - 1.0).abs() < 1e-9);
+        assert_eq!(jaccard(&BTreeSet::new(), &BTreeSet::new()), 0.0);
+    }
+
+    #[test]
+    fn test_near_identical_titles_cluster() {
+        let issues = vec![
+            issue("mn-dup001", "Fix login bug on SSO page"),
+            issue("mn-dup002", "Write release notes"),
+            issue("mn-dup003", "fix the login bug on sso page"),
+        ];
+
+        let clusters = find_duplicate_clusters(&issues, 0.7);
+
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].issue_ids, vec!["mn-dup001", "mn-dup003"]);
+        assert!(clusters[0].max_similarity >= 0.7);
+    }
+
+    #[test]
+    fn test_unrelated_titles_do_not_cluster() {
+        let issues = vec![
+            issue("mn-dup004", "Add metrics endpoint"),
+            issue("mn-dup005", "Refactor storage layer"),
+        ];
+
+        assert!(find_duplicate_clusters(&issues, 0.5).is_empty());
+    }
+}
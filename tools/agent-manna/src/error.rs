@@ -1,7 +1,10 @@
 //! Error types for Manna using thiserror.
 
+use serde::Serialize;
 use thiserror::Error;
 
+use crate::issue::IssueStatus;
+
 /// Errors that can occur in Manna operations.
 #[derive(Error, Debug)]
 pub enum MannaError {
@@ -17,8 +20,15 @@ pub enum MannaError {
     #[error("Issue already exists: {0}")]
     IssueAlreadyExists(String),
 
-    #[error("Invalid status transition: {from} -> {to}")]
-    InvalidStatusTransition { from: String, to: String },
+    #[error("Invalid status transition: {from} -> {to} ({reason})")]
+    InvalidStatusTransition {
+        from: IssueStatus,
+        to: IssueStatus,
+        reason: TransitionReason,
+    },
+
+    #[error("Issue {id} is claimed by {holder}, which is still active (use --force-anyway to take it over)")]
+    ClaimHeld { id: String, holder: String },
 
     #[error("Storage not initialized")]
     NotInitialized,
@@ -28,6 +38,37 @@ pub enum MannaError {
 
     #[error("Invalid ID format: {0}")]
     InvalidId(String),
+
+    #[error("MessagePack error: {0}")]
+    MsgPack(String),
+
+    #[error("Invalid config.toml: {0}")]
+    Config(String),
+}
+
+/// Why a status transition was refused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransitionReason {
+    /// No transition leads from the current status to the target
+    WrongStatus,
+    /// A claim was requested but the issue is already claimed
+    AlreadyClaimed,
+    /// A release was requested but the issue is not claimed
+    NotClaimed,
+    /// The issue is in the trash
+    Trashed,
+}
+
+impl std::fmt::Display for TransitionReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransitionReason::WrongStatus => write!(f, "not allowed from this status"),
+            TransitionReason::AlreadyClaimed => write!(f, "issue is already claimed"),
+            TransitionReason::NotClaimed => write!(f, "issue is not claimed"),
+            TransitionReason::Trashed => write!(f, "issue is in the trash"),
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, MannaError>;
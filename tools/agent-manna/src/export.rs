@@ -0,0 +1,100 @@
+//! Bulk export and import encodings for issue sets.
+//!
+//! Supported encodings:
+//! - JSONL - one issue per line, identical to `.manna/issues.jsonl`
+//! - MessagePack - the full issue array as a single binary document
+
+use std::io::Write;
+
+use crate::error::{MannaError, Result};
+use crate::issue::Issue;
+
+/// Encode issues as JSONL, one compact JSON object per line.
+pub fn to_jsonl(issues: &[Issue]) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    for issue in issues {
+        serde_json::to_writer(&mut buf, issue)?;
+        writeln!(buf)?;
+    }
+    Ok(buf)
+}
+
+/// Decode issues from JSONL, skipping blank lines.
+///
+/// Unlike `MannaStore::load_issues`, malformed lines are an error: an
+/// import should not silently drop records.
+pub fn from_jsonl(bytes: &[u8]) -> Result<Vec<Issue>> {
+    let mut issues = Vec::new();
+    for line in bytes.split(|b| *b == b'\n') {
+        if line.iter().all(|b| b.is_ascii_whitespace()) {
+            continue;
+        }
+        issues.push(serde_json::from_slice(line)?);
+    }
+    Ok(issues)
+}
+
+/// Encode issues as a MessagePack array.
+///
+/// Fields are written by name so optional fields skipped during
+/// serialization do not shift positional decoding.
+pub fn to_msgpack(issues: &[Issue]) -> Result<Vec<u8>> {
+    rmp_serde::to_vec_named(issues).map_err(|e| MannaError::MsgPack(e.to_string()))
+}
+
+/// Decode issues from a MessagePack array.
+pub fn from_msgpack(bytes: &[u8]) -> Result<Vec<Issue>> {
+    rmp_serde::from_slice(bytes).map_err(|e| MannaError::MsgPack(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::issue::SessionId;
+
+    fn sample_issues() -> Vec<Issue> {
+        let plain = Issue::new("mn-aaa111".to_string(), "Plain".to_string()).unwrap();
+
+        let mut described = Issue::new("mn-bbb222".to_string(), "Described".to_string()).unwrap();
+        described.description = Some("Details".to_string());
+        described.add_blocker("mn-aaa111".to_string());
+
+        let mut claimed = Issue::new("mn-ccc333".to_string(), "Claimed".to_string()).unwrap();
+        claimed.claim(SessionId::new("ses_123").unwrap()).unwrap();
+
+        vec![plain, described, claimed]
+    }
+
+    #[test]
+    fn test_msgpack_round_trip() {
+        let issues = sample_issues();
+
+        let bytes = to_msgpack(&issues).unwrap();
+        let decoded = from_msgpack(&bytes).unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&issues).unwrap(),
+            serde_json::to_value(&decoded).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_msgpack_rejects_garbage() {
+        let result = from_msgpack(b"not msgpack");
+        assert!(matches!(result, Err(MannaError::MsgPack(_))));
+    }
+
+    #[test]
+    fn test_jsonl_round_trip() {
+        let issues = sample_issues();
+
+        let bytes = to_jsonl(&issues).unwrap();
+        assert_eq!(bytes.iter().filter(|b| **b == b'\n').count(), 3);
+
+        let decoded = from_jsonl(&bytes).unwrap();
+        assert_eq!(
+            serde_json::to_value(&issues).unwrap(),
+            serde_json::to_value(&decoded).unwrap()
+        );
+    }
+}
@@ -0,0 +1,309 @@
+//! Blocker dependency graph.
+//!
+//! Edges point from an issue to each issue in its `blocked_by` list, so a
+//! path from `a` to `b` means `a` (transitively) waits on `b`.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::issue::Issue;
+
+/// Directed graph of blocker edges between issue IDs.
+#[derive(Debug, Clone, Default)]
+pub struct BlockerGraph {
+    edges: HashMap<String, Vec<String>>,
+}
+
+impl BlockerGraph {
+    /// Build the graph from every issue's `blocked_by` list.
+    pub fn new(issues: &[Issue]) -> Self {
+        let edges = issues
+            .iter()
+            .map(|i| (i.id.clone(), i.blocked_by.clone()))
+            .collect();
+        BlockerGraph { edges }
+    }
+
+    /// Direct blockers of `id` (empty for unknown IDs).
+    pub fn blockers(&self, id: &str) -> &[String] {
+        self.edges.get(id).map_or(&[], |b| b.as_slice())
+    }
+
+    /// Shortest blocker path from `from` to `to`, both ends included.
+    ///
+    /// Returns `None` if `to` is not reachable. A path always has at least
+    /// one edge, so `path(a, a)` is only found through a cycle.
+    pub fn path(&self, from: &str, to: &str) -> Option<Vec<String>> {
+        let mut previous: HashMap<&str, &str> = HashMap::new();
+        let mut seen: HashSet<&str> = HashSet::new();
+        let mut queue: VecDeque<&str> = VecDeque::new();
+        queue.push_back(from);
+
+        while let Some(current) = queue.pop_front() {
+            for next in self.blockers(current) {
+                let next = next.as_str();
+                if !seen.insert(next) {
+                    continue;
+                }
+                previous.insert(next, current);
+
+                if next == to {
+                    // Walk back to `from`, which has no predecessor entry
+                    // of its own unless it is also `to`
+                    let mut path = vec![to.to_string()];
+                    let mut step = current;
+                    loop {
+                        path.push(step.to_string());
+                        if step == from {
+                            break;
+                        }
+                        step = previous[step];
+                    }
+                    path.reverse();
+                    return Some(path);
+                }
+                queue.push_back(next);
+            }
+        }
+
+        None
+    }
+
+    /// Transitive blockers of `id` up to `max_depth` edges away, each with
+    /// the depth it was first reached at (direct blockers are depth 1).
+    ///
+    /// Breadth-first, so every ID appears once at its shallowest depth;
+    /// `id` itself is never included, even through a cycle.
+    pub fn blockers_within(&self, id: &str, max_depth: usize) -> Vec<(String, usize)> {
+        let mut seen: HashSet<&str> = HashSet::from([id]);
+        let mut found = Vec::new();
+        let mut frontier = vec![id];
+
+        for depth in 1..=max_depth {
+            let mut next_frontier = Vec::new();
+            for current in frontier {
+                for next in self.blockers(current) {
+                    if seen.insert(next.as_str()) {
+                        found.push((next.clone(), depth));
+                        next_frontier.push(next.as_str());
+                    }
+                }
+            }
+            if next_frontier.is_empty() {
+                break;
+            }
+            frontier = next_frontier;
+        }
+
+        found
+    }
+
+    /// Whether `from` transitively waits on `to`.
+    pub fn reaches(&self, from: &str, to: &str) -> bool {
+        self.path(from, to).is_some()
+    }
+
+    /// Number of issues that list each ID as a direct blocker.
+    ///
+    /// This is the out-degree in the reverse graph; IDs nothing waits on
+    /// are absent.
+    pub fn dependent_counts(&self) -> HashMap<&str, usize> {
+        let mut counts = HashMap::new();
+        for blockers in self.edges.values() {
+            for blocker in blockers {
+                *counts.entry(blocker.as_str()).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+
+    /// Every blocker cycle, one per strongly-connected component of two or
+    /// more issues.
+    ///
+    /// Each cycle starts and ends at the component's smallest ID and follows
+    /// the shortest loop through it, so `[a, b, a]` means `a` waits on `b`
+    /// and `b` waits on `a`. Cycles are ordered by their starting ID.
+    pub fn cycles(&self) -> Vec<Vec<String>> {
+        let mut nodes: Vec<&str> = self.edges.keys().map(|k| k.as_str()).collect();
+        nodes.sort_unstable();
+
+        let mut tarjan = Tarjan::default();
+        for node in nodes {
+            if !tarjan.index.contains_key(node) {
+                tarjan.visit(self, node);
+            }
+        }
+
+        let mut cycles: Vec<Vec<String>> = tarjan
+            .components
+            .into_iter()
+            .filter(|c| c.len() > 1)
+            .filter_map(|c| {
+                let start = c.iter().min()?;
+                self.path(start, start)
+            })
+            .collect();
+        cycles.sort();
+        cycles
+    }
+}
+
+/// Working state for Tarjan's strongly-connected components algorithm.
+#[derive(Default)]
+struct Tarjan<'a> {
+    next_index: usize,
+    index: HashMap<&'a str, usize>,
+    low_link: HashMap<&'a str, usize>,
+    stack: Vec<&'a str>,
+    on_stack: HashSet<&'a str>,
+    components: Vec<Vec<&'a str>>,
+}
+
+impl<'a> Tarjan<'a> {
+    fn visit(&mut self, graph: &'a BlockerGraph, node: &'a str) {
+        self.index.insert(node, self.next_index);
+        self.low_link.insert(node, self.next_index);
+        self.next_index += 1;
+        self.stack.push(node);
+        self.on_stack.insert(node);
+
+        for next in graph.blockers(node) {
+            let next = next.as_str();
+            if !self.index.contains_key(next) {
+                self.visit(graph, next);
+                let low = self.low_link[node].min(self.low_link[next]);
+                self.low_link.insert(node, low);
+            } else if self.on_stack.contains(next) {
+                let low = self.low_link[node].min(self.index[next]);
+                self.low_link.insert(node, low);
+            }
+        }
+
+        // Root of a component: pop everything above it off the stack
+        if self.low_link[node] == self.index[node] {
+            let mut component = Vec::new();
+            while let Some(member) = self.stack.pop() {
+                self.on_stack.remove(member);
+                component.push(member);
+                if member == node {
+                    break;
+                }
+            }
+            self.components.push(component);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issue(id: &str, blockers: &[&str]) -> Issue {
+        let mut issue = Issue::new(id.to_string(), "Issue".to_string()).unwrap();
+        issue.blocked_by = blockers.iter().map(|b| b.to_string()).collect();
+        issue
+    }
+
+    #[test]
+    fn test_path_follows_transitive_blockers() {
+        // c waits on b, b waits on a
+        let graph = BlockerGraph::new(&[
+            issue("mn-00000a", &[]),
+            issue("mn-00000b", &["mn-00000a"]),
+            issue("mn-00000c", &["mn-00000b"]),
+        ]);
+
+        assert_eq!(
+            graph.path("mn-00000c", "mn-00000a").unwrap(),
+            vec!["mn-00000c", "mn-00000b", "mn-00000a"]
+        );
+        assert!(graph.reaches("mn-00000c", "mn-00000b"));
+        assert!(!graph.reaches("mn-00000a", "mn-00000c"));
+        assert!(!graph.reaches("mn-00000a", "mn-00000a"));
+    }
+
+    #[test]
+    fn test_blockers_within_depth() {
+        // c waits on b and a, b waits on a, a waits on c (cycle)
+        let graph = BlockerGraph::new(&[
+            issue("mn-00000a", &["mn-00000c"]),
+            issue("mn-00000b", &["mn-00000a"]),
+            issue("mn-00000c", &["mn-00000b", "mn-00000a"]),
+            issue("mn-00000d", &["mn-00000c"]),
+        ]);
+
+        assert_eq!(
+            graph.blockers_within("mn-00000d", 2),
+            vec![
+                ("mn-00000c".to_string(), 1),
+                ("mn-00000b".to_string(), 2),
+                ("mn-00000a".to_string(), 2),
+            ]
+        );
+        assert_eq!(graph.blockers_within("mn-00000d", 1).len(), 1);
+        // The cycle back to c does not list c as its own blocker
+        assert!(graph
+            .blockers_within("mn-00000c", 5)
+            .iter()
+            .all(|(id, _)| id != "mn-00000c"));
+    }
+
+    #[test]
+    fn test_path_to_self_through_cycle() {
+        let graph = BlockerGraph::new(&[
+            issue("mn-00000a", &["mn-00000b"]),
+            issue("mn-00000b", &["mn-00000a"]),
+        ]);
+
+        assert_eq!(
+            graph.path("mn-00000a", "mn-00000a").unwrap(),
+            vec!["mn-00000a", "mn-00000b", "mn-00000a"]
+        );
+    }
+
+    #[test]
+    fn test_cycles_reports_each_independent_loop() {
+        let graph = BlockerGraph::new(&[
+            // a -> b -> c -> a
+            issue("mn-00000a", &["mn-00000b"]),
+            issue("mn-00000b", &["mn-00000c"]),
+            issue("mn-00000c", &["mn-00000a"]),
+            // d <-> e
+            issue("mn-00000d", &["mn-00000e"]),
+            issue("mn-00000e", &["mn-00000d"]),
+            // f waits on the first loop but is not part of it
+            issue("mn-00000f", &["mn-00000a"]),
+        ]);
+
+        assert_eq!(
+            graph.cycles(),
+            vec![
+                vec!["mn-00000a", "mn-00000b", "mn-00000c", "mn-00000a"],
+                vec!["mn-00000d", "mn-00000e", "mn-00000d"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dependent_counts() {
+        let graph = BlockerGraph::new(&[
+            issue("mn-00000a", &[]),
+            issue("mn-00000b", &["mn-00000a"]),
+            issue("mn-00000c", &["mn-00000a", "mn-00000b"]),
+        ]);
+
+        let counts = graph.dependent_counts();
+        assert_eq!(counts["mn-00000a"], 2);
+        assert_eq!(counts["mn-00000b"], 1);
+        assert!(!counts.contains_key("mn-00000c"));
+    }
+
+    #[test]
+    fn test_cycles_empty_for_acyclic_graph() {
+        let graph = BlockerGraph::new(&[
+            issue("mn-00000a", &[]),
+            issue("mn-00000b", &["mn-00000a", "mn-0000ff"]),
+        ]);
+
+        assert!(graph.cycles().is_empty());
+    }
+}
@@ -2,23 +2,77 @@ use sha2::{Digest, Sha256};
 use std::collections::HashSet;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use crate::error::{MannaError, Result};
+
+/// Shortest hex suffix a valid ID may have, and the default generated length.
+pub const MIN_ID_LENGTH: usize = 6;
+
+/// Longest hex suffix: the full SHA-256 digest.
+pub const MAX_ID_LENGTH: usize = 64;
+
+/// Components of a parsed issue ID
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdParts {
+    /// Prefix before the dash (always `mn`)
+    pub prefix: String,
+
+    /// Lowercase hex suffix
+    pub hex: String,
+
+    /// Number of hex characters (at least `MIN_ID_LENGTH`)
+    pub length: usize,
+}
+
+/// Parse an ID of the form `mn-[a-f0-9]{6,}` into its parts
+///
+/// Returns `MannaError::InvalidId` if the prefix is wrong, the suffix is
+/// shorter than `MIN_ID_LENGTH` characters, or it contains anything but
+/// lowercase hex. The minimum does not follow a configured `id_length`, so
+/// IDs generated before the length was raised stay valid.
+pub fn parse(id: &str) -> Result<IdParts> {
+    let hex = id
+        .strip_prefix("mn-")
+        .ok_or_else(|| MannaError::InvalidId(format!("{} (must start with 'mn-')", id)))?;
+
+    if hex.len() < MIN_ID_LENGTH {
+        return Err(MannaError::InvalidId(format!(
+            "{} (need at least {} hex characters)",
+            id, MIN_ID_LENGTH
+        )));
+    }
+
+    if !hex.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f')) {
+        return Err(MannaError::InvalidId(format!(
+            "{} (suffix must be lowercase hex)",
+            id
+        )));
+    }
+
+    Ok(IdParts {
+        prefix: "mn".to_string(),
+        hex: hex.to_string(),
+        length: hex.len(),
+    })
+}
+
 /// Generate a hash-based ID with format `mn-[a-f0-9]{6,}`
 ///
 /// Uses 16 random bytes + current timestamp, hashed with SHA256.
-/// Takes first 6 hex characters of the hash.
-pub fn generate_id() -> String {
-    generate_id_with_seed(None)
+/// Takes the first `length` hex characters of the hash, clamped to
+/// `MIN_ID_LENGTH..=MAX_ID_LENGTH`.
+pub fn generate_id(length: usize) -> String {
+    generate_id_with_seed(None, length)
 }
 
 /// Generate a unique ID, extending length on collision
 ///
-/// Starts with 6 hex characters. If collision detected in `existing_ids`,
-/// extends to 7, 8, 9... characters until unique.
-pub fn generate_unique_id(existing_ids: &HashSet<String>) -> String {
-    let mut id = generate_id();
-    let mut length = 6;
+/// Starts with `length` hex characters. If collision detected in
+/// `existing_ids`, extends one character at a time until unique.
+pub fn generate_unique_id(existing_ids: &HashSet<String>, length: usize) -> String {
+    let mut length = length.clamp(MIN_ID_LENGTH, MAX_ID_LENGTH);
+    let mut id = generate_id(length);
 
-    while existing_ids.contains(&id) && length < 64 {
+    while existing_ids.contains(&id) && length < MAX_ID_LENGTH {
         length += 1;
         id = generate_id_extended(length);
     }
@@ -27,7 +81,7 @@ pub fn generate_unique_id(existing_ids: &HashSet<String>) -> String {
 }
 
 /// Internal: Generate ID with optional seed for deterministic testing
-fn generate_id_with_seed(seed: Option<u64>) -> String {
+fn generate_id_with_seed(seed: Option<u64>, length: usize) -> String {
     let mut data = Vec::new();
 
     // Add random bytes
@@ -53,9 +107,8 @@ fn generate_id_with_seed(seed: Option<u64>) -> String {
     hasher.update(&data);
     let hash = hasher.finalize();
 
-    // Take first 6 hex characters
     let hex = format!("{:x}", hash);
-    format!("mn-{}", &hex[..6])
+    format!("mn-{}", &hex[..length.clamp(MIN_ID_LENGTH, MAX_ID_LENGTH)])
 }
 
 /// Internal: Generate ID with specific hex length
@@ -75,7 +128,7 @@ fn generate_id_extended(length: usize) -> String {
     let hash = hasher.finalize();
 
     let hex = format!("{:x}", hash);
-    let take_len = length.min(hex.len());
+    let take_len = length.clamp(MIN_ID_LENGTH, MAX_ID_LENGTH);
     format!("mn-{}", &hex[..take_len])
 }
 
@@ -93,7 +146,7 @@ mod tests {
 
     #[test]
     fn test_generate_id_format() {
-        let id = generate_id();
+        let id = generate_id(MIN_ID_LENGTH);
         assert!(id.starts_with("mn-"));
         assert_eq!(id.len(), 9); // "mn-" + 6 hex chars
 
@@ -102,17 +155,51 @@ mod tests {
         assert!(hex_part.chars().all(|c| c.is_ascii_hexdigit()));
     }
 
+    #[test]
+    fn test_parse_valid_ids() {
+        let parts = parse("mn-a1b2c3").unwrap();
+        assert_eq!(parts.prefix, "mn");
+        assert_eq!(parts.hex, "a1b2c3");
+        assert_eq!(parts.length, 6);
+
+        let extended = parse("mn-f4e5d6c").unwrap();
+        assert_eq!(extended.hex, "f4e5d6c");
+        assert_eq!(extended.length, 7);
+
+        let generated = generate_id(MIN_ID_LENGTH);
+        assert_eq!(parse(&generated).unwrap().length, 6);
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed() {
+        for bad in [
+            "",
+            "mn-",
+            "mn-abc",
+            "xx-a1b2c3",
+            "mn-A1B2C3",
+            "mn-a1b2cz",
+            "a1b2c3",
+        ] {
+            assert!(
+                matches!(parse(bad), Err(MannaError::InvalidId(_))),
+                "{} should be rejected",
+                bad
+            );
+        }
+    }
+
     #[test]
     fn test_generate_id_randomness() {
-        let id1 = generate_id();
-        let id2 = generate_id();
+        let id1 = generate_id(MIN_ID_LENGTH);
+        let id2 = generate_id(MIN_ID_LENGTH);
         assert_ne!(id1, id2, "Generated IDs should be different");
     }
 
     #[test]
     fn test_generate_unique_id_no_collision() {
         let existing = HashSet::new();
-        let id = generate_unique_id(&existing);
+        let id = generate_unique_id(&existing, MIN_ID_LENGTH);
         assert!(id.starts_with("mn-"));
         assert_eq!(id.len(), 9);
     }
@@ -125,7 +212,7 @@ mod tests {
         existing.insert("mn-abcdef".to_string());
 
         // Generate unique should extend if collision
-        let id = generate_unique_id(&existing);
+        let id = generate_unique_id(&existing, MIN_ID_LENGTH);
         assert!(id.starts_with("mn-"));
         // Should be longer than 9 if collision handling worked
         // (though with random generation, collision is unlikely)
@@ -136,7 +223,7 @@ mod tests {
         let mut ids = HashSet::new();
 
         for _ in 0..1000 {
-            let id = generate_id();
+            let id = generate_id(MIN_ID_LENGTH);
             assert!(
                 ids.insert(id.clone()),
                 "Collision detected: {} already exists",
@@ -147,6 +234,20 @@ mod tests {
         assert_eq!(ids.len(), 1000, "All 1000 IDs should be unique");
     }
 
+    #[test]
+    fn test_generate_ids_at_configured_length() {
+        let mut ids = HashSet::new();
+        for _ in 0..500 {
+            let id = generate_unique_id(&ids, 10);
+            let parts = parse(&id).unwrap();
+            assert_eq!(parts.length, 10);
+            assert!(ids.insert(id));
+        }
+
+        // Lengths below the minimum are raised to it
+        assert_eq!(parse(&generate_id(3)).unwrap().length, MIN_ID_LENGTH);
+    }
+
     #[test]
     fn test_unique_id_extends_on_collision() {
         let mut existing = HashSet::new();
@@ -156,7 +257,7 @@ mod tests {
             existing.insert(format!("mn-{:06x}", i));
         }
 
-        let id = generate_unique_id(&existing);
+        let id = generate_unique_id(&existing, MIN_ID_LENGTH);
         assert!(id.starts_with("mn-"));
         // Should still be unique
         assert!(!existing.contains(&id));
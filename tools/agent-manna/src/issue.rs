@@ -3,22 +3,19 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::error::{MannaError, TransitionReason};
+
 /// Issue status enum matching SCHEMA.md
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum IssueStatus {
+    #[default]
     Open,
     InProgress,
     Blocked,
     Done,
 }
 
-impl Default for IssueStatus {
-    fn default() -> Self {
-        IssueStatus::Open
-    }
-}
-
 impl std::fmt::Display for IssueStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -30,6 +27,31 @@ impl std::fmt::Display for IssueStatus {
     }
 }
 
+/// A reference to a file produced for or about an issue.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Attachment {
+    /// Path to the file, as given at attach time
+    pub path: String,
+
+    /// Optional note about what the file is
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// When it was attached
+    pub at: DateTime<Utc>,
+}
+
+/// How `Issue::merge_from` resolves conflicting scalar fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// Keep this issue's values
+    PreferSelf,
+    /// Take the other issue's values
+    PreferOther,
+    /// Take the values of whichever issue was updated last (ties keep self)
+    PreferNewer,
+}
+
 /// An issue in Manna.
 ///
 /// See SCHEMA.md for field definitions.
@@ -65,9 +87,68 @@ pub struct Issue {
     /// When it was claimed
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub claimed_at: Option<DateTime<Utc>>,
+
+    /// Files attached to this issue
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Attachment>,
+
+    /// Parent issue, if this issue is part of a larger one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+
+    /// Who the issue is assigned to (independent of who has claimed it)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assignee: Option<String>,
+
+    /// When the issue should be done by
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_at: Option<DateTime<Utc>>,
+
+    /// Manual queue position; lower ranks come first
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rank: Option<f64>,
+
+    /// When the issue was moved to the trash; trashed issues are hidden
+    /// from normal views until restored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 impl Issue {
+    /// Every serialized field name, in declaration order.
+    pub const FIELDS: [&'static str; 15] = [
+        "id",
+        "title",
+        "status",
+        "description",
+        "created_at",
+        "updated_at",
+        "blocked_by",
+        "claimed_by",
+        "claimed_at",
+        "attachments",
+        "parent_id",
+        "assignee",
+        "due_at",
+        "rank",
+        "deleted_at",
+    ];
+
+    /// Longest allowed title, in bytes.
+    pub const MAX_TITLE_LEN: usize = 500;
+
+    /// Fields omitted from serialized output when unset.
+    pub const OPTIONAL_FIELDS: [&'static str; 8] = [
+        "description",
+        "claimed_by",
+        "claimed_at",
+        "parent_id",
+        "assignee",
+        "due_at",
+        "rank",
+        "deleted_at",
+    ];
+
     /// Create a new issue with the given ID and title.
     ///
     /// # Arguments
@@ -77,9 +158,10 @@ impl Issue {
     /// # Returns
     /// Result with new Issue or validation error
     pub fn new(id: String, title: String) -> Result<Self, String> {
-        if title.is_empty() || title.len() > 500 {
+        if title.is_empty() || title.len() > Self::MAX_TITLE_LEN {
             return Err(format!(
-                "Title must be 1-500 characters, got {}",
+                "Title must be 1-{} characters, got {}",
+                Self::MAX_TITLE_LEN,
                 title.len()
             ));
         }
@@ -95,30 +177,44 @@ impl Issue {
             blocked_by: Vec::new(),
             claimed_by: None,
             claimed_at: None,
+            attachments: Vec::new(),
+            parent_id: None,
+            assignee: None,
+            due_at: None,
+            rank: None,
+            deleted_at: None,
         })
     }
 
+    /// Normalize a title: trim the ends and collapse internal whitespace
+    /// runs to a single space.
+    pub fn normalize_title(title: &str) -> String {
+        title.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
     /// Claim this issue for a session
     ///
     /// # Arguments
     /// * `session_id` - Session identifier claiming the issue
     ///
     /// # Returns
-    /// Result indicating success or error if already claimed
-    pub fn claim(&mut self, session_id: String) -> Result<(), String> {
+    /// Result indicating success or a transition error if not claimable
+    pub fn claim(&mut self, session_id: SessionId) -> Result<(), MannaError> {
+        let to = IssueStatus::InProgress;
+        if self.deleted_at.is_some() {
+            return Err(self.refused(to, TransitionReason::Trashed));
+        }
+
         if self.status != IssueStatus::Open {
-            return Err(format!(
-                "Cannot claim issue with status '{}', must be 'open'",
-                self.status
-            ));
+            return Err(self.refused(to, TransitionReason::WrongStatus));
         }
 
         if self.claimed_by.is_some() {
-            return Err("Issue is already claimed".to_string());
+            return Err(self.refused(to, TransitionReason::AlreadyClaimed));
         }
 
         let now = Utc::now();
-        self.claimed_by = Some(session_id);
+        self.claimed_by = Some(session_id.into());
         self.claimed_at = Some(now);
         self.status = IssueStatus::InProgress;
         self.updated_at = now;
@@ -129,17 +225,14 @@ impl Issue {
     /// Release (abandon) this issue
     ///
     /// # Returns
-    /// Result indicating success or error if not claimed
-    pub fn release(&mut self) -> Result<(), String> {
+    /// Result indicating success or a transition error if not claimed
+    pub fn release(&mut self) -> Result<(), MannaError> {
         if self.claimed_by.is_none() {
-            return Err("Issue is not claimed".to_string());
+            return Err(self.refused(IssueStatus::Open, TransitionReason::NotClaimed));
         }
 
         if self.status != IssueStatus::InProgress {
-            return Err(format!(
-                "Cannot release issue with status '{}', must be 'in_progress'",
-                self.status
-            ));
+            return Err(self.refused(IssueStatus::Open, TransitionReason::WrongStatus));
         }
 
         self.claimed_by = None;
@@ -153,13 +246,10 @@ impl Issue {
     /// Mark this issue as complete
     ///
     /// # Returns
-    /// Result indicating success or error if not in progress
-    pub fn complete(&mut self) -> Result<(), String> {
+    /// Result indicating success or a transition error if not in progress
+    pub fn complete(&mut self) -> Result<(), MannaError> {
         if self.status != IssueStatus::InProgress {
-            return Err(format!(
-                "Cannot complete issue with status '{}', must be 'in_progress'",
-                self.status
-            ));
+            return Err(self.refused(IssueStatus::Done, TransitionReason::WrongStatus));
         }
 
         self.status = IssueStatus::Done;
@@ -168,6 +258,90 @@ impl Issue {
         Ok(())
     }
 
+    /// Reopen a completed issue
+    ///
+    /// Clears any claim. The issue returns to `blocked` if blockers are
+    /// still recorded, otherwise to `open`.
+    ///
+    /// # Returns
+    /// Result indicating success or a transition error if not done
+    pub fn reopen(&mut self) -> Result<(), MannaError> {
+        if self.status != IssueStatus::Done {
+            return Err(self.refused(IssueStatus::Open, TransitionReason::WrongStatus));
+        }
+
+        self.claimed_by = None;
+        self.claimed_at = None;
+        self.status = IssueStatus::Open;
+        self.update_blocked_status();
+        self.updated_at = Utc::now();
+
+        Ok(())
+    }
+
+    /// Move this issue to `to` through the matching lifecycle transition
+    ///
+    /// Open to in_progress claims for `session_id`; in_progress to done
+    /// completes; in_progress to open releases; done to open reopens.
+    /// `blocked` is only reachable through blockers, so it is never a
+    /// valid target.
+    ///
+    /// # Returns
+    /// Result indicating success or a transition error if not legal
+    pub fn set_status(
+        &mut self,
+        to: IssueStatus,
+        session_id: &SessionId,
+    ) -> Result<(), MannaError> {
+        match (&self.status, &to) {
+            (IssueStatus::Open, IssueStatus::InProgress) => self.claim(session_id.clone()),
+            (IssueStatus::InProgress, IssueStatus::Done) => self.complete(),
+            (IssueStatus::InProgress, IssueStatus::Open) => self.release(),
+            (IssueStatus::Done, IssueStatus::Open) => self.reopen(),
+            _ => Err(self.refused(to, TransitionReason::WrongStatus)),
+        }
+    }
+
+    /// Build the error for a refused transition from the current status.
+    fn refused(&self, to: IssueStatus, reason: TransitionReason) -> MannaError {
+        MannaError::InvalidStatusTransition {
+            from: self.status.clone(),
+            to,
+            reason,
+        }
+    }
+
+    /// Move this issue to the trash
+    ///
+    /// # Returns
+    /// Result indicating success or error if already trashed
+    pub fn trash(&mut self) -> Result<(), String> {
+        if self.deleted_at.is_some() {
+            return Err(format!("Issue {} is already in the trash", self.id));
+        }
+
+        let now = Utc::now();
+        self.deleted_at = Some(now);
+        self.updated_at = now;
+
+        Ok(())
+    }
+
+    /// Take this issue back out of the trash
+    ///
+    /// # Returns
+    /// Result indicating success or error if not trashed
+    pub fn restore(&mut self) -> Result<(), String> {
+        if self.deleted_at.is_none() {
+            return Err(format!("Issue {} is not in the trash", self.id));
+        }
+
+        self.deleted_at = None;
+        self.updated_at = Utc::now();
+
+        Ok(())
+    }
+
     /// Add a blocker to this issue
     ///
     /// # Arguments
@@ -192,6 +366,46 @@ impl Issue {
         }
     }
 
+    /// Merge another issue's fields into this one
+    ///
+    /// Blockers and attachments (by path) are unioned. Title, description
+    /// and status are taken wholesale from one side per `strategy`; status
+    /// brings its claim along so the two stay consistent. A blocker on this
+    /// issue's own ID is dropped, and gaining blockers blocks an issue that
+    /// is not done. The ID and creation time never change.
+    ///
+    /// # Arguments
+    /// * `other` - Issue whose fields are merged in
+    /// * `strategy` - Which side wins scalar conflicts
+    pub fn merge_from(&mut self, other: &Issue, strategy: MergeStrategy) {
+        let take_other = match strategy {
+            MergeStrategy::PreferSelf => false,
+            MergeStrategy::PreferOther => true,
+            MergeStrategy::PreferNewer => other.updated_at > self.updated_at,
+        };
+        if take_other {
+            self.title = other.title.clone();
+            self.description = other.description.clone();
+            self.status = other.status.clone();
+            self.claimed_by = other.claimed_by.clone();
+            self.claimed_at = other.claimed_at;
+            self.updated_at = Utc::now();
+        }
+
+        for blocker_id in &other.blocked_by {
+            if blocker_id != &self.id {
+                self.add_blocker(blocker_id.clone());
+            }
+        }
+        for attachment in &other.attachments {
+            if !self.attachments.iter().any(|a| a.path == attachment.path) {
+                self.attachments.push(attachment.clone());
+                self.updated_at = Utc::now();
+            }
+        }
+        self.update_blocked_status();
+    }
+
     /// Update blocked status based on blocked_by list
     fn update_blocked_status(&mut self) {
         if !self.blocked_by.is_empty() && self.status != IssueStatus::Done {
@@ -205,11 +419,91 @@ impl Issue {
         }
     }
 
+    /// Attach a file reference to this issue
+    ///
+    /// # Arguments
+    /// * `path` - Path to the file
+    /// * `description` - Optional note about the file
+    ///
+    /// # Returns
+    /// Result indicating success or error if the path is already attached
+    pub fn attach(&mut self, path: String, description: Option<String>) -> Result<(), String> {
+        if self.attachments.iter().any(|a| a.path == path) {
+            return Err(format!("'{}' is already attached to {}", path, self.id));
+        }
+
+        let now = Utc::now();
+        self.attachments.push(Attachment {
+            path,
+            description,
+            at: now,
+        });
+        self.updated_at = now;
+
+        Ok(())
+    }
+
+    /// Remove a file reference from this issue
+    ///
+    /// # Returns
+    /// Result indicating success or error if the path is not attached
+    pub fn detach(&mut self, path: &str) -> Result<(), String> {
+        let pos = self
+            .attachments
+            .iter()
+            .position(|a| a.path == path)
+            .ok_or_else(|| format!("'{}' is not attached to {}", path, self.id))?;
+
+        self.attachments.remove(pos);
+        self.updated_at = Utc::now();
+
+        Ok(())
+    }
+
+    /// Check whether this issue can be worked on as-is
+    ///
+    /// Actionable means the issue is open or in progress and has no
+    /// recorded blockers.
+    pub fn is_actionable(&self) -> bool {
+        matches!(self.status, IssueStatus::Open | IssueStatus::InProgress)
+            && self.blocked_by.is_empty()
+    }
+
+    /// Check whether this issue is past its due date at `now`
+    ///
+    /// Done issues are never overdue.
+    pub fn is_overdue(&self, now: DateTime<Utc>) -> bool {
+        self.status != IssueStatus::Done && self.due_at.is_some_and(|due| due < now)
+    }
+
+    /// Check whether this issue is ready to be claimed
+    ///
+    /// Claimable means the issue is open, unclaimed, not trashed, and every
+    /// entry in `blocked_by` refers to a done issue in `all`. Blockers that
+    /// cannot be found in `all` count as unresolved.
+    ///
+    /// # Arguments
+    /// * `all` - Every issue in the store, used to resolve blockers
+    pub fn is_claimable(&self, all: &[Issue]) -> bool {
+        if self.status != IssueStatus::Open
+            || self.claimed_by.is_some()
+            || self.deleted_at.is_some()
+        {
+            return false;
+        }
+
+        self.blocked_by.iter().all(|blocker_id| {
+            all.iter()
+                .any(|i| &i.id == blocker_id && i.status == IssueStatus::Done)
+        })
+    }
+
     /// Validate issue data integrity
     pub fn validate(&self) -> Result<(), String> {
-        if self.title.is_empty() || self.title.len() > 500 {
+        if self.title.is_empty() || self.title.len() > Self::MAX_TITLE_LEN {
             return Err(format!(
-                "Title must be 1-500 characters, got {}",
+                "Title must be 1-{} characters, got {}",
+                Self::MAX_TITLE_LEN,
                 self.title.len()
             ));
         }
@@ -234,6 +528,71 @@ impl Issue {
     }
 }
 
+/// A session identifier, kept distinct from issue IDs at the type level.
+///
+/// Serializes as a bare string, so the wire format is unchanged. Passing an
+/// issue ID where a session is expected does not compile:
+///
+/// ```compile_fail
+/// use manna_core::issue::Issue;
+///
+/// let mut issue = Issue::new("mn-abc123".to_string(), "Test".to_string()).unwrap();
+/// issue.claim("mn-def456".to_string()).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SessionId(String);
+
+impl SessionId {
+    /// Create a session ID, rejecting empty values, whitespace and control
+    /// characters, and anything over 128 characters.
+    pub fn new(id: impl Into<String>) -> Result<Self, String> {
+        let id = id.into();
+        if id.is_empty() || id.len() > 128 {
+            return Err(format!(
+                "Session ID must be 1-128 characters, got {}",
+                id.len()
+            ));
+        }
+        if id.chars().any(|c| c.is_whitespace() || c.is_control()) {
+            return Err(format!(
+                "Session ID '{}' must not contain whitespace or control characters",
+                id.escape_debug()
+            ));
+        }
+        Ok(SessionId(id))
+    }
+
+    /// The session ID as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for SessionId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl PartialEq<str> for SessionId {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for SessionId {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl From<SessionId> for String {
+    fn from(id: SessionId) -> Self {
+        id.0
+    }
+}
+
 /// Session event types matching SCHEMA.md
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -243,6 +602,13 @@ pub enum SessionEventType {
     Release,
     Done,
     End,
+    Create,
+    Edit,
+    Block,
+    Unblock,
+    Reopen,
+    Note,
+    Delete,
 }
 
 impl std::fmt::Display for SessionEventType {
@@ -253,6 +619,13 @@ impl std::fmt::Display for SessionEventType {
             SessionEventType::Release => write!(f, "release"),
             SessionEventType::Done => write!(f, "done"),
             SessionEventType::End => write!(f, "end"),
+            SessionEventType::Create => write!(f, "create"),
+            SessionEventType::Edit => write!(f, "edit"),
+            SessionEventType::Block => write!(f, "block"),
+            SessionEventType::Unblock => write!(f, "unblock"),
+            SessionEventType::Reopen => write!(f, "reopen"),
+            SessionEventType::Note => write!(f, "note"),
+            SessionEventType::Delete => write!(f, "delete"),
         }
     }
 }
@@ -263,7 +636,7 @@ impl std::fmt::Display for SessionEventType {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionEvent {
     /// Session identifier
-    pub session_id: String,
+    pub session_id: SessionId,
 
     /// Event type
     pub event: SessionEventType,
@@ -271,18 +644,19 @@ pub struct SessionEvent {
     /// When event occurred
     pub timestamp: DateTime<Utc>,
 
-    /// Issue ID (required for claim, release, done events)
+    /// Issue ID (required for all events except start and end)
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub issue_id: Option<String>,
 
-    /// Context data (required for start, end events)
+    /// Context data (required for start and end events; a change summary
+    /// for mutation events)
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub context: Option<serde_json::Value>,
 }
 
 impl SessionEvent {
     /// Create a new session start event.
-    pub fn start(session_id: String, context: serde_json::Value) -> Self {
+    pub fn start(session_id: SessionId, context: serde_json::Value) -> Self {
         SessionEvent {
             session_id,
             event: SessionEventType::Start,
@@ -293,7 +667,7 @@ impl SessionEvent {
     }
 
     /// Create a new claim event.
-    pub fn claim(session_id: String, issue_id: String) -> Self {
+    pub fn claim(session_id: SessionId, issue_id: String) -> Self {
         SessionEvent {
             session_id,
             event: SessionEventType::Claim,
@@ -304,7 +678,7 @@ impl SessionEvent {
     }
 
     /// Create a new release event.
-    pub fn release(session_id: String, issue_id: String) -> Self {
+    pub fn release(session_id: SessionId, issue_id: String) -> Self {
         SessionEvent {
             session_id,
             event: SessionEventType::Release,
@@ -315,7 +689,7 @@ impl SessionEvent {
     }
 
     /// Create a new done event.
-    pub fn done(session_id: String, issue_id: String) -> Self {
+    pub fn done(session_id: SessionId, issue_id: String) -> Self {
         SessionEvent {
             session_id,
             event: SessionEventType::Done,
@@ -325,8 +699,19 @@ impl SessionEvent {
         }
     }
 
+    /// Create a new issue-deleted event, for an issue removed permanently.
+    pub fn delete(session_id: SessionId, issue_id: String) -> Self {
+        SessionEvent {
+            session_id,
+            event: SessionEventType::Delete,
+            timestamp: Utc::now(),
+            issue_id: Some(issue_id),
+            context: None,
+        }
+    }
+
     /// Create a new session end event.
-    pub fn end(session_id: String, context: serde_json::Value) -> Self {
+    pub fn end(session_id: SessionId, context: serde_json::Value) -> Self {
         SessionEvent {
             session_id,
             event: SessionEventType::End,
@@ -335,12 +720,239 @@ impl SessionEvent {
             context: Some(context),
         }
     }
+
+    /// Create a new issue-created event.
+    pub fn create(session_id: SessionId, issue_id: String, context: serde_json::Value) -> Self {
+        Self::mutation(session_id, SessionEventType::Create, issue_id, context)
+    }
+
+    /// Create a new issue-edited event.
+    pub fn edit(session_id: SessionId, issue_id: String, context: serde_json::Value) -> Self {
+        Self::mutation(session_id, SessionEventType::Edit, issue_id, context)
+    }
+
+    /// Create a new blocker-added event.
+    pub fn block(session_id: SessionId, issue_id: String, context: serde_json::Value) -> Self {
+        Self::mutation(session_id, SessionEventType::Block, issue_id, context)
+    }
+
+    /// Create a new blocker-removed event.
+    pub fn unblock(session_id: SessionId, issue_id: String, context: serde_json::Value) -> Self {
+        Self::mutation(session_id, SessionEventType::Unblock, issue_id, context)
+    }
+
+    /// Create a new issue-reopened event.
+    pub fn reopen(session_id: SessionId, issue_id: String, context: serde_json::Value) -> Self {
+        Self::mutation(session_id, SessionEventType::Reopen, issue_id, context)
+    }
+
+    /// Create a new free-text note event.
+    pub fn note(session_id: SessionId, issue_id: String, text: String) -> Self {
+        Self::mutation(
+            session_id,
+            SessionEventType::Note,
+            issue_id,
+            serde_json::json!({ "text": text }),
+        )
+    }
+
+    /// Build an issue mutation event carrying a change summary.
+    fn mutation(
+        session_id: SessionId,
+        event: SessionEventType,
+        issue_id: String,
+        context: serde_json::Value,
+    ) -> Self {
+        SessionEvent {
+            session_id,
+            event,
+            timestamp: Utc::now(),
+            issue_id: Some(issue_id),
+            context: Some(context),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_session_id_validation_and_round_trip() {
+        let id = SessionId::new("ses_pid42_1700000000").unwrap();
+        assert_eq!(id.as_str(), "ses_pid42_1700000000");
+
+        // Transparent: serializes as a bare string
+        let json = serde_json::to_string(&id).unwrap();
+        assert_eq!(json, "\"ses_pid42_1700000000\"");
+        let parsed: SessionId = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, id);
+
+        let event = SessionEvent::start(id.clone(), serde_json::json!({}));
+        let line = serde_json::to_string(&event).unwrap();
+        assert!(line.contains("\"session_id\":\"ses_pid42_1700000000\""));
+
+        assert!(SessionId::new("").is_err());
+        assert!(SessionId::new("has space").is_err());
+        assert!(SessionId::new("x".repeat(129)).is_err());
+    }
+
+    fn merge_pair() -> (Issue, Issue) {
+        let mut mine = Issue::new("mn-3f0001".to_string(), "Mine".to_string()).unwrap();
+        mine.add_blocker("mn-3f00aa".to_string());
+        mine.attach("a.log".to_string(), None).unwrap();
+        let mut theirs = Issue::new("mn-3f0002".to_string(), "Theirs".to_string()).unwrap();
+        theirs.description = Some("Their notes".to_string());
+        theirs.add_blocker("mn-3f00aa".to_string());
+        theirs.add_blocker("mn-3f00bb".to_string());
+        theirs.attach("a.log".to_string(), None).unwrap();
+        theirs.attach("b.log".to_string(), None).unwrap();
+        (mine, theirs)
+    }
+
+    #[test]
+    fn test_merge_from_unions_collections() {
+        let (mut mine, theirs) = merge_pair();
+        let created_at = mine.created_at;
+        mine.merge_from(&theirs, MergeStrategy::PreferSelf);
+
+        assert_eq!(mine.id, "mn-3f0001");
+        assert_eq!(mine.created_at, created_at);
+        assert_eq!(mine.blocked_by, vec!["mn-3f00aa", "mn-3f00bb"]);
+        let paths: Vec<&str> = mine.attachments.iter().map(|a| a.path.as_str()).collect();
+        assert_eq!(paths, vec!["a.log", "b.log"]);
+
+        // A blocker on our own ID is not carried over
+        let mut pointer = Issue::new("mn-3f0003".to_string(), "Pointer".to_string()).unwrap();
+        pointer.add_blocker(mine.id.clone());
+        let mut solo = Issue::new("mn-3f0001".to_string(), "Solo".to_string()).unwrap();
+        solo.merge_from(&pointer, MergeStrategy::PreferSelf);
+        assert!(solo.blocked_by.is_empty());
+        assert_eq!(solo.status, IssueStatus::Open);
+    }
+
+    #[test]
+    fn test_merge_from_scalar_strategies() {
+        let (mine, mut theirs) = merge_pair();
+        theirs.remove_blocker("mn-3f00aa");
+        theirs.remove_blocker("mn-3f00bb");
+        theirs.claim(SessionId::new("ses_them").unwrap()).unwrap();
+
+        let mut kept = mine.clone();
+        kept.merge_from(&theirs, MergeStrategy::PreferSelf);
+        assert_eq!(kept.title, "Mine");
+        assert_eq!(kept.description, None);
+        assert_eq!(kept.status, IssueStatus::Blocked);
+        assert_eq!(kept.claimed_by, None);
+
+        let mut taken = mine.clone();
+        taken.merge_from(&theirs, MergeStrategy::PreferOther);
+        assert_eq!(taken.title, "Theirs");
+        assert_eq!(taken.description.as_deref(), Some("Their notes"));
+        assert_eq!(taken.claimed_by.as_deref(), Some("ses_them"));
+        // Still carries our blocker, so the claimed issue is blocked
+        assert_eq!(taken.status, IssueStatus::Blocked);
+
+        let mut newer = mine.clone();
+        theirs.updated_at = mine.updated_at + chrono::Duration::seconds(1);
+        newer.merge_from(&theirs, MergeStrategy::PreferNewer);
+        assert_eq!(newer.title, "Theirs");
+
+        let mut older = mine.clone();
+        theirs.updated_at = mine.updated_at - chrono::Duration::seconds(1);
+        older.merge_from(&theirs, MergeStrategy::PreferNewer);
+        assert_eq!(older.title, "Mine");
+    }
+
+    #[test]
+    fn test_normalize_title() {
+        assert_eq!(
+            Issue::normalize_title("  Fix   login\t bug \n"),
+            "Fix login bug"
+        );
+        assert_eq!(Issue::normalize_title("Already clean"), "Already clean");
+        assert_eq!(Issue::normalize_title("   "), "");
+    }
+
+    #[test]
+    fn test_attachments_round_trip() {
+        let mut issue = Issue::new("mn-att001".to_string(), "Artifacts".to_string()).unwrap();
+        issue
+            .attach("logs/run.log".to_string(), Some("CI log".to_string()))
+            .unwrap();
+        issue.attach("out.diff".to_string(), None).unwrap();
+        assert!(issue.attach("out.diff".to_string(), None).is_err());
+
+        let json = serde_json::to_string(&issue).unwrap();
+        let parsed: Issue = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.attachments, issue.attachments);
+        assert_eq!(parsed.attachments[0].description.as_deref(), Some("CI log"));
+
+        issue.detach("logs/run.log").unwrap();
+        assert_eq!(issue.attachments.len(), 1);
+        assert!(issue.detach("logs/run.log").is_err());
+    }
+
+    #[test]
+    fn test_attachments_omitted_when_empty() {
+        let issue = Issue::new("mn-att002".to_string(), "Plain".to_string()).unwrap();
+        let json = serde_json::to_string(&issue).unwrap();
+        assert!(!json.contains("attachments"));
+
+        // Records written before attachments existed still load
+        let parsed: Issue = serde_json::from_str(&json).unwrap();
+        assert!(parsed.attachments.is_empty());
+    }
+
+    #[test]
+    fn test_set_status_routes_transitions() {
+        let mut issue = Issue::new("mn-set001".to_string(), "Cycle".to_string()).unwrap();
+        let session = SessionId::new("ses_a").unwrap();
+
+        issue.set_status(IssueStatus::InProgress, &session).unwrap();
+        assert_eq!(issue.claimed_by.as_deref(), Some("ses_a"));
+
+        issue.set_status(IssueStatus::Done, &session).unwrap();
+        assert_eq!(issue.status, IssueStatus::Done);
+
+        issue.set_status(IssueStatus::Open, &session).unwrap();
+        assert_eq!(issue.status, IssueStatus::Open);
+        assert!(issue.claimed_by.is_none());
+        assert!(issue.validate().is_ok());
+
+        assert!(issue.set_status(IssueStatus::Done, &session).is_err());
+        assert!(issue.set_status(IssueStatus::Blocked, &session).is_err());
+    }
+
+    #[test]
+    fn test_reopen_keeps_blockers() {
+        let mut issue = Issue::new("mn-set002".to_string(), "Blocked".to_string()).unwrap();
+        issue.claim(SessionId::new("ses").unwrap()).unwrap();
+        issue.complete().unwrap();
+        issue.blocked_by.push("mn-set003".to_string());
+
+        issue.reopen().unwrap();
+
+        assert_eq!(issue.status, IssueStatus::Blocked);
+        assert!(issue.reopen().is_err());
+    }
+
+    #[test]
+    fn test_trash_and_restore() {
+        let mut issue = Issue::new("mn-7a5001".to_string(), "Oops".to_string()).unwrap();
+
+        issue.trash().unwrap();
+        assert!(issue.deleted_at.is_some());
+        assert!(issue.trash().is_err());
+        assert!(!issue.is_claimable(std::slice::from_ref(&issue)));
+        assert!(issue.claim(SessionId::new("ses").unwrap()).is_err());
+
+        issue.restore().unwrap();
+        assert!(issue.deleted_at.is_none());
+        assert!(issue.restore().is_err());
+        assert!(issue.is_claimable(std::slice::from_ref(&issue)));
+    }
+
     #[test]
     fn test_new_issue_valid() {
         let issue = Issue::new("mn-abc123".to_string(), "Test issue".to_string()).unwrap();
@@ -371,7 +983,7 @@ mod tests {
     #[test]
     fn test_claim_issue() {
         let mut issue = Issue::new("mn-abc123".to_string(), "Test".to_string()).unwrap();
-        let result = issue.claim("ses_123".to_string());
+        let result = issue.claim(SessionId::new("ses_123").unwrap());
         assert!(result.is_ok());
         assert_eq!(issue.status, IssueStatus::InProgress);
         assert_eq!(issue.claimed_by, Some("ses_123".to_string()));
@@ -381,25 +993,34 @@ mod tests {
     #[test]
     fn test_claim_already_claimed() {
         let mut issue = Issue::new("mn-abc123".to_string(), "Test".to_string()).unwrap();
-        issue.claim("ses_123".to_string()).unwrap();
-        let result = issue.claim("ses_456".to_string());
+        issue.claim(SessionId::new("ses_123").unwrap()).unwrap();
+        let result = issue.claim(SessionId::new("ses_456").unwrap());
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("must be 'open'"));
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("in_progress -> in_progress"));
     }
 
     #[test]
     fn test_claim_wrong_status() {
         let mut issue = Issue::new("mn-abc123".to_string(), "Test".to_string()).unwrap();
         issue.status = IssueStatus::Done;
-        let result = issue.claim("ses_123".to_string());
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("must be 'open'"));
+        let result = issue.claim(SessionId::new("ses_123").unwrap());
+        match result.unwrap_err() {
+            MannaError::InvalidStatusTransition { from, to, reason } => {
+                assert_eq!(from, IssueStatus::Done);
+                assert_eq!(to, IssueStatus::InProgress);
+                assert_eq!(reason, TransitionReason::WrongStatus);
+            }
+            other => panic!("expected a transition error, got {:?}", other),
+        }
     }
 
     #[test]
     fn test_release_issue() {
         let mut issue = Issue::new("mn-abc123".to_string(), "Test".to_string()).unwrap();
-        issue.claim("ses_123".to_string()).unwrap();
+        issue.claim(SessionId::new("ses_123").unwrap()).unwrap();
         let result = issue.release();
         assert!(result.is_ok());
         assert_eq!(issue.status, IssueStatus::Open);
@@ -412,13 +1033,13 @@ mod tests {
         let mut issue = Issue::new("mn-abc123".to_string(), "Test".to_string()).unwrap();
         let result = issue.release();
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("not claimed"));
+        assert!(result.unwrap_err().to_string().contains("not claimed"));
     }
 
     #[test]
     fn test_complete_issue() {
         let mut issue = Issue::new("mn-abc123".to_string(), "Test".to_string()).unwrap();
-        issue.claim("ses_123".to_string()).unwrap();
+        issue.claim(SessionId::new("ses_123").unwrap()).unwrap();
         let result = issue.complete();
         assert!(result.is_ok());
         assert_eq!(issue.status, IssueStatus::Done);
@@ -428,8 +1049,14 @@ mod tests {
     fn test_complete_not_in_progress() {
         let mut issue = Issue::new("mn-abc123".to_string(), "Test".to_string()).unwrap();
         let result = issue.complete();
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("must be 'in_progress'"));
+        assert!(matches!(
+            result,
+            Err(MannaError::InvalidStatusTransition {
+                from: IssueStatus::Open,
+                to: IssueStatus::Done,
+                reason: TransitionReason::WrongStatus,
+            })
+        ));
     }
 
     #[test]
@@ -460,7 +1087,7 @@ mod tests {
     #[test]
     fn test_blocked_status_with_claim() {
         let mut issue = Issue::new("mn-abc123".to_string(), "Test".to_string()).unwrap();
-        issue.claim("ses_123".to_string()).unwrap();
+        issue.claim(SessionId::new("ses_123").unwrap()).unwrap();
         assert_eq!(issue.status, IssueStatus::InProgress);
 
         issue.add_blocker("mn-def456".to_string());
@@ -470,6 +1097,74 @@ mod tests {
         assert_eq!(issue.status, IssueStatus::InProgress);
     }
 
+    #[test]
+    fn test_is_actionable() {
+        let mut issue = Issue::new("mn-abc123".to_string(), "Test".to_string()).unwrap();
+        assert!(issue.is_actionable());
+
+        issue.claim(SessionId::new("ses_123").unwrap()).unwrap();
+        assert!(issue.is_actionable());
+
+        issue.add_blocker("mn-def456".to_string());
+        assert!(!issue.is_actionable());
+
+        issue.remove_blocker("mn-def456");
+        issue.complete().unwrap();
+        assert!(!issue.is_actionable());
+    }
+
+    #[test]
+    fn test_is_overdue() {
+        let now = Utc::now();
+        let mut issue = Issue::new("mn-abc123".to_string(), "Test".to_string()).unwrap();
+        assert!(!issue.is_overdue(now));
+
+        issue.due_at = Some(now + chrono::Duration::days(1));
+        assert!(!issue.is_overdue(now));
+
+        issue.due_at = Some(now - chrono::Duration::hours(1));
+        assert!(issue.is_overdue(now));
+
+        issue.claim(SessionId::new("ses_123").unwrap()).unwrap();
+        issue.complete().unwrap();
+        assert!(!issue.is_overdue(now));
+    }
+
+    #[test]
+    fn test_is_claimable_with_done_blocker() {
+        let mut blocker = Issue::new("mn-def456".to_string(), "Blocker".to_string()).unwrap();
+        blocker.claim(SessionId::new("ses_123").unwrap()).unwrap();
+        blocker.complete().unwrap();
+
+        let mut issue = Issue::new("mn-abc123".to_string(), "Test".to_string()).unwrap();
+        issue.blocked_by.push("mn-def456".to_string());
+
+        let all = vec![blocker, issue.clone()];
+        assert!(issue.is_claimable(&all));
+    }
+
+    #[test]
+    fn test_is_claimable_with_live_blocker() {
+        let blocker = Issue::new("mn-def456".to_string(), "Blocker".to_string()).unwrap();
+
+        let mut issue = Issue::new("mn-abc123".to_string(), "Test".to_string()).unwrap();
+        issue.blocked_by.push("mn-def456".to_string());
+
+        let all = vec![blocker, issue.clone()];
+        assert!(!issue.is_claimable(&all));
+    }
+
+    #[test]
+    fn test_is_claimable_rejects_claimed_and_missing_blocker() {
+        let mut claimed = Issue::new("mn-abc123".to_string(), "Test".to_string()).unwrap();
+        claimed.claim(SessionId::new("ses_123").unwrap()).unwrap();
+        assert!(!claimed.is_claimable(&[claimed.clone()]));
+
+        let mut dangling = Issue::new("mn-def456".to_string(), "Test".to_string()).unwrap();
+        dangling.blocked_by.push("mn-gone00".to_string());
+        assert!(!dangling.is_claimable(&[dangling.clone()]));
+    }
+
     #[test]
     fn test_validate_valid_issue() {
         let issue = Issue::new("mn-abc123".to_string(), "Test".to_string()).unwrap();
@@ -495,6 +1190,78 @@ mod tests {
         assert_eq!(issue.status, deserialized.status);
     }
 
+    #[test]
+    fn test_mutation_events_carry_issue_and_context() {
+        let events = [
+            (
+                SessionEvent::create(
+                    SessionId::new("ses_1").unwrap(),
+                    "mn-abc123".to_string(),
+                    serde_json::json!({"title": "Test"}),
+                ),
+                SessionEventType::Create,
+                "create",
+            ),
+            (
+                SessionEvent::edit(
+                    SessionId::new("ses_1").unwrap(),
+                    "mn-abc123".to_string(),
+                    serde_json::json!({"title": "Renamed"}),
+                ),
+                SessionEventType::Edit,
+                "edit",
+            ),
+            (
+                SessionEvent::block(
+                    SessionId::new("ses_1").unwrap(),
+                    "mn-abc123".to_string(),
+                    serde_json::json!({"blocker_id": "mn-def456"}),
+                ),
+                SessionEventType::Block,
+                "block",
+            ),
+            (
+                SessionEvent::unblock(
+                    SessionId::new("ses_1").unwrap(),
+                    "mn-abc123".to_string(),
+                    serde_json::json!({"blocker_id": "mn-def456"}),
+                ),
+                SessionEventType::Unblock,
+                "unblock",
+            ),
+            (
+                SessionEvent::reopen(
+                    SessionId::new("ses_1").unwrap(),
+                    "mn-abc123".to_string(),
+                    serde_json::json!({}),
+                ),
+                SessionEventType::Reopen,
+                "reopen",
+            ),
+            (
+                SessionEvent::note(
+                    SessionId::new("ses_1").unwrap(),
+                    "mn-abc123".to_string(),
+                    "Looks like a config issue".to_string(),
+                ),
+                SessionEventType::Note,
+                "note",
+            ),
+        ];
+
+        for (event, expected_type, wire_name) in events {
+            assert_eq!(event.event, expected_type);
+            assert_eq!(event.issue_id.as_deref(), Some("mn-abc123"));
+            assert!(event.context.is_some());
+
+            let json = serde_json::to_string(&event).unwrap();
+            assert!(json.contains(&format!(r#""event":"{}""#, wire_name)));
+
+            let parsed: SessionEvent = serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed.event, expected_type);
+        }
+    }
+
     #[test]
     fn test_status_serialization() {
         let issue = Issue::new("mn-abc123".to_string(), "Test".to_string()).unwrap();
@@ -4,3 +4,7 @@ pub mod store;
 pub mod id;
 pub mod context;
 pub mod error;
+pub mod export;
+pub mod dedup;
+pub mod graph;
+pub mod replay;
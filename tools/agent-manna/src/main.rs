@@ -1,42 +1,211 @@
 //! Manna CLI - Issue tracking for AI agents.
 //!
 //! All output is YAML format for machine parsing.
-//! Exit codes: 0=success, 1=user error, 2=system error.
+//! Exit codes: 0=success, 1=user error, 2=system error, 3=empty result
+//! (only with --exit-nonzero-on-empty).
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, IsTerminal, Read, Write};
 use std::path::Path;
+use std::sync::{Mutex, OnceLock};
 
-use chrono::Utc;
-use clap::{Parser, Subcommand};
+use chrono::{DateTime, Utc};
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
 use serde::Serialize;
 
-use manna_core::error::MannaError;
-use manna_core::id::generate_unique_id;
-use manna_core::issue::{Issue, IssueStatus};
-use manna_core::store::MannaStore;
+use manna_core::context::{
+    build_agent_context, build_agent_prompt, build_context_with_budget, build_structured_context,
+    context_diff, context_members, issue_fingerprints, next_overdue_at, parse_sections,
+    shown_members, ContextBudget, ContextDiff, ContextOptions, ContextSection, CHARS_PER_TOKEN,
+};
+use manna_core::dedup::find_duplicate_clusters;
+use manna_core::error::{MannaError, TransitionReason};
+use manna_core::export;
+use manna_core::graph::BlockerGraph;
+use manna_core::id::{generate_unique_id, MAX_ID_LENGTH, MIN_ID_LENGTH};
+use manna_core::issue::{
+    Issue, IssueStatus, MergeStrategy as FieldMergeStrategy, SessionEvent, SessionEventType,
+    SessionId,
+};
+use manna_core::replay;
+use manna_core::store::{
+    context_cache_key, hostname, reset_store_timings, store_timings, ContextCache,
+    GenerationSnapshot, LockStrategy, MannaStore, MannaStoreConfig, StoreTimings,
+};
 
 /// Exit codes
 const EXIT_SUCCESS: i32 = 0;
 const EXIT_USER_ERROR: i32 = 1;
 const EXIT_SYSTEM_ERROR: i32 = 2;
+const EXIT_EMPTY: i32 = 3;
 
 #[derive(Parser)]
 #[command(name = "manna-core")]
 #[command(version)]
 #[command(about = "Manna issue tracking system for AI agents", long_about = None)]
 struct Cli {
+    /// Output format for command responses
+    #[arg(long, global = true, value_enum, default_value = "yaml")]
+    format: OutputFormat,
+
+    /// Render unset optional issue fields as null instead of omitting them
+    #[arg(long, global = true)]
+    explicit_nulls: bool,
+
+    /// Print a timing breakdown (load, compute, lock wait, write) to stderr
+    #[arg(long, global = true)]
+    profile: bool,
+
+    /// How timestamps are written in command output (storage stays RFC 3339)
+    #[arg(long, global = true, value_enum, default_value = "rfc3339")]
+    time_format: TimeFormat,
+
+    /// Confirm every confirmation-gated command, as if each got --force
+    #[arg(short = 'y', long, global = true)]
+    assume_yes: bool,
+
+    /// Cap on items returned by read commands; hitting it sets `truncated`
+    #[arg(long, global = true, default_value_t = DEFAULT_MAX_RESULTS)]
+    max_results: usize,
+
+    /// Exit with code 3 when a read command returns no results
+    #[arg(long, global = true)]
+    exit_nonzero_on_empty: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
 
+/// Output formats for command responses.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// YAML envelope (default)
+    Yaml,
+    /// Shell `KEY='value'` assignments for scalar fields, for use with eval
+    Env,
+    /// TOML document; lists of records become arrays of tables
+    Toml,
+    /// One compact JSON object per line; `list` emits full issue records
+    Jsonl,
+    /// Instruction-style text with claim commands for `context` and `list`;
+    /// other commands fall back to YAML
+    AgentPrompt,
+}
+
+/// Timestamp encodings for command output.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum TimeFormat {
+    /// RFC 3339 strings, as stored (default)
+    Rfc3339,
+    /// Integer seconds since the Unix epoch
+    Unix,
+}
+
+/// Timestamp encoding selected on the command line.
+static TIME_FORMAT: OnceLock<TimeFormat> = OnceLock::new();
+
+/// Output format selected on the command line.
+static OUTPUT_FORMAT: OnceLock<OutputFormat> = OnceLock::new();
+
+/// Get the selected output format, defaulting to YAML.
+fn output_format() -> OutputFormat {
+    OUTPUT_FORMAT.get().copied().unwrap_or(OutputFormat::Yaml)
+}
+
+/// Whether `--explicit-nulls` was given.
+static EXPLICIT_NULLS: OnceLock<bool> = OnceLock::new();
+
+/// Whether `-y/--assume-yes` was given.
+static ASSUME_YES: OnceLock<bool> = OnceLock::new();
+
+/// Default `--max-results`: generous, but bounded.
+const DEFAULT_MAX_RESULTS: usize = 1000;
+
+/// Item cap for read commands, set from `--max-results`.
+static MAX_RESULTS: OnceLock<usize> = OnceLock::new();
+
+/// Get the `--max-results` cap.
+fn max_results() -> usize {
+    MAX_RESULTS.get().copied().unwrap_or(DEFAULT_MAX_RESULTS)
+}
+
+/// Cut `items` to at most `max`.
+///
+/// # Returns
+/// Whether anything was dropped
+fn cap_results<T>(items: &mut Vec<T>, max: usize) -> bool {
+    let truncated = items.len() > max;
+    items.truncate(max);
+    truncated
+}
+
+/// Whether `--exit-nonzero-on-empty` was given.
+static EXIT_NONZERO_ON_EMPTY: OnceLock<bool> = OnceLock::new();
+
+/// Get the `--exit-nonzero-on-empty` setting.
+fn exit_nonzero_on_empty() -> bool {
+    EXIT_NONZERO_ON_EMPTY.get().copied().unwrap_or(false)
+}
+
+/// Exit code for a read command that produced `count` results.
+fn results_exit_code(count: usize, nonzero_on_empty: bool) -> i32 {
+    if count == 0 && nonzero_on_empty {
+        EXIT_EMPTY
+    } else {
+        EXIT_SUCCESS
+    }
+}
+
+/// Whether a confirmation-gated command may proceed: either its own
+/// `--force` or the global `--assume-yes`.
+fn confirmed(force: bool) -> bool {
+    force || ASSUME_YES.get().copied().unwrap_or(false)
+}
+
+/// Start of the timed command, set when `--profile` is given: the process
+/// start, or inside `repl` the start of the current command.
+static PROFILE_START: Mutex<Option<std::time::Instant>> = Mutex::new(None);
+
+/// When `--profile` timing started, if it was requested.
+fn profile_start() -> Option<std::time::Instant> {
+    *PROFILE_START.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Set (or with `None`, stop) `--profile` timing.
+fn set_profile_start(start: Option<std::time::Instant>) {
+    *PROFILE_START.lock().unwrap_or_else(|e| e.into_inner()) = start;
+}
+
+/// Whether `repl` is running.
+static IN_REPL: OnceLock<bool> = OnceLock::new();
+
 #[derive(Subcommand)]
 enum Commands {
-    /// Initialize .manna/ directory
+    /// Initialize .manna/ directory, restoring any missing data files
     Init,
 
+    /// Recreate data files missing from an existing .manna/ directory
+    RepairInit,
+
     /// Show current session status
     Status,
 
+    /// Everything an agent needs on wake: its claims, ready work and counts
+    Brief {
+        /// How many ready issues to include, oldest first
+        #[arg(long, default_value = "5")]
+        top: usize,
+    },
+
+    /// Record the start of the current session
+    SessionStart,
+
+    /// Record the end of the current session
+    SessionEnd,
+
+    /// List sessions that have started but not ended
+    ActiveSessions,
+
     /// Create a new issue
     Create {
         /// Issue title (1-500 characters)
@@ -44,24 +213,121 @@ enum Commands {
 
         /// Optional description
         description: Option<String>,
+
+        /// Due date: RFC 3339 timestamp or a duration from now (e.g. 3d)
+        #[arg(long)]
+        due: Option<String>,
     },
 
     /// Claim an issue for the current session
     Claim {
         /// Issue ID (e.g., mn-abc123)
         id: String,
+
+        /// Take over an issue another session holds, unless that session
+        /// is still active
+        #[arg(long)]
+        force: bool,
+
+        /// Take over the issue even if the holding session is still active
+        #[arg(long)]
+        force_anyway: bool,
+
+        /// Also assign the issue to the current session
+        #[arg(long)]
+        assign_self: bool,
+
+        /// Also record a note in the session log
+        #[arg(long)]
+        note: Option<String>,
+    },
+
+    /// Claim up to N claimable issues, oldest first
+    ClaimBatch {
+        /// Number of issues to claim
+        #[arg(long, default_value = "1")]
+        count: usize,
+
+        /// If nothing is claimable, poll for up to this long (e.g. 30s, 5m)
+        #[arg(long)]
+        wait: Option<String>,
     },
 
     /// Mark an issue as done
     Done {
         /// Issue ID (e.g., mn-abc123)
         id: String,
+
+        /// Complete even if another session holds the claim
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Reopen a done issue, clearing its claim
+    Reopen {
+        /// Issue ID (e.g., mn-abc123)
+        id: String,
     },
 
     /// Abandon/release a claimed issue
     Abandon {
         /// Issue ID (e.g., mn-abc123)
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        id: Option<String>,
+
+        /// Release even if another session holds the claim
+        #[arg(long)]
+        force: bool,
+
+        /// Release every issue this session has in progress
+        #[arg(long)]
+        all: bool,
+    },
+
+    /// Place an issue in the manual queue next to a ranked neighbor
+    Rank {
+        /// Issue ID (e.g., mn-abc123)
+        id: String,
+
+        /// Rank just ahead of this issue
+        #[arg(long, conflicts_with = "after", required_unless_present = "after")]
+        before: Option<String>,
+
+        /// Rank just behind this issue
+        #[arg(long)]
+        after: Option<String>,
+    },
+
+    /// Move an issue under a new parent, or detach it to a root
+    Reparent {
+        /// Issue ID (e.g., mn-abc123)
+        id: String,
+
+        /// New parent issue ID, or `none` to detach
+        #[arg(long)]
+        parent: String,
+    },
+
+    /// Attach a file reference to an issue
+    Attach {
+        /// Issue ID
+        id: String,
+
+        /// Path to the file (must exist)
+        path: String,
+
+        /// Note about what the file is
+        #[arg(long)]
+        desc: Option<String>,
+    },
+
+    /// Remove a file reference from an issue
+    Detach {
+        /// Issue ID
         id: String,
+
+        /// Attached path to remove
+        path: String,
     },
 
     /// Add a blocker dependency
@@ -69,8 +335,21 @@ enum Commands {
         /// Issue ID to mark as blocked
         id: String,
 
-        /// ID of the blocking issue
-        blocker_id: String,
+        /// IDs of the blocking issues
+        #[arg(required = true, num_args = 1..)]
+        blocker_ids: Vec<String>,
+
+        /// Create an open "(stub)" issue for any blocker that does not exist yet
+        #[arg(long)]
+        create_stub: bool,
+
+        /// Warn if the issue already waits on a blocker transitively
+        #[arg(long)]
+        warn_redundant: bool,
+
+        /// Like --warn-redundant, but leave redundant blockers unrecorded
+        #[arg(long)]
+        skip_redundant: bool,
     },
 
     /// Remove a blocker dependency
@@ -83,16 +362,36 @@ enum Commands {
     },
 
     /// List issues with optional status filter
-    List {
-        /// Filter by status (open, in_progress, blocked, done)
-        #[arg(long)]
-        status: Option<String>,
-    },
+    List(ListArgs),
 
     /// Show issue details
     Show {
         /// Issue ID (e.g., mn-abc123)
         id: String,
+
+        /// Truncate the description to this many characters
+        #[arg(long)]
+        limit_description: Option<usize>,
+
+        /// Print a human-readable view instead of the response envelope
+        #[arg(long)]
+        plain: bool,
+
+        /// Include full transitive blockers up to this depth (default 1)
+        #[arg(long, value_name = "DEPTH", num_args = 0..=1, default_missing_value = "1", conflicts_with = "plain")]
+        follow_blockers: Option<usize>,
+    },
+
+    /// Explain why an issue has its current status
+    Explain {
+        /// Issue ID (e.g., mn-abc123)
+        id: String,
+    },
+
+    /// Show which session last changed an issue's status, claim and blockers
+    Blame {
+        /// Issue ID (e.g., mn-abc123)
+        id: String,
     },
 
     /// Output context blob for AI agents
@@ -100,7 +399,401 @@ enum Commands {
         /// Maximum tokens for context (default 8000)
         #[arg(long, default_value = "8000")]
         max_tokens: usize,
+
+        /// Comma-separated sections in output order (open, in_progress, blocked)
+        #[arg(long)]
+        sections: Option<String>,
+
+        /// Drop the section for this status (repeatable)
+        #[arg(long)]
+        exclude_status: Vec<String>,
+
+        /// List blocked issues last and claimable issues first
+        #[arg(long)]
+        sort_blocked_last: bool,
+
+        /// Return sections of issue objects instead of a markdown blob
+        #[arg(long)]
+        structured: bool,
+
+        /// Also report estimated tokens and omitted issues per section
+        #[arg(long, conflicts_with = "structured")]
+        budget_report: bool,
+
+        /// Return only the issues changed since this generation from an
+        /// earlier context, and the IDs of those removed
+        #[arg(long, conflicts_with_all = ["structured", "budget_report"])]
+        since_generation: Option<String>,
+    },
+
+    /// One-line count of issues by status
+    Summary {
+        /// Print only the line, without the response envelope
+        #[arg(long)]
+        plain: bool,
+    },
+
+    /// List blocked issues with the status of each blocker
+    Blocked,
+
+    /// List unfinished issues past their due date, most overdue first
+    Overdue,
+
+    /// Emit Prometheus text-format gauges (no YAML envelope)
+    Metrics,
+
+    /// Emit a self-contained HTML backlog report (no YAML envelope)
+    Report,
+
+    /// List done issues, optionally grouped by UTC completion date
+    Completed {
+        /// Bucket by the UTC date of each issue's updated_at
+        #[arg(long)]
+        group_by_day: bool,
+    },
+
+    /// Completions per day over a trailing window
+    Velocity {
+        /// Window length, e.g. 7d or 2w
+        #[arg(long, default_value = "7d")]
+        window: String,
+    },
+
+    /// Report clusters of issues with near-duplicate titles
+    FindDuplicates {
+        /// Minimum title similarity (0.0-1.0, Jaccard over title tokens)
+        #[arg(long, default_value = "0.6")]
+        threshold: f64,
+    },
+
+    /// Move an issue to the trash, hiding it from normal views
+    Trash {
+        /// Issue ID (e.g., mn-abc123)
+        id: String,
+    },
+
+    /// Take an issue back out of the trash
+    Restore {
+        /// Issue ID (e.g., mn-abc123)
+        id: String,
+    },
+
+    /// Permanently remove trashed issues in one rewrite
+    EmptyTrash {
+        /// Only remove issues trashed longer ago than this (e.g. 30d)
+        #[arg(long)]
+        older_than: Option<String>,
+
+        /// List what would be removed without writing
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Required for a real run: confirms the removal
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Trim sessions.jsonl to a retention policy
+    PurgeSessions {
+        /// Drop events older than this (e.g. 30d)
+        #[arg(
+            long,
+            required_unless_present = "keep_last",
+            conflicts_with = "keep_last"
+        )]
+        older_than: Option<String>,
+
+        /// Keep only the most recent N events
+        #[arg(long)]
+        keep_last: Option<usize>,
+
+        /// Count what would be purged without writing
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Required for a real run: confirms the purge
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Rebuild issues.jsonl by replaying sessions.jsonl
+    Replay {
+        /// Required: confirms overwriting the current issues
+        #[arg(long)]
+        force: bool,
+
+        /// Replace the issues even where the log does not reproduce them
+        #[arg(long)]
+        allow_incomplete: bool,
+    },
+
+    /// Merge duplicate issues into one kept issue
+    Squash {
+        /// Issue that survives the merge
+        keep_id: String,
+
+        /// Issues folded into the kept one
+        #[arg(required = true)]
+        merge_ids: Vec<String>,
+
+        /// Delete merged issues instead of marking them done
+        #[arg(long)]
+        delete: bool,
+    },
+
+    /// Find blocker references to issues that no longer exist
+    RelinkBlockers {
+        /// Remove dangling references and rewrite affected issues
+        #[arg(long)]
+        prune: bool,
+    },
+
+    /// Check the store for structural problems such as blocker cycles
+    Doctor,
+
+    /// Exercise the store's locking on this filesystem
+    #[command(hide = true)]
+    SelfTest {
+        /// Run the concurrent-append check
+        #[arg(long, required = true)]
+        concurrency: bool,
+
+        /// Writer threads
+        #[arg(long, default_value_t = 8)]
+        threads: usize,
+
+        /// Issues appended by each thread
+        #[arg(long, default_value_t = 25)]
+        per_thread: usize,
+    },
+
+    /// Apply a status transition to every issue with a given status
+    BulkTransition {
+        /// Only transition issues currently in this status
+        #[arg(long)]
+        status: Option<String>,
+
+        /// Target status (open, in_progress, done)
+        #[arg(long)]
+        to: String,
+
+        /// Required: confirms the mass mutation
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Delete every issue matching the filters in one rewrite
+    BulkDelete {
+        /// Only delete issues with this status
+        #[arg(long)]
+        status: Option<String>,
+
+        /// Only delete issues created longer ago than this (e.g. 30d)
+        #[arg(long)]
+        older_than: Option<String>,
+
+        /// Delete every issue; required when no filter is given
+        #[arg(long, conflicts_with_all = ["status", "older_than"])]
+        all: bool,
+
+        /// List what would be deleted without writing
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Required for a real run: confirms the deletion
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Clear the assignee from every issue assigned to someone
+    UnassignAll {
+        /// Assignee whose issues are released
+        #[arg(long)]
+        assignee: String,
+
+        /// Required: confirms the mass mutation
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Show the effective configuration and where each value came from
+    #[command(alias = "env-dump")]
+    Config,
+
+    /// Describe what this build supports: fields, formats and commands
+    Capabilities,
+
+    /// Rewrite issues.jsonl with one compact record per line
+    Normalize,
+
+    /// Compare issues.jsonl against its recorded checksum
+    VerifyChecksum {
+        /// Record the current hash, enabling checksums from now on
+        #[arg(long)]
+        update: bool,
+
+        /// Fail instead of warning when the file changed outside manna
+        #[arg(long)]
+        strict: bool,
+    },
+
+    /// Export all issues in a bulk interchange format
+    Export {
+        /// Encoding to write
+        #[arg(long, value_enum, default_value = "jsonl")]
+        encoding: ExportFormat,
+
+        /// Write to this file instead of stdout
+        #[arg(long)]
+        output: Option<String>,
+    },
+
+    /// Import issues from a bulk interchange format
+    Import {
+        /// Encoding to read
+        #[arg(long, value_enum, default_value = "jsonl")]
+        encoding: ExportFormat,
+
+        /// Read from this file instead of stdin
+        #[arg(long)]
+        input: Option<String>,
+    },
+
+    /// Merge another checkout's .manna issues and sessions into this one
+    MergeStores {
+        /// Directory containing the other .manna store
+        other_dir: String,
+
+        /// What to do when an incoming issue's ID already exists here
+        #[arg(long, value_enum, default_value = "skip")]
+        strategy: MergeStrategy,
+    },
+
+    /// Give issues fresh mn- IDs, rewriting every reference to them
+    ReindexIds {
+        /// Only reindex issues whose ID starts with this legacy prefix
+        /// (e.g. "bd-"); every issue by default
+        #[arg(long)]
+        prefix: Option<String>,
+
+        /// Confirm rewriting every matching ID
+        #[arg(long)]
+        force: bool,
     },
+
+    /// Move the issues to the layout config.toml's per_issue_files selects:
+    /// one file per issue under .manna/issues/, or issues.jsonl
+    MigrateLayout,
+
+    /// Run commands read from stdin, one per line, keeping issues loaded
+    /// between them
+    Repl,
+}
+
+/// One line of `repl` input: a command as it would follow `manna-core` on
+/// the command line. Global options come from the `repl` invocation.
+#[derive(Parser)]
+#[command(name = "manna-core", no_binary_name = true)]
+struct ReplLine {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+/// Arguments for the `list` command.
+#[derive(Args)]
+struct ListArgs {
+    /// Filter by status (open, in_progress, blocked, done)
+    #[arg(long)]
+    status: Option<String>,
+
+    /// Exclude issues with this status (repeatable)
+    #[arg(long)]
+    exclude_status: Vec<String>,
+
+    /// Only issues currently claimed by a session
+    #[arg(long, conflicts_with = "unclaimed")]
+    claimed: bool,
+
+    /// Only issues no session has claimed
+    #[arg(long)]
+    unclaimed: bool,
+
+    /// Only issues claimed by this session ID (exact match)
+    #[arg(long, value_name = "SESSION_ID", conflicts_with = "unclaimed")]
+    claimed_by: Option<String>,
+
+    /// Only issues with a description
+    #[arg(long, conflicts_with = "no_description")]
+    has_description: bool,
+
+    /// Only issues without a description
+    #[arg(long)]
+    no_description: bool,
+
+    /// Only issues updated at or after this RFC 3339 timestamp or Unix second
+    #[arg(long)]
+    since: Option<String>,
+
+    /// List issues in the trash instead of live ones
+    #[arg(long)]
+    trashed: bool,
+
+    /// Ignore the configured default_list_filter
+    #[arg(long, conflicts_with_all = ["status", "exclude_status"])]
+    all: bool,
+
+    /// Only include these issue fields (comma-separated or repeated)
+    #[arg(long, value_delimiter = ',')]
+    fields: Vec<String>,
+
+    /// Order results instead of using file order
+    #[arg(long, value_enum)]
+    sort: Option<ListSort>,
+
+    /// Group results into age buckets by created_at
+    #[arg(long, conflicts_with = "fields")]
+    bucket: bool,
+
+    /// Upper bounds of the age buckets for --bucket, ascending
+    /// (comma-separated durations)
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "1d,7d,30d",
+        requires = "bucket"
+    )]
+    buckets: Vec<String>,
+}
+
+/// Orderings for `list --sort`.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ListSort {
+    /// Ascending manual rank; unranked issues last, in file order
+    Rank,
+    /// Most dependents first: how many issues list each one as a blocker
+    Blockers,
+    /// Oldest first, by created_at
+    Age,
+}
+
+/// Bulk interchange encodings for export/import.
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    /// One JSON issue per line (same as issues.jsonl)
+    Jsonl,
+    /// Binary MessagePack array of issues
+    Msgpack,
+}
+
+/// How `merge-stores` handles an incoming issue whose ID already exists.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum MergeStrategy {
+    /// Keep the local issue and drop the incoming one with its events
+    Skip,
+    /// Replace the local issue with the incoming one
+    Overwrite,
+    /// Give the incoming issue a fresh ID and rewrite references to it
+    Rename,
 }
 
 // ============================================================================
@@ -118,6 +811,16 @@ struct SuccessResponse<T: Serialize> {
 struct ErrorResponse {
     success: bool,
     error: String,
+    /// Set when a status transition was refused, for callers to branch on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    transition: Option<TransitionDetail>,
+}
+
+#[derive(Debug, Serialize)]
+struct TransitionDetail {
+    from: IssueStatus,
+    to: IssueStatus,
+    reason: TransitionReason,
 }
 
 #[derive(Serialize)]
@@ -126,74 +829,777 @@ struct IssueData {
 }
 
 #[derive(Serialize)]
-struct IssueListData {
-    issues: Vec<IssueSummary>,
+struct DoneData {
+    issue: Issue,
+    /// Parents completed because this issue finished their last child,
+    /// nearest first
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    auto_completed: Vec<String>,
 }
 
 #[derive(Serialize)]
-struct IssueSummary {
-    id: String,
-    title: String,
-    status: IssueStatus,
-    #[serde(skip_serializing_if = "Option::is_none")]
+struct BlockData {
+    issue: Issue,
+    added: Vec<String>,
+    already_present: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct FollowedBlocker {
+    depth: usize,
+    issue: Issue,
+}
+
+#[derive(Serialize)]
+struct ShowWithBlockersData {
+    issue: Issue,
+    blockers: Vec<FollowedBlocker>,
+}
+
+#[derive(Serialize)]
+struct IssueListData {
+    issues: Vec<IssueSummary>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    truncated: bool,
+    /// Cursor for the next `--since` poll, set only when polling
+    #[serde(skip_serializing_if = "Option::is_none")]
+    as_of: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+struct AgeBucket {
+    age: String,
+    count: usize,
+    issues: Vec<IssueSummary>,
+}
+
+#[derive(Serialize)]
+struct BucketedListData {
+    buckets: Vec<AgeBucket>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    truncated: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    as_of: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+struct ProjectedListData {
+    issues: Vec<serde_json::Map<String, serde_json::Value>>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    truncated: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    as_of: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+struct IssueSummary {
+    id: String,
+    title: String,
+    status: IssueStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
     claimed_by: Option<String>,
 }
 
 #[derive(Serialize)]
 struct StatusData {
-    session_id: String,
+    session_id: SessionId,
+    claimed_issues: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct StatusCounts {
+    open: usize,
+    in_progress: usize,
+    blocked: usize,
+    done: usize,
+}
+
+#[derive(Serialize)]
+struct BriefData {
+    session_id: SessionId,
+    claimed_issues: Vec<IssueSummary>,
+    ready_count: usize,
+    ready: Vec<IssueSummary>,
+    counts: StatusCounts,
+}
+
+#[derive(Debug, Serialize)]
+struct Explanation {
+    id: String,
+    status: IssueStatus,
+    reason: String,
+    claimable: bool,
+    blockers: Vec<BlockerState>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    claimed_by: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    claimed_for_seconds: Option<i64>,
+    to_unblock: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct SessionEventData {
+    event: SessionEvent,
+}
+
+#[derive(Serialize)]
+struct ActiveSessionsData {
+    sessions: Vec<ActiveSession>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    truncated: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct ActiveSession {
+    session_id: SessionId,
+    started_at: DateTime<Utc>,
     claimed_issues: Vec<String>,
 }
 
 #[derive(Serialize)]
 struct ContextData {
     context: String,
+    /// Pass as `--since-generation` to get only what changes after this
+    generation: String,
+    cached: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    budget: Option<ContextBudget>,
+}
+
+#[derive(Serialize)]
+struct ContextDiffData {
+    generation: String,
+    since_generation: String,
+    #[serde(flatten)]
+    diff: ContextDiff,
+}
+
+#[derive(Serialize)]
+struct ClaimBatchData {
+    issues: Vec<Issue>,
+    requested: usize,
+    shortfall: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    note: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SummaryData {
+    summary: String,
+}
+
+#[derive(Serialize)]
+struct CompletedData {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    issues: Option<Vec<IssueSummary>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    days: Option<Vec<CompletedDay>>,
+    total: usize,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    truncated: bool,
+}
+
+#[derive(Serialize)]
+struct CompletedDay {
+    date: chrono::NaiveDate,
+    issues: Vec<IssueSummary>,
+}
+
+#[derive(Serialize)]
+struct VelocityData {
+    window_days: i64,
+    days: Vec<DayCount>,
+    total: usize,
+    average_per_day: f64,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    truncated: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct DayCount {
+    date: chrono::NaiveDate,
+    completed: usize,
+}
+
+#[derive(Serialize)]
+struct BlameData {
+    id: String,
+    status: BlameEntry,
+    claim: BlameEntry,
+    blockers: BlameEntry,
+}
+
+/// Last logged event that changed one aspect of an issue.
+///
+/// `session_id` is `unknown` when no logged event touches the aspect, e.g.
+/// for data written before the session log recorded it.
+#[derive(Debug, PartialEq, Serialize)]
+struct BlameEntry {
+    session_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    event: Option<SessionEventType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+struct OverdueData {
+    issues: Vec<OverdueEntry>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    truncated: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct OverdueEntry {
+    id: String,
+    title: String,
+    status: IssueStatus,
+    due_at: DateTime<Utc>,
+    overdue_seconds: i64,
+}
+
+#[derive(Serialize)]
+struct BlockedData {
+    issues: Vec<BlockedEntry>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    truncated: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct BlockedEntry {
+    id: String,
+    title: String,
+    blocked_since: DateTime<Utc>,
+    blockers: Vec<BlockerState>,
+}
+
+#[derive(Debug, Serialize)]
+struct BlockerState {
+    id: String,
+    /// Blocker status, or `missing` if the blocker does not exist
+    status: String,
+}
+
+#[derive(Serialize)]
+struct DuplicatesData {
+    clusters: Vec<DuplicateClusterData>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    truncated: bool,
+}
+
+#[derive(Serialize)]
+struct DuplicateClusterData {
+    similarity: f64,
+    issues: Vec<IssueSummary>,
+}
+
+#[derive(Serialize)]
+struct RelinkData {
+    dangling: Vec<DanglingBlocker>,
+    pruned: bool,
+    updated: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct DoctorData {
+    healthy: bool,
+    cycles: Vec<Vec<String>>,
+}
+
+#[derive(Serialize)]
+struct BulkTransitionData {
+    to: IssueStatus,
+    transitioned: Vec<String>,
+    transitioned_count: usize,
+    skipped: Vec<SkippedTransition>,
+    skipped_count: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct SkippedTransition {
+    id: String,
+    reason: String,
+}
+
+#[derive(Debug, Serialize)]
+struct DanglingBlocker {
+    issue_id: String,
+    blocker_id: String,
+}
+
+#[derive(Serialize)]
+struct BulkDeleteData {
+    dry_run: bool,
+    deleted: Vec<String>,
+    deleted_count: usize,
+    stripped_blockers: Vec<DanglingBlocker>,
+}
+
+#[derive(Serialize)]
+struct PurgeSessionsData {
+    dry_run: bool,
+    purged: usize,
+    kept: usize,
+    preserved_starts: usize,
+}
+
+#[derive(Serialize)]
+struct ReplayData {
+    replayed_events: usize,
+    skipped_events: usize,
+    issue_count: usize,
+    /// Live issues the log did not reproduce, replaced anyway
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    diverged: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct AbandonAllData {
+    released: Vec<String>,
+    released_count: usize,
+}
+
+#[derive(Serialize)]
+struct SquashData {
+    issue: Issue,
+    merged: Vec<String>,
+    deleted: bool,
+    redirected: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct UnassignAllData {
+    assignee: String,
+    unassigned: Vec<String>,
+    unassigned_count: usize,
+}
+
+/// A resolved configuration value and where it came from.
+#[derive(Debug, Serialize)]
+struct ConfigEntry {
+    value: String,
+    source: String,
+}
+
+#[derive(Serialize)]
+struct ConfigData {
+    storage_dir: ConfigEntry,
+    session_id: ConfigEntry,
+    session_format: ConfigEntry,
+    lock_strategy: ConfigEntry,
+    id_length: ConfigEntry,
+    normalize_titles: ConfigEntry,
+    title_limit: ConfigEntry,
+    chars_per_token: ConfigEntry,
+    default_list_filter: ConfigEntry,
+    auto_complete_parents: ConfigEntry,
+    per_issue_files: ConfigEntry,
+}
+
+#[derive(Serialize)]
+struct CapabilitiesData {
+    version: &'static str,
+    fields: Vec<&'static str>,
+    optional_fields: Vec<&'static str>,
+    statuses: Vec<String>,
+    formats: Vec<String>,
+    global_flags: Vec<String>,
+    commands: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct NormalizeData {
+    records: usize,
+    changed: bool,
+}
+
+#[derive(Serialize)]
+struct MigrateLayoutData {
+    layout: &'static str,
+    issues: usize,
+    changed: bool,
+}
+
+#[derive(Serialize)]
+struct ChecksumData {
+    enabled: bool,
+    matches: bool,
+    expected: Option<String>,
+    actual: String,
+}
+
+#[derive(Serialize)]
+struct ExportData {
+    exported: usize,
+    path: String,
+}
+
+#[derive(Serialize)]
+struct ConcurrencyReport {
+    passed: bool,
+    lock_strategy: String,
+    threads: usize,
+    per_thread: usize,
+    expected: usize,
+    found: usize,
+    lost_writes: usize,
+    failed_appends: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct ImportData {
+    imported: Vec<String>,
+    skipped: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct RenamedId {
+    from: String,
+    to: String,
+}
+
+#[derive(Serialize)]
+struct MergeStoresData {
+    merged: Vec<String>,
+    renamed: Vec<RenamedId>,
+    skipped: Vec<String>,
+    overwritten: Vec<String>,
+    merged_events: usize,
+}
+
+#[derive(Serialize)]
+struct ReindexIdsData {
+    reindexed: Vec<RenamedId>,
+    reindexed_count: usize,
 }
 
 #[derive(Serialize)]
 struct InitData {
     initialized: bool,
     path: String,
+    created: Vec<String>,
 }
 
 // ============================================================================
 // Helper Functions
 // ============================================================================
 
+/// Default session ID for this process, generated once.
+static DEFAULT_SESSION_ID: OnceLock<String> = OnceLock::new();
+
 /// Get session ID from environment or generate default.
-fn get_session_id() -> String {
-    std::env::var("MANNA_SESSION_ID")
-        .unwrap_or_else(|_| format!("ses_pid{}_{}", std::process::id(), Utc::now().timestamp()))
+///
+/// `$MANNA_SESSION_ID` always wins. Otherwise the ID is generated once per
+/// process in the format chosen by `$MANNA_SESSION_FORMAT`. An invalid
+/// `$MANNA_SESSION_ID` is a user error.
+fn get_session_id() -> CommandResult<SessionId> {
+    let raw = std::env::var("MANNA_SESSION_ID").unwrap_or_else(|_| {
+        DEFAULT_SESSION_ID
+            .get_or_init(|| {
+                let format = std::env::var("MANNA_SESSION_FORMAT").unwrap_or_default();
+                generate_session_id(&format)
+            })
+            .clone()
+    });
+
+    match SessionId::new(raw) {
+        Ok(id) => Ok(id),
+        Err(e) => output_error(&e, EXIT_USER_ERROR),
+    }
 }
 
-/// Output success response as YAML and exit with success code.
-fn output_success<T: Serialize>(data: T) -> ! {
+/// Generate a session ID in the given format.
+///
+/// - `host`: `ses_{host}_{pid}_{rand}`, unique across machines sharing a mount
+/// - anything else: `ses_pid{pid}_{timestamp}`
+fn generate_session_id(format: &str) -> String {
+    match format {
+        "host" => {
+            use rand::Rng;
+            format!(
+                "ses_{}_{}_{:08x}",
+                hostname(),
+                std::process::id(),
+                rand::thread_rng().gen::<u32>()
+            )
+        }
+        _ => format!("ses_pid{}_{}", std::process::id(), Utc::now().timestamp()),
+    }
+}
+
+/// Render a response in the selected output format.
+fn render_response<T: Serialize>(response: &T) -> String {
+    if TIME_FORMAT.get() != Some(&TimeFormat::Unix) {
+        return render_formatted(response);
+    }
+
+    match serde_json::to_value(response) {
+        Ok(mut value) => {
+            unix_timestamps(&mut value);
+            render_formatted(&value)
+        }
+        Err(e) => render_formatted(&serde_json::json!({
+            "success": false,
+            "error": format!("Serialization error: {}", e),
+        })),
+    }
+}
+
+/// Render a response in the selected output format.
+fn render_formatted<T: Serialize>(response: &T) -> String {
+    match output_format() {
+        OutputFormat::Yaml | OutputFormat::AgentPrompt
+            if EXPLICIT_NULLS.get().copied().unwrap_or(false) =>
+        {
+            serde_json::to_value(response)
+                .map_err(|e| e.to_string())
+                .and_then(|mut value| {
+                    fill_explicit_nulls(&mut value);
+                    serde_yaml::to_string(&value).map_err(|e| e.to_string())
+                })
+                .unwrap_or_else(|e| {
+                    format!("success: false\nerror: \"YAML serialization error: {}\"", e)
+                })
+        }
+        OutputFormat::Yaml | OutputFormat::AgentPrompt => serde_yaml::to_string(response)
+            .unwrap_or_else(|e| {
+                format!("success: false\nerror: \"YAML serialization error: {}\"", e)
+            }),
+        OutputFormat::Env => match serde_json::to_value(response) {
+            Ok(value) => render_env(&value),
+            Err(e) => render_env(&serde_json::json!({
+                "success": false,
+                "error": format!("Serialization error: {}", e),
+            })),
+        },
+        OutputFormat::Jsonl => serde_json::to_string(response).unwrap_or_else(|e| {
+            serde_json::json!({
+                "success": false,
+                "error": format!("Serialization error: {}", e),
+            })
+            .to_string()
+        }),
+        OutputFormat::Toml => match serde_json::to_value(response) {
+            Ok(value) => render_toml(value),
+            Err(e) => render_toml(serde_json::json!({
+                "success": false,
+                "error": format!("Serialization error: {}", e),
+            })),
+        },
+    }
+}
+
+/// Rewrite RFC 3339 timestamp fields as integer Unix seconds, recursively.
+///
+/// Only keys that name a point in time (`*_at`, `*_since`, `at`, `as_of`,
+/// `timestamp`) are touched, so free text that happens to look like a date
+/// is left alone.
+fn unix_timestamps(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, field) in map.iter_mut() {
+                let is_time = key == "at"
+                    || key == "as_of"
+                    || key == "timestamp"
+                    || key.ends_with("_at")
+                    || key.ends_with("_since");
+                let parsed = field
+                    .as_str()
+                    .filter(|_| is_time)
+                    .and_then(|s| DateTime::parse_from_rfc3339(s).ok());
+                match parsed {
+                    Some(time) => *field = serde_json::Value::from(time.timestamp()),
+                    None => unix_timestamps(field),
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(unix_timestamps),
+        _ => {}
+    }
+}
+
+/// Add `null` for every unset optional field of each issue record.
+///
+/// Issue records are recognized by their `created_at` and `blocked_by`
+/// fields; list summaries (`id` and `status` only) gain `claimed_by`.
+fn fill_explicit_nulls(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            let fields: &[&str] =
+                if map.contains_key("created_at") && map.contains_key("blocked_by") {
+                    &Issue::OPTIONAL_FIELDS
+                } else if map.contains_key("id") && map.contains_key("status") {
+                    &["claimed_by"]
+                } else {
+                    &[]
+                };
+            for field in fields {
+                map.entry(field.to_string())
+                    .or_insert(serde_json::Value::Null);
+            }
+            map.values_mut().for_each(fill_explicit_nulls);
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(fill_explicit_nulls),
+        _ => {}
+    }
+}
+
+/// Render a response as a TOML document.
+///
+/// TOML has no null, so null fields and array elements are dropped. Arrays
+/// of objects (such as a list of issues) are emitted as arrays of tables.
+fn render_toml(mut value: serde_json::Value) -> String {
+    fn strip_nulls(value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(map) => {
+                map.retain(|_, v| !v.is_null());
+                map.values_mut().for_each(strip_nulls);
+            }
+            serde_json::Value::Array(items) => {
+                items.retain(|v| !v.is_null());
+                items.iter_mut().for_each(strip_nulls);
+            }
+            _ => {}
+        }
+    }
+
+    strip_nulls(&mut value);
+    toml::to_string(&value).unwrap_or_else(|e| {
+        format!(
+            "success = false\nerror = \"TOML serialization error: {}\"\n",
+            e
+        )
+    })
+}
+
+/// Render scalar fields as `MANNA_<FIELD>='value'` lines.
+///
+/// Nested objects are flattened into the same namespace; arrays and nulls
+/// are skipped. Values are single-quoted so the output is safe to `eval`.
+fn render_env(value: &serde_json::Value) -> String {
+    fn collect(value: &serde_json::Value, out: &mut Vec<String>) {
+        if let serde_json::Value::Object(map) = value {
+            for (key, field) in map {
+                let scalar = match field {
+                    serde_json::Value::String(s) => s.clone(),
+                    serde_json::Value::Bool(b) => b.to_string(),
+                    serde_json::Value::Number(n) => n.to_string(),
+                    serde_json::Value::Object(_) => {
+                        collect(field, out);
+                        continue;
+                    }
+                    serde_json::Value::Array(_) | serde_json::Value::Null => continue,
+                };
+                out.push(format!(
+                    "MANNA_{}='{}'",
+                    key.to_uppercase(),
+                    scalar.replace('\'', "'\\''")
+                ));
+            }
+        }
+    }
+
+    let mut lines = Vec::new();
+    collect(value, &mut lines);
+    lines.join("\n")
+}
+
+/// What a command leaves on stdout, and the code the process exits with.
+#[derive(Debug)]
+struct CommandOutput {
+    stdout: Vec<u8>,
+    exit_code: i32,
+}
+
+/// The outcome of a command, or of a step that can end one: `Err` carries
+/// the failure response. `emit` prints either.
+type CommandResult<T = CommandOutput> = std::result::Result<T, CommandOutput>;
+
+/// Output success response with success code.
+fn output_success<T: Serialize>(data: T) -> CommandResult {
+    output_success_with_code(data, EXIT_SUCCESS)
+}
+
+/// Output the success response of a read command that found `count`
+/// results, exiting 3 on none under `--exit-nonzero-on-empty`.
+fn output_results<T: Serialize>(data: T, count: usize) -> CommandResult {
+    output_success_with_code(data, results_exit_code(count, exit_nonzero_on_empty()))
+}
+
+fn output_success_with_code<T: Serialize>(data: T, exit_code: i32) -> CommandResult {
     let response = SuccessResponse {
         success: true,
         data,
     };
-    println!(
-        "{}",
-        serde_yaml::to_string(&response).unwrap_or_else(|e| {
-            format!("success: false\nerror: \"YAML serialization error: {}\"", e)
-        })
-    );
-    std::process::exit(EXIT_SUCCESS);
+    output_raw(format!("{}\n", render_response(&response)), exit_code)
 }
 
-/// Output error response as YAML and exit with specified code.
-fn output_error(error: &str, exit_code: i32) -> ! {
-    let response = ErrorResponse {
-        success: false,
-        error: error.to_string(),
-    };
-    println!(
-        "{}",
-        serde_yaml::to_string(&response).unwrap_or_else(|e| {
-            format!("success: false\nerror: \"YAML serialization error: {}\"", e)
-        })
-    );
-    std::process::exit(exit_code);
+/// Output `stdout` as it is, with no response envelope.
+fn output_raw(stdout: impl Into<Vec<u8>>, exit_code: i32) -> CommandResult {
+    Ok(CommandOutput {
+        stdout: stdout.into(),
+        exit_code,
+    })
+}
+
+/// Output error response with specified code.
+fn output_error<T>(error: &str, exit_code: i32) -> CommandResult<T> {
+    output_error_response(
+        ErrorResponse {
+            success: false,
+            error: error.to_string(),
+            transition: None,
+        },
+        exit_code,
+    )
+}
+
+/// Output a prepared error response with specified code.
+fn output_error_response<T>(response: ErrorResponse, exit_code: i32) -> CommandResult<T> {
+    Err(CommandOutput {
+        stdout: format!("{}\n", render_response(&response)).into_bytes(),
+        exit_code,
+    })
+}
+
+/// Print a command's output, then the `--profile` report if requested.
+///
+/// # Returns
+/// The code to exit with
+fn emit(result: CommandResult) -> i32 {
+    let output = result.unwrap_or_else(|failure| failure);
+    let mut stdout = std::io::stdout().lock();
+    let written = stdout
+        .write_all(&output.stdout)
+        .and_then(|_| stdout.flush());
+    if let Some(start) = profile_start() {
+        eprintln!("{}", profile_report(start.elapsed(), store_timings()));
+    }
+    match written {
+        Ok(()) => output.exit_code,
+        Err(_) => EXIT_SYSTEM_ERROR,
+    }
+}
+
+/// Render a single-line JSON timing report, in milliseconds.
+///
+/// `compute` is whatever part of `total` was not spent in store I/O.
+fn profile_report(total: std::time::Duration, store: StoreTimings) -> String {
+    let ms = |d: std::time::Duration| d.as_secs_f64() * 1000.0;
+    let io = store.load + store.lock_wait + store.write;
+    serde_json::json!({
+        "profile": {
+            "total_ms": ms(total),
+            "load_ms": ms(store.load),
+            "compute_ms": ms(total.saturating_sub(io)),
+            "lock_wait_ms": ms(store.lock_wait),
+            "lock_waits": store.lock_waits,
+            "write_ms": ms(store.write),
+        }
+    })
+    .to_string()
 }
 
 /// Convert MannaError to exit code.
@@ -202,18 +1608,40 @@ fn error_to_exit_code(err: &MannaError) -> i32 {
         MannaError::IssueNotFound(_) => EXIT_USER_ERROR,
         MannaError::IssueAlreadyExists(_) => EXIT_USER_ERROR,
         MannaError::InvalidStatusTransition { .. } => EXIT_USER_ERROR,
+        MannaError::ClaimHeld { .. } => EXIT_USER_ERROR,
         MannaError::InvalidId(_) => EXIT_USER_ERROR,
         MannaError::Io(_) => EXIT_SYSTEM_ERROR,
         MannaError::Json(_) => EXIT_SYSTEM_ERROR,
         MannaError::NotInitialized => EXIT_USER_ERROR,
         MannaError::LockFailed(_) => EXIT_SYSTEM_ERROR,
+        MannaError::MsgPack(_) => EXIT_USER_ERROR,
+        MannaError::Config(_) => EXIT_USER_ERROR,
     }
 }
 
-/// Handle MannaError by outputting YAML error and exiting.
-fn handle_manna_error(err: MannaError) -> ! {
+/// Handle MannaError by building its YAML error response.
+///
+/// Refused status transitions also carry their `from`, `to` and `reason`.
+fn handle_manna_error<T>(err: MannaError) -> CommandResult<T> {
     let exit_code = error_to_exit_code(&err);
-    output_error(&err.to_string(), exit_code);
+    output_error_response(error_response(&err), exit_code)
+}
+
+/// Build the error response for `err`.
+fn error_response(err: &MannaError) -> ErrorResponse {
+    let transition = match err {
+        MannaError::InvalidStatusTransition { from, to, reason } => Some(TransitionDetail {
+            from: from.clone(),
+            to: to.clone(),
+            reason: *reason,
+        }),
+        _ => None,
+    };
+    ErrorResponse {
+        success: false,
+        error: err.to_string(),
+        transition,
+    }
 }
 
 /// Parse status string to IssueStatus.
@@ -230,699 +1658,6730 @@ fn parse_status(s: &str) -> Result<IssueStatus, String> {
     }
 }
 
-/// Find issue by ID or exit with error.
-fn find_issue(issues: &[Issue], id: &str) -> Issue {
-    issues
+/// Store configuration from the environment.
+///
+/// `MANNA_LOCK_STRATEGY` selects `flock` (default), `lockfile` or `none`,
+/// and `MANNA_ID_LENGTH` the hex length of new IDs (6-64, default 6); an
+/// invalid value is a user error.
+fn store_config() -> CommandResult<MannaStoreConfig> {
+    let lock_strategy = match std::env::var("MANNA_LOCK_STRATEGY") {
+        Ok(value) => match LockStrategy::parse(&value) {
+            Ok(s) => s,
+            Err(e) => return output_error(&e, EXIT_USER_ERROR),
+        },
+        Err(_) => LockStrategy::default(),
+    };
+    let id_length = match std::env::var("MANNA_ID_LENGTH") {
+        Ok(value) => match value.trim().parse::<usize>() {
+            Ok(n) if (MIN_ID_LENGTH..=MAX_ID_LENGTH).contains(&n) => n,
+            _ => {
+                return output_error(
+                    &format!(
+                        "Invalid MANNA_ID_LENGTH '{}'. Must be {}-{}",
+                        value, MIN_ID_LENGTH, MAX_ID_LENGTH
+                    ),
+                    EXIT_USER_ERROR,
+                )
+            }
+        },
+        Err(_) => MIN_ID_LENGTH,
+    };
+    Ok(MannaStoreConfig {
+        lock_strategy,
+        id_length,
+        // Contention is only reported by --profile, so only count it then
+        count_lock_waits: profile_start().is_some(),
+        // A REPL rereads issues.jsonl only when it changes
+        cache_issues: IN_REPL.get().copied().unwrap_or(false),
+        ..MannaStoreConfig::default()
+    })
+}
+
+/// An environment-controlled setting: the variable's value if set,
+/// otherwise `default`.
+fn env_entry(name: &str, default: &str) -> ConfigEntry {
+    match std::env::var(name) {
+        Ok(value) => ConfigEntry {
+            value,
+            source: format!("env {}", name),
+        },
+        Err(_) => ConfigEntry {
+            value: default.to_string(),
+            source: "default".to_string(),
+        },
+    }
+}
+
+/// Resolve the effective configuration for a store rooted at `store`.
+fn resolve_config(store: &MannaStore) -> CommandResult<ConfigData> {
+    let session_id = match std::env::var("MANNA_SESSION_ID") {
+        Ok(value) => ConfigEntry {
+            value,
+            source: "env MANNA_SESSION_ID".to_string(),
+        },
+        Err(_) => ConfigEntry {
+            value: get_session_id()?.to_string(),
+            source: "generated".to_string(),
+        },
+    };
+    let project = match store.load_project_config() {
+        Ok(c) => c,
+        Err(err) => return handle_manna_error(err),
+    };
+    let from_project = |value: Option<String>, default: &str| match value {
+        Some(value) => ConfigEntry {
+            value,
+            source: "config.toml".to_string(),
+        },
+        None => ConfigEntry {
+            value: default.to_string(),
+            source: "default".to_string(),
+        },
+    };
+    let built_in = |value: usize| ConfigEntry {
+        value: value.to_string(),
+        source: "built-in".to_string(),
+    };
+
+    Ok(ConfigData {
+        storage_dir: ConfigEntry {
+            value: store.manna_dir().display().to_string(),
+            source: "working directory".to_string(),
+        },
+        session_id,
+        session_format: env_entry("MANNA_SESSION_FORMAT", "pid"),
+        lock_strategy: env_entry("MANNA_LOCK_STRATEGY", "flock"),
+        id_length: env_entry("MANNA_ID_LENGTH", &MIN_ID_LENGTH.to_string()),
+        normalize_titles: env_entry("MANNA_NORMALIZE_TITLES", "false"),
+        title_limit: built_in(Issue::MAX_TITLE_LEN),
+        chars_per_token: built_in(CHARS_PER_TOKEN),
+        default_list_filter: from_project(project.default_list_filter, "all"),
+        auto_complete_parents: from_project(
+            project.auto_complete_parents.map(|b| b.to_string()),
+            "false",
+        ),
+        per_issue_files: from_project(project.per_issue_files.map(|b| b.to_string()), "false"),
+    })
+}
+
+/// What this build supports, read from the issue schema constants and the
+/// CLI definition so it cannot drift from either. Hidden commands are left
+/// out.
+fn capabilities() -> CapabilitiesData {
+    let cli = Cli::command();
+    CapabilitiesData {
+        version: env!("CARGO_PKG_VERSION"),
+        fields: Issue::FIELDS.to_vec(),
+        optional_fields: Issue::OPTIONAL_FIELDS.to_vec(),
+        statuses: [
+            IssueStatus::Open,
+            IssueStatus::InProgress,
+            IssueStatus::Blocked,
+            IssueStatus::Done,
+        ]
         .iter()
-        .find(|i| i.id == id)
-        .cloned()
-        .unwrap_or_else(|| {
-            output_error(&format!("Issue {} not found", id), EXIT_USER_ERROR);
+        .map(|s| s.to_string())
+        .collect(),
+        formats: OutputFormat::value_variants()
+            .iter()
+            .filter_map(|v| v.to_possible_value())
+            .map(|v| v.get_name().to_string())
+            .collect(),
+        global_flags: cli
+            .get_arguments()
+            .filter(|a| a.is_global_set() && !a.is_hide_set())
+            .filter_map(|a| a.get_long().map(|l| format!("--{}", l)))
+            .collect(),
+        commands: cli
+            .get_subcommands()
+            .filter(|c| !c.is_hide_set())
+            .map(|c| c.get_name().to_string())
+            .collect(),
+    }
+}
+
+/// Check `--fields` names against the issue schema.
+fn parse_fields(fields: &[String]) -> Result<Vec<String>, String> {
+    fields
+        .iter()
+        .map(|f| f.trim())
+        .filter(|f| !f.is_empty())
+        .map(|f| {
+            if Issue::FIELDS.contains(&f) {
+                Ok(f.to_string())
+            } else {
+                Err(format!(
+                    "Unknown field '{}'. Valid fields: {}",
+                    f,
+                    Issue::FIELDS.join(", ")
+                ))
+            }
         })
+        .collect()
 }
 
-// ============================================================================
-// Command Implementations
-// ============================================================================
+/// Keep only `fields` of a serialized issue. Unset optional fields stay
+/// absent.
+fn project_fields(issue: &Issue, fields: &[String]) -> serde_json::Map<String, serde_json::Value> {
+    let mut full = match serde_json::to_value(issue) {
+        Ok(serde_json::Value::Object(map)) => map,
+        _ => serde_json::Map::new(),
+    };
+    fields
+        .iter()
+        .filter_map(|f| full.remove(f).map(|v| (f.clone(), v)))
+        .collect()
+}
 
-fn cmd_init() -> ! {
-    let store = MannaStore::new(Path::new("."));
-    match store.init() {
-        Ok(()) => output_success(InitData {
-            initialized: true,
-            path: ".manna".to_string(),
-        }),
-        Err(err) => handle_manna_error(err),
+/// Parse a due date: an RFC 3339 timestamp, or a duration such as `3d`
+/// counted forward from `now`.
+fn parse_due(s: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(s.trim()) {
+        return Ok(at.with_timezone(&Utc));
+    }
+    let duration = parse_duration(s).map_err(|_| {
+        format!(
+            "Invalid due date '{}'. Use an RFC 3339 timestamp or a duration such as 3d",
+            s
+        )
+    })?;
+    now.checked_add_signed(duration)
+        .ok_or_else(|| format!("Due date '{}' is past the latest supported time", s.trim()))
+}
+
+/// Parse a `list --since` cursor: RFC 3339, or integer Unix seconds as
+/// emitted for `as_of` under `--time-format unix`.
+fn parse_since(s: &str) -> Result<DateTime<Utc>, String> {
+    let s = s.trim();
+    if let Ok(secs) = s.parse::<i64>() {
+        return DateTime::from_timestamp(secs, 0)
+            .ok_or_else(|| format!("Invalid --since '{}'. Out of range", s));
     }
+    DateTime::parse_from_rfc3339(s)
+        .map(|at| at.with_timezone(&Utc))
+        .map_err(|_| {
+            format!(
+                "Invalid --since '{}'. Use an RFC 3339 timestamp or Unix seconds",
+                s
+            )
+        })
 }
 
-fn cmd_status() -> ! {
-    let store = MannaStore::new(Path::new("."));
+/// Unfinished issues past their due date at `now`, most overdue first.
+fn overdue_report(issues: &[Issue], now: DateTime<Utc>) -> Vec<OverdueEntry> {
+    let mut entries: Vec<OverdueEntry> = issues
+        .iter()
+        .filter(|i| i.is_overdue(now))
+        .filter_map(|i| {
+            let due_at = i.due_at?;
+            Some(OverdueEntry {
+                id: i.id.clone(),
+                title: i.title.clone(),
+                status: i.status.clone(),
+                due_at,
+                overdue_seconds: (now - due_at).num_seconds(),
+            })
+        })
+        .collect();
 
-    if !store.is_initialized() {
-        output_error(
-            "Storage not initialized. Run 'manna-core init' first.",
-            EXIT_USER_ERROR,
-        );
+    entries.sort_by_key(|e| e.due_at);
+    entries
+}
+
+/// Truncate `text` to at most `max` characters, ending with an ellipsis
+/// when anything was cut. Counts chars, so multibyte text is never split.
+fn truncate_chars(text: &str, max: usize) -> String {
+    match text.char_indices().nth(max) {
+        Some((cut, _)) => format!("{}…", &text[..cut]),
+        None => text.to_string(),
+    }
+}
+
+/// Human-readable view of an issue for `show --plain`.
+///
+/// On a terminal the description's markdown is rendered with ANSI styling;
+/// otherwise it is printed exactly as stored.
+fn plain_issue_view(issue: &Issue, tty: bool) -> String {
+    let mut view = format!("{}  {}  [{}]\n", issue.id, issue.title, issue.status);
+    if let Some(holder) = &issue.claimed_by {
+        view.push_str(&format!("claimed by {}\n", holder));
+    }
+    if !issue.blocked_by.is_empty() {
+        view.push_str(&format!("blocked by {}\n", issue.blocked_by.join(", ")));
+    }
+    if let Some(description) = &issue.description {
+        view.push('\n');
+        if tty {
+            view.push_str(&render_markdown_ansi(description));
+        } else {
+            view.push_str(description);
+            view.push('\n');
+        }
     }
+    view
+}
 
-    let session_id = get_session_id();
+/// Render basic markdown (headers, bullet lists, `**bold**`) with ANSI
+/// escapes. Anything else passes through unchanged.
+fn render_markdown_ansi(text: &str) -> String {
+    let mut out = String::new();
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        let indent = &line[..line.len() - trimmed.len()];
+        let heading = trimmed.trim_start_matches('#');
+        if heading.len() < trimmed.len() && heading.starts_with(' ') {
+            out.push_str(&format!("\x1b[1;4m{}\x1b[0m\n", heading.trim()));
+        } else if let Some(item) = trimmed
+            .strip_prefix("- ")
+            .or_else(|| trimmed.strip_prefix("* "))
+        {
+            out.push_str(&format!("{}  • {}\n", indent, render_bold(item)));
+        } else {
+            out.push_str(&format!("{}\n", render_bold(line)));
+        }
+    }
+    out
+}
 
-    let claimed_issues: Vec<String> = match store.load_issues() {
-        Ok(issues) => issues
-            .iter()
-            .filter(|i| i.claimed_by.as_ref() == Some(&session_id))
-            .map(|i| i.id.clone())
-            .collect(),
-        Err(err) => handle_manna_error(err),
+/// Replace each `**text**` span with ANSI bold; an unpaired `**` is kept.
+fn render_bold(line: &str) -> String {
+    let mut out = String::new();
+    let mut rest = line;
+    while let Some(start) = rest.find("**") {
+        let Some(len) = rest[start + 2..].find("**") else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        out.push_str(&format!(
+            "\x1b[1m{}\x1b[22m",
+            &rest[start + 2..start + 2 + len]
+        ));
+        rest = &rest[start + 4 + len..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Parse a duration such as `30m`, `12h`, `7d` or `2w`.
+fn parse_duration(s: &str) -> Result<chrono::Duration, String> {
+    let s = s.trim();
+    let invalid = || {
+        format!(
+            "Invalid duration '{}'. Use a number followed by s, m, h, d or w (e.g. 7d)",
+            s
+        )
     };
 
-    output_success(StatusData {
-        session_id,
-        claimed_issues,
-    });
+    let split = s.len().checked_sub(1).ok_or_else(invalid)?;
+    if !s.is_char_boundary(split) {
+        return Err(invalid());
+    }
+    let (amount, unit) = s.split_at(split);
+    let amount: i64 = amount.parse().map_err(|_| invalid())?;
+    if amount < 0 {
+        return Err(invalid());
+    }
+
+    let duration = match unit {
+        "s" => chrono::Duration::try_seconds(amount),
+        "m" => chrono::Duration::try_minutes(amount),
+        "h" => chrono::Duration::try_hours(amount),
+        "d" => chrono::Duration::try_days(amount),
+        "w" => chrono::Duration::try_weeks(amount),
+        _ => return Err(invalid()),
+    };
+    duration.ok_or_else(|| format!("Duration '{}' is too large", s))
 }
 
-fn cmd_create(title: String, description: Option<String>) -> ! {
-    let store = MannaStore::new(Path::new("."));
+/// Parse an `--older-than` duration into the cutoff time that far before now.
+fn parse_cutoff(s: &str) -> Result<DateTime<Utc>, String> {
+    let duration = parse_duration(s)?;
+    Utc::now().checked_sub_signed(duration).ok_or_else(|| {
+        format!(
+            "Duration '{}' reaches before the earliest supported time",
+            s.trim()
+        )
+    })
+}
 
-    if !store.is_initialized() {
-        output_error(
-            "Storage not initialized. Run 'manna-core init' first.",
-            EXIT_USER_ERROR,
+/// Summaries of done issues, sorted by ID.
+fn done_summaries(issues: &[Issue]) -> Vec<IssueSummary> {
+    let mut done: Vec<IssueSummary> = issues
+        .iter()
+        .filter(|i| i.status == IssueStatus::Done)
+        .map(|i| IssueSummary {
+            id: i.id.clone(),
+            title: i.title.clone(),
+            status: i.status.clone(),
+            claimed_by: i.claimed_by.clone(),
+        })
+        .collect();
+    done.sort_by(|a, b| a.id.cmp(&b.id));
+    done
+}
+
+/// Group done issues by the UTC date of `updated_at`, oldest day first,
+/// each day sorted by ID.
+fn group_done_by_day(issues: &[Issue]) -> Vec<CompletedDay> {
+    let mut days: std::collections::BTreeMap<chrono::NaiveDate, Vec<Issue>> =
+        std::collections::BTreeMap::new();
+    for issue in issues.iter().filter(|i| i.status == IssueStatus::Done) {
+        days.entry(issue.updated_at.date_naive())
+            .or_default()
+            .push(issue.clone());
+    }
+
+    days.into_iter()
+        .map(|(date, members)| CompletedDay {
+            date,
+            issues: done_summaries(&members),
+        })
+        .collect()
+}
+
+/// Count done issues per day over the `days` days ending on `today`.
+///
+/// Completion time is the issue's `updated_at`, which `done` sets. Every day
+/// in the window appears, oldest first, with zero for days without completions.
+fn completions_per_day(issues: &[Issue], days: i64, today: chrono::NaiveDate) -> Vec<DayCount> {
+    (0..days)
+        .rev()
+        .map(|offset| {
+            let date = today - chrono::Duration::days(offset);
+            let completed = issues
+                .iter()
+                .filter(|i| i.status == IssueStatus::Done && i.updated_at.date_naive() == date)
+                .count();
+            DayCount { date, completed }
+        })
+        .collect()
+}
+
+/// Whether an on/off environment variable is set to `1`, `true` or `yes`.
+fn env_flag(name: &str) -> bool {
+    std::env::var(name)
+        .map(|v| matches!(v.trim().to_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false)
+}
+
+/// Append an event to the session log.
+///
+/// The issue change has already been written, so a failure here is reported
+/// as a warning rather than failing the command.
+fn record_event(store: &MannaStore, event: &SessionEvent) {
+    if let Err(err) = store.append_session(event) {
+        eprintln!(
+            "Warning: Failed to record {} event for {}: {}",
+            event.event,
+            event.issue_id.as_deref().unwrap_or("-"),
+            err
         );
     }
+}
 
-    // Validate title
-    if title.is_empty() || title.len() > 500 {
-        output_error(
-            &format!("Title must be 1-500 characters, got {}", title.len()),
-            EXIT_USER_ERROR,
+/// Log every difference between `before` and `after` as the session events
+/// that replay would need to get from one to the other, in one append.
+/// Issues only in `before` are logged as deleted. Warns on failure, like
+/// `record_event`.
+fn record_changes(store: &MannaStore, session_id: &SessionId, before: &[Issue], after: &[Issue]) {
+    let old: HashMap<&str, &Issue> = before.iter().map(|i| (i.id.as_str(), i)).collect();
+    let mut events: Vec<SessionEvent> = after
+        .iter()
+        .flat_map(|issue| {
+            replay::events_between(session_id, old.get(issue.id.as_str()).copied(), issue)
+        })
+        .collect();
+    let kept: HashSet<&str> = after.iter().map(|i| i.id.as_str()).collect();
+    events.extend(
+        before
+            .iter()
+            .filter(|i| !kept.contains(i.id.as_str()))
+            .map(|i| SessionEvent::delete(session_id.clone(), i.id.clone())),
+    );
+
+    if events.is_empty() {
+        return;
+    }
+    if let Err(err) = store.append_sessions(&events) {
+        eprintln!(
+            "Warning: Failed to record {} change event(s): {}",
+            events.len(),
+            err
         );
     }
+}
+
+/// Record a `delete` session event for each permanently removed issue, so
+/// that replay drops them (and their blocker and parent references) too.
+fn record_deletes(store: &MannaStore, session_id: &SessionId, deleted: &[String]) {
+    for id in deleted {
+        record_event(store, &SessionEvent::delete(session_id.clone(), id.clone()));
+    }
+}
 
-    // Get existing IDs for unique generation
-    let existing_ids: HashSet<String> = match store.load_issues() {
-        Ok(issues) => issues.into_iter().map(|i| i.id).collect(),
-        Err(err) => handle_manna_error(err),
+/// Claim an issue, taking it over from another session when `force` is set.
+///
+/// A takeover is refused while the holder has a `start` without a later
+/// `end` in the session log, unless `check_live` is off; this tells dead
+/// sessions apart from slow ones.
+fn claim_issue(
+    issue: &mut Issue,
+    session_id: SessionId,
+    events: &[SessionEvent],
+    force: bool,
+    check_live: bool,
+) -> Result<(), MannaError> {
+    if let Some(holder) = issue.claimed_by.clone() {
+        if force && holder != session_id.as_str() {
+            if check_live
+                && active_sessions(events, &[])
+                    .iter()
+                    .any(|s| s.session_id == holder.as_str())
+            {
+                return Err(MannaError::ClaimHeld {
+                    id: issue.id.clone(),
+                    holder,
+                });
+            }
+            issue.release()?;
+        }
+    }
+
+    issue.claim(session_id)
+}
+
+/// Sort issues in place for `list --sort`.
+///
+/// `dependents` maps an issue ID to how many issues it blocks, counted
+/// over the whole store rather than just the filtered results.
+fn sort_issues(issues: &mut [Issue], sort: ListSort, dependents: &HashMap<&str, usize>) {
+    match sort {
+        // Stable, so unranked issues keep file order at the end
+        ListSort::Rank => issues.sort_by(|a, b| match (a.rank, b.rank) {
+            (Some(a), Some(b)) => a.total_cmp(&b),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        }),
+        ListSort::Blockers => issues.sort_by_key(|i| {
+            std::cmp::Reverse(dependents.get(i.id.as_str()).copied().unwrap_or(0))
+        }),
+        ListSort::Age => issues.sort_by_key(|i| i.created_at),
+    }
+}
+
+/// Parse `--buckets` bounds into labeled durations. Bounds must be strictly
+/// increasing.
+fn parse_buckets(bounds: &[String]) -> Result<Vec<(String, chrono::Duration)>, String> {
+    let mut parsed: Vec<(String, chrono::Duration)> = Vec::new();
+    for bound in bounds {
+        let duration = parse_duration(bound)?;
+        if parsed.last().is_some_and(|(_, prev)| duration <= *prev) {
+            return Err(format!(
+                "Bucket bounds must be increasing, got '{}' after '{}'",
+                bound.trim(),
+                parsed.last().map_or("", |(label, _)| label.as_str())
+            ));
+        }
+        parsed.push((bound.trim().to_string(), duration));
+    }
+    if parsed.is_empty() {
+        return Err("At least one bucket bound is required".to_string());
+    }
+    Ok(parsed)
+}
+
+/// Group `issues` by age at `now`, youngest bucket first. An issue whose
+/// age equals a bound falls in the older bucket. Every bucket is listed,
+/// empty or not, and keeps the issues' order.
+fn age_buckets(
+    issues: Vec<Issue>,
+    bounds: &[(String, chrono::Duration)],
+    now: DateTime<Utc>,
+) -> Vec<AgeBucket> {
+    let mut buckets: Vec<AgeBucket> = (0..=bounds.len())
+        .map(|n| {
+            let age = match n {
+                0 => format!("<{}", bounds[0].0),
+                n if n == bounds.len() => format!(">={}", bounds[n - 1].0),
+                n => format!("{}-{}", bounds[n - 1].0, bounds[n].0),
+            };
+            AgeBucket {
+                age,
+                count: 0,
+                issues: Vec::new(),
+            }
+        })
+        .collect();
+
+    for issue in issues {
+        let age = now - issue.created_at;
+        let n = bounds.iter().take_while(|(_, b)| age >= *b).count();
+        buckets[n].count += 1;
+        buckets[n].issues.push(IssueSummary {
+            id: issue.id,
+            title: issue.title,
+            status: issue.status,
+            claimed_by: issue.claimed_by,
+        });
+    }
+    buckets
+}
+
+/// Rank `id` immediately before or after `target` by fractional ranking.
+///
+/// The new rank is the midpoint between `target` and its ranked neighbor
+/// on that side, or one step past `target` at either end of the queue, so
+/// no other issue is renumbered. An unranked `target` is first appended to
+/// the end of the queue. Returns the IDs whose rank changed.
+fn place_rank(
+    issues: &mut [Issue],
+    id: &str,
+    target: &str,
+    after: bool,
+) -> Result<Vec<String>, String> {
+    if id == target {
+        return Err("Cannot rank an issue relative to itself".to_string());
+    }
+    if !issues.iter().any(|i| i.id == id) {
+        return Err(format!("Issue {} not found", id));
+    }
+    let target_idx = issues
+        .iter()
+        .position(|i| i.id == target)
+        .ok_or_else(|| format!("Issue {} not found", target))?;
+
+    let mut changed = Vec::new();
+    let now = Utc::now();
+    let target_rank = match issues[target_idx].rank {
+        Some(rank) => rank,
+        None => {
+            let last = issues
+                .iter()
+                .filter(|i| i.id != id)
+                .filter_map(|i| i.rank)
+                .max_by(f64::total_cmp);
+            let rank = last.map_or(0.0, |r| r + 1.0);
+            issues[target_idx].rank = Some(rank);
+            issues[target_idx].updated_at = now;
+            changed.push(target.to_string());
+            rank
+        }
     };
 
-    // Generate unique ID
-    let id = generate_unique_id(&existing_ids);
+    // Nearest ranked neighbor on the requested side, ignoring `id` itself
+    let others = issues
+        .iter()
+        .filter(|i| i.id != id && i.id != target)
+        .filter_map(|i| i.rank);
+    let neighbor = if after {
+        others.filter(|r| *r > target_rank).min_by(f64::total_cmp)
+    } else {
+        others.filter(|r| *r < target_rank).max_by(f64::total_cmp)
+    };
+    let rank = match (neighbor, after) {
+        (Some(n), _) => (target_rank + n) / 2.0,
+        (None, true) => target_rank + 1.0,
+        (None, false) => target_rank - 1.0,
+    };
 
-    // Create issue
-    let mut issue = match Issue::new(id, title) {
-        Ok(i) => i,
-        Err(e) => output_error(&e, EXIT_USER_ERROR),
+    if let Some(issue) = issues.iter_mut().find(|i| i.id == id) {
+        issue.rank = Some(rank);
+        issue.updated_at = now;
+    }
+    changed.push(id.to_string());
+    Ok(changed)
+}
+
+/// Check that `id` may be moved under `parent` (`None` detaches to a root).
+///
+/// The parent must exist and must not be `id` itself or one of its
+/// descendants, which would make `id` its own ancestor.
+fn check_reparent(issues: &[Issue], id: &str, parent: Option<&str>) -> Result<(), String> {
+    let Some(parent) = parent else {
+        return Ok(());
     };
 
-    // Set description if provided
-    issue.description = description;
+    let mut seen = HashSet::new();
+    let mut current = Some(parent);
+    while let Some(ancestor) = current {
+        if ancestor == id {
+            return Err(format!(
+                "Cannot move {} under {}: {} is already beneath {}",
+                id, parent, parent, id
+            ));
+        }
+        if !seen.insert(ancestor) {
+            break;
+        }
+        current = match issues.iter().find(|i| i.id == ancestor) {
+            Some(issue) => issue.parent_id.as_deref(),
+            None if ancestor == parent => {
+                return Err(format!("Parent issue {} not found", parent));
+            }
+            None => None,
+        };
+    }
 
-    // Append to store
-    if let Err(err) = store.append_issue(&issue) {
-        handle_manna_error(err);
+    Ok(())
+}
+
+/// Transition every issue matching `from` to `to` via `Issue::set_status`.
+///
+/// Issues already in `to` are left alone. Illegal transitions are skipped
+/// with the reason, so one bad issue does not stop the batch.
+fn bulk_transition(
+    issues: &mut [Issue],
+    from: Option<&IssueStatus>,
+    to: &IssueStatus,
+    session_id: &SessionId,
+) -> (Vec<String>, Vec<SkippedTransition>) {
+    let mut transitioned = Vec::new();
+    let mut skipped = Vec::new();
+
+    for issue in issues.iter_mut() {
+        if from.is_some_and(|f| &issue.status != f) || &issue.status == to {
+            continue;
+        }
+        match issue.set_status(to.clone(), session_id) {
+            Ok(()) => transitioned.push(issue.id.clone()),
+            Err(err) => skipped.push(SkippedTransition {
+                id: issue.id.clone(),
+                reason: err.to_string(),
+            }),
+        }
     }
 
-    output_success(IssueData { issue });
+    (transitioned, skipped)
 }
 
-fn cmd_claim(id: String) -> ! {
-    let store = MannaStore::new(Path::new("."));
+/// Apply the optional side effects of `claim`: assign the issue to the
+/// claiming session and build a note event for the session log.
+fn claim_extras(
+    issue: &mut Issue,
+    session_id: &SessionId,
+    assign_self: bool,
+    note: Option<String>,
+) -> Option<SessionEvent> {
+    if assign_self {
+        issue.assignee = Some(session_id.to_string());
+    }
+    note.map(|text| SessionEvent::note(session_id.clone(), issue.id.clone(), text))
+}
 
-    if !store.is_initialized() {
-        output_error(
-            "Storage not initialized. Run 'manna-core init' first.",
-            EXIT_USER_ERROR,
-        );
+/// Verify the current session holds the claim on an issue.
+///
+/// Unclaimed issues pass so the status transition reports its own error.
+/// `force` bypasses the check for admin cleanup.
+fn check_claim_owner(issue: &Issue, session_id: &SessionId, force: bool) -> Result<(), String> {
+    match &issue.claimed_by {
+        Some(holder) if holder != session_id.as_str() && !force => Err(format!(
+            "Issue {} is claimed by {}, not {} (use --force to override)",
+            issue.id, holder, session_id
+        )),
+        _ => Ok(()),
     }
+}
 
-    let session_id = get_session_id();
+/// Issue filter built from `list` arguments.
+#[derive(Debug, Default)]
+struct ListFilter {
+    status: Option<IssueStatus>,
+    exclude_status: Vec<IssueStatus>,
+    claimed: Option<bool>,
+    claimed_by: Option<String>,
+    described: Option<bool>,
+    since: Option<DateTime<Utc>>,
+    trashed: bool,
+}
 
-    // Load issues
-    let issues = match store.load_issues() {
-        Ok(i) => i,
-        Err(err) => handle_manna_error(err),
-    };
+impl ListFilter {
+    /// Parse include/exclude status arguments.
+    ///
+    /// A status that is both included and excluded is an error.
+    fn new(status: Option<&str>, exclude_status: &[String]) -> Result<Self, String> {
+        let status = status.map(parse_status).transpose()?;
+        let exclude_status = exclude_status
+            .iter()
+            .map(|s| parse_status(s))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if let Some(included) = &status {
+            if exclude_status.contains(included) {
+                return Err(format!(
+                    "Status '{}' cannot be both included and excluded",
+                    included
+                ));
+            }
+        }
+
+        Ok(ListFilter {
+            status,
+            exclude_status,
+            claimed: None,
+            claimed_by: None,
+            described: None,
+            since: None,
+            trashed: false,
+        })
+    }
 
-    // Find issue
-    let mut issue = find_issue(&issues, &id);
+    /// Status filter for `list`: the explicit flags when any are given
+    /// (`all` counts as one), otherwise the configured default.
+    fn for_list(
+        status: Option<&str>,
+        exclude_status: &[String],
+        all: bool,
+        default: Option<&str>,
+    ) -> Result<Self, String> {
+        let explicit = all || status.is_some() || !exclude_status.is_empty();
+        match default.filter(|_| !explicit) {
+            Some("all") => ListFilter::new(None, &[]),
+            Some("active") => ListFilter::new(None, &["done".to_string()]),
+            Some(name) => ListFilter::new(Some(name), &[]).map_err(|e| {
+                format!(
+                    "Invalid default_list_filter in config.toml: {} (or use active, all)",
+                    e
+                )
+            }),
+            None => ListFilter::new(status, exclude_status),
+        }
+    }
 
-    // Claim it
-    if let Err(e) = issue.claim(session_id) {
-        output_error(&e, EXIT_USER_ERROR);
+    /// Keep only claimed (`Some(true)`) or unclaimed (`Some(false)`) issues.
+    fn with_claimed(mut self, claimed: Option<bool>) -> Self {
+        self.claimed = claimed;
+        self
     }
 
-    // Update store
-    if let Err(err) = store.update_issue(&issue) {
-        handle_manna_error(err);
+    /// Keep only issues claimed by exactly this session.
+    fn with_claimed_by(mut self, claimed_by: Option<String>) -> Self {
+        self.claimed_by = claimed_by;
+        self
     }
 
-    output_success(IssueData { issue });
+    /// Keep only issues with (`Some(true)`) or without (`Some(false)`) a
+    /// description.
+    fn with_described(mut self, described: Option<bool>) -> Self {
+        self.described = described;
+        self
+    }
+
+    /// Keep only issues updated at or after `since`.
+    fn with_since(mut self, since: Option<DateTime<Utc>>) -> Self {
+        self.since = since;
+        self
+    }
+
+    /// List the trash instead of live issues.
+    fn with_trashed(mut self, trashed: bool) -> Self {
+        self.trashed = trashed;
+        self
+    }
+
+    /// Check whether an issue passes every filter.
+    ///
+    /// Trashed issues only match when listing the trash, and then nothing
+    /// else does.
+    fn matches(&self, issue: &Issue) -> bool {
+        self.status.as_ref().is_none_or(|s| &issue.status == s)
+            && !self.exclude_status.contains(&issue.status)
+            && self.claimed.is_none_or(|c| issue.claimed_by.is_some() == c)
+            && self
+                .claimed_by
+                .as_ref()
+                .is_none_or(|s| issue.claimed_by.as_ref() == Some(s))
+            && self
+                .described
+                .is_none_or(|d| issue.description.is_some() == d)
+            && self.since.is_none_or(|t| issue.updated_at >= t)
+            && issue.deleted_at.is_some() == self.trashed
+    }
 }
 
-fn cmd_done(id: String) -> ! {
-    let store = MannaStore::new(Path::new("."));
+/// Drop trashed issues, for commands that present the normal view.
+fn without_trashed(mut issues: Vec<Issue>) -> Vec<Issue> {
+    issues.retain(|i| i.deleted_at.is_none());
+    issues
+}
 
-    if !store.is_initialized() {
-        output_error(
-            "Storage not initialized. Run 'manna-core init' first.",
-            EXIT_USER_ERROR,
-        );
+/// Find issue by ID or exit with error.
+fn find_issue(issues: &[Issue], id: &str) -> CommandResult<Issue> {
+    match issues.iter().find(|i| i.id == id) {
+        Some(issue) => Ok(issue.clone()),
+        None => output_error(&format!("Issue {} not found", id), EXIT_USER_ERROR),
+    }
+}
+
+/// Which session events `purge-sessions` keeps.
+#[derive(Debug, Clone, Copy)]
+enum SessionRetention {
+    /// Events at or after this time
+    Since(DateTime<Utc>),
+    /// The last N events in log order
+    KeepLast(usize),
+}
+
+/// Trim `events` to `retention` in place, returning how many were purged
+/// and how many were kept only to preserve an active session.
+///
+/// The latest `start` of every session that is still active survives any
+/// policy, so `active-sessions` reports the same sessions afterwards.
+fn purge_sessions(events: &mut Vec<SessionEvent>, retention: SessionRetention) -> (usize, usize) {
+    let mut pinned: HashMap<SessionId, DateTime<Utc>> = active_sessions(events, &[])
+        .into_iter()
+        .map(|s| (s.session_id, s.started_at))
+        .collect();
+
+    let total = events.len();
+    let mut preserved = 0;
+    let mut idx = 0;
+    events.retain(|event| {
+        let within = match retention {
+            SessionRetention::Since(cutoff) => event.timestamp >= cutoff,
+            SessionRetention::KeepLast(n) => idx + n >= total,
+        };
+        idx += 1;
+
+        let pins_active = event.event == SessionEventType::Start
+            && pinned.get(&event.session_id) == Some(&event.timestamp);
+        if pins_active {
+            // One start per session is enough
+            pinned.remove(&event.session_id);
+            if !within {
+                preserved += 1;
+            }
+        }
+        within || pins_active
+    });
+
+    (total - events.len(), preserved)
+}
+
+/// Fold the session log into the sessions that are currently active.
+///
+/// A session is active when its most recent `start` is later than its most
+/// recent `end`. Repeated starts are collapsed, keeping the latest.
+fn active_sessions(events: &[SessionEvent], issues: &[Issue]) -> Vec<ActiveSession> {
+    // Latest start and end per session, in first-seen order
+    let mut order: Vec<SessionId> = Vec::new();
+    let mut starts: HashMap<SessionId, DateTime<Utc>> = HashMap::new();
+    let mut ends: HashMap<SessionId, DateTime<Utc>> = HashMap::new();
+
+    for event in events {
+        let latest = match event.event {
+            SessionEventType::Start => &mut starts,
+            SessionEventType::End => &mut ends,
+            _ => continue,
+        };
+        if !order.contains(&event.session_id) {
+            order.push(event.session_id.clone());
+        }
+        let entry = latest
+            .entry(event.session_id.clone())
+            .or_insert(event.timestamp);
+        *entry = (*entry).max(event.timestamp);
+    }
+
+    order
+        .into_iter()
+        .filter_map(|session_id| {
+            let started_at = *starts.get(&session_id)?;
+            if ends.get(&session_id).is_some_and(|e| *e >= started_at) {
+                return None;
+            }
+            let claimed_issues = issues
+                .iter()
+                .filter(|i| i.claimed_by.as_deref() == Some(session_id.as_str()))
+                .map(|i| i.id.clone())
+                .collect();
+            Some(ActiveSession {
+                session_id,
+                started_at,
+                claimed_issues,
+            })
+        })
+        .collect()
+}
+
+/// Check that a blocker exists, or build a stub for it when allowed.
+///
+/// Returns `Ok(None)` if the blocker is already in `issues`, and
+/// `Ok(Some(stub))` for a new open issue titled "(stub)" that the caller
+/// must store. Stub IDs must be well-formed.
+fn resolve_blocker(
+    issues: &[Issue],
+    blocker_id: &str,
+    create_stub: bool,
+) -> Result<Option<Issue>, String> {
+    if issues.iter().any(|i| i.id == blocker_id) {
+        return Ok(None);
+    }
+
+    if !create_stub {
+        return Err(format!("Blocker issue {} not found", blocker_id));
+    }
+
+    manna_core::id::parse(blocker_id).map_err(|e| e.to_string())?;
+    Issue::new(blocker_id.to_string(), "(stub)".to_string()).map(Some)
+}
+
+/// Complete the ancestors of `id` whose children are now all done.
+///
+/// Walks up from `id`'s parent, completing each parent that is in progress
+/// and has no unfinished live child, and stops at the first that is not.
+/// Returns the completed parents' IDs, nearest first.
+fn complete_parents(issues: &mut [Issue], id: &str) -> Vec<String> {
+    let mut completed = Vec::new();
+    let mut child = id.to_string();
+    while let Some(parent_id) = issues
+        .iter()
+        .find(|i| i.id == child)
+        .and_then(|i| i.parent_id.clone())
+    {
+        // Parent links are acyclic, but a corrupt store should not hang
+        if parent_id == id || completed.contains(&parent_id) {
+            break;
+        }
+        let all_done = issues
+            .iter()
+            .filter(|i| i.parent_id.as_deref() == Some(parent_id.as_str()))
+            .filter(|i| i.deleted_at.is_none())
+            .all(|i| i.status == IssueStatus::Done);
+        let Some(parent) = issues.iter_mut().find(|i| i.id == parent_id) else {
+            break;
+        };
+        if !all_done || parent.status != IssueStatus::InProgress {
+            break;
+        }
+        if parent.complete().is_err() {
+            break;
+        }
+        completed.push(parent_id.clone());
+        child = parent_id;
+    }
+    completed
+}
+
+/// What `add_blockers` did.
+#[derive(Debug, Default)]
+struct BlockOutcome {
+    /// Blockers newly recorded on the issue
+    added: Vec<String>,
+
+    /// Blockers the issue already listed
+    already_present: Vec<String>,
+
+    /// Blockers the issue already waits on through others, with the path
+    redundant: Vec<(String, Vec<String>)>,
+
+    /// Stub issues filed for missing blockers, already added to `issues`
+    stubs: Vec<Issue>,
+}
+
+/// Add `blocker_ids` to issue `id` in `issues`.
+///
+/// Every blocker is checked before anything changes: the issue may not
+/// block itself, each blocker must exist (or gets a stub when
+/// `create_stub` is set), and none may already wait on the issue, which
+/// would close a cycle. Redundant blockers are reported, and left out when
+/// `skip_redundant` is set.
+fn add_blockers(
+    issues: &mut Vec<Issue>,
+    id: &str,
+    blocker_ids: &[String],
+    create_stub: bool,
+    skip_redundant: bool,
+) -> Result<BlockOutcome, String> {
+    let idx = issues
+        .iter()
+        .position(|i| i.id == id)
+        .ok_or_else(|| format!("Issue {} not found", id))?;
+
+    let graph = BlockerGraph::new(issues);
+    let mut outcome = BlockOutcome::default();
+    let mut to_add = Vec::new();
+    for blocker_id in blocker_ids {
+        if to_add.contains(blocker_id) || outcome.already_present.contains(blocker_id) {
+            continue;
+        }
+        if blocker_id == id {
+            return Err(format!("Issue {} cannot block itself", id));
+        }
+        if let Some(stub) = resolve_blocker(issues, blocker_id, create_stub)? {
+            outcome.stubs.push(stub);
+        }
+        if let Some(path) = graph.path(blocker_id, id) {
+            return Err(format!(
+                "Blocking {} on {} would create a cycle: {}",
+                id,
+                blocker_id,
+                path.join(" -> ")
+            ));
+        }
+
+        if issues[idx].blocked_by.contains(blocker_id) {
+            outcome.already_present.push(blocker_id.clone());
+            continue;
+        }
+        if let Some(path) = graph.path(id, blocker_id) {
+            outcome.redundant.push((blocker_id.clone(), path));
+            if skip_redundant {
+                continue;
+            }
+        }
+        to_add.push(blocker_id.clone());
+    }
+
+    for blocker_id in &to_add {
+        issues[idx].add_blocker(blocker_id.clone());
+    }
+    issues.extend(outcome.stubs.iter().cloned());
+    outcome.added = to_add;
+    Ok(outcome)
+}
+
+/// Claim up to `count` claimable issues for a session, oldest first.
+///
+/// Returns the indices of the claimed issues.
+fn claim_batch(
+    issues: &mut [Issue],
+    count: usize,
+    session_id: &SessionId,
+) -> manna_core::error::Result<Vec<usize>> {
+    let mut candidates: Vec<usize> = (0..issues.len())
+        .filter(|&idx| issues[idx].is_claimable(issues))
+        .collect();
+    candidates.sort_by_key(|&idx| issues[idx].created_at);
+    candidates.truncate(count);
+
+    for &idx in &candidates {
+        issues[idx].claim(session_id.clone())?;
+    }
+
+    Ok(candidates)
+}
+
+/// Claim up to `count` issues, polling until at least one is claimable or
+/// `timeout` elapses. Polls back off from 50ms to 1s.
+///
+/// Returns the claimed issues, empty on timeout. With a zero timeout this
+/// is a single attempt. Each attempt holds the issues lock from load to
+/// write, so concurrent claimers never take the same issue.
+fn claim_batch_waiting(
+    store: &MannaStore,
+    count: usize,
+    session_id: &SessionId,
+    timeout: std::time::Duration,
+) -> manna_core::error::Result<Vec<Issue>> {
+    let deadline = std::time::Instant::now() + timeout;
+    let mut backoff = std::time::Duration::from_millis(50);
+
+    loop {
+        {
+            let guard = store.lock_issues()?;
+            let mut issues = store.load_issues()?;
+            let claimed = claim_batch(&mut issues, count, session_id)?;
+
+            // Write all claims in one rewrite
+            if !claimed.is_empty() {
+                guard.replace_issues(&issues)?;
+                return Ok(claimed.into_iter().map(|idx| issues[idx].clone()).collect());
+            }
+        }
+
+        let now = std::time::Instant::now();
+        if now >= deadline || count == 0 {
+            return Ok(Vec::new());
+        }
+        std::thread::sleep(backoff.min(deadline - now));
+        backoff = (backoff * 2).min(std::time::Duration::from_secs(1));
+    }
+}
+
+/// Build the one-line status summary, e.g. `5 open, 2 in progress, 1 blocked, 12 done`.
+fn summary_line(issues: &[Issue]) -> String {
+    let (mut open, mut in_progress, mut blocked, mut done) = (0, 0, 0, 0);
+    for issue in issues {
+        match issue.status {
+            IssueStatus::Open => open += 1,
+            IssueStatus::InProgress => in_progress += 1,
+            IssueStatus::Blocked => blocked += 1,
+            IssueStatus::Done => done += 1,
+        }
+    }
+
+    format!(
+        "{} open, {} in progress, {} blocked, {} done",
+        open, in_progress, blocked, done
+    )
+}
+
+/// Build the session bootstrap payload: `session_id`'s claims, the `top`
+/// oldest claimable issues (the ones `claim-batch` would take) with the
+/// total ready count, and issue counts by status.
+fn brief(issues: &[Issue], session_id: SessionId, top: usize) -> BriefData {
+    let summary = |issue: &Issue| IssueSummary {
+        id: issue.id.clone(),
+        title: issue.title.clone(),
+        status: issue.status.clone(),
+        claimed_by: issue.claimed_by.clone(),
+    };
+    let count = |status: IssueStatus| issues.iter().filter(|i| i.status == status).count();
+
+    let claimed_issues = issues
+        .iter()
+        .filter(|i| i.claimed_by.as_deref() == Some(session_id.as_str()))
+        .filter(|i| i.status != IssueStatus::Done)
+        .map(summary)
+        .collect();
+    let mut ready: Vec<&Issue> = issues.iter().filter(|i| i.is_claimable(issues)).collect();
+    ready.sort_by_key(|i| i.created_at);
+
+    BriefData {
+        session_id,
+        claimed_issues,
+        ready_count: ready.len(),
+        ready: ready.into_iter().take(top).map(summary).collect(),
+        counts: StatusCounts {
+            open: count(IssueStatus::Open),
+            in_progress: count(IssueStatus::InProgress),
+            blocked: count(IssueStatus::Blocked),
+            done: count(IssueStatus::Done),
+        },
+    }
+}
+
+/// Build the blocked-issue triage report, oldest blockage first.
+///
+/// `blocked_since` is the earliest logged `block` event for a blocker the
+/// issue still has; issues without such an event fall back to `updated_at`.
+fn blocked_report(issues: &[Issue], events: &[SessionEvent]) -> Vec<BlockedEntry> {
+    let mut entries: Vec<BlockedEntry> = issues
+        .iter()
+        .filter(|i| i.status == IssueStatus::Blocked)
+        .map(|issue| {
+            let blocked_since = events
+                .iter()
+                .filter(|e| {
+                    e.event == SessionEventType::Block
+                        && e.issue_id.as_deref() == Some(issue.id.as_str())
+                        && e.context
+                            .as_ref()
+                            .and_then(|c| c.get("blocker_id"))
+                            .and_then(|b| b.as_str())
+                            .is_some_and(|b| issue.blocked_by.iter().any(|id| id == b))
+                })
+                .map(|e| e.timestamp)
+                .min()
+                .unwrap_or(issue.updated_at);
+
+            let blockers = blocker_states(issue, issues);
+
+            BlockedEntry {
+                id: issue.id.clone(),
+                title: issue.title.clone(),
+                blocked_since,
+                blockers,
+            }
+        })
+        .collect();
+
+    entries.sort_by_key(|e| e.blocked_since);
+    entries
+}
+
+/// Attribute an issue's status, claim and blockers to the last session
+/// event that changed each.
+fn blame_issue(issue_id: &str, events: &[SessionEvent]) -> BlameData {
+    use SessionEventType::*;
+
+    let last = |kinds: &[SessionEventType]| {
+        events
+            .iter()
+            .filter(|e| e.issue_id.as_deref() == Some(issue_id) && kinds.contains(&e.event))
+            .max_by_key(|e| e.timestamp)
+            .map_or(
+                BlameEntry {
+                    session_id: "unknown".to_string(),
+                    event: None,
+                    at: None,
+                },
+                |e| BlameEntry {
+                    session_id: e.session_id.to_string(),
+                    event: Some(e.event.clone()),
+                    at: Some(e.timestamp),
+                },
+            )
+    };
+
+    BlameData {
+        id: issue_id.to_string(),
+        status: last(&[Create, Claim, Release, Done, Reopen, Block, Unblock]),
+        claim: last(&[Claim, Release, Reopen]),
+        blockers: last(&[Block, Unblock]),
+    }
+}
+
+/// Escape text for inclusion in HTML element content or attribute values.
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Render a self-contained HTML table of issues grouped by status.
+///
+/// Each issue row is anchored by its ID, so blocker references link to the
+/// blocking issue's row. Styling is inline; no external assets are used.
+fn render_html_report(issues: &[Issue]) -> String {
+    let statuses = [
+        IssueStatus::Open,
+        IssueStatus::InProgress,
+        IssueStatus::Blocked,
+        IssueStatus::Done,
+    ];
+
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str("<title>Manna backlog</title>\n<style>\n");
+    out.push_str("body { font-family: sans-serif; }\n");
+    out.push_str("table { border-collapse: collapse; }\n");
+    out.push_str("th, td { border: 1px solid #ccc; padding: 4px 8px; text-align: left; }\n");
+    out.push_str("tr.group th { background: #eee; }\n");
+    out.push_str("tr.open { background: #fff; }\n");
+    out.push_str("tr.in_progress { background: #e8f0fe; }\n");
+    out.push_str("tr.blocked { background: #fdecea; }\n");
+    out.push_str("tr.done { background: #e6f4ea; color: #666; }\n");
+    out.push_str("</style>\n</head>\n<body>\n<table>\n");
+    out.push_str(
+        "<thead><tr><th>ID</th><th>Title</th><th>Claimed by</th><th>Blocked by</th></tr></thead>\n",
+    );
+
+    for status in &statuses {
+        let group: Vec<&Issue> = issues.iter().filter(|i| &i.status == status).collect();
+        if group.is_empty() {
+            continue;
+        }
+
+        out.push_str(&format!(
+            "<tbody>\n<tr class=\"group\"><th colspan=\"4\">{} ({})</th></tr>\n",
+            status,
+            group.len()
+        ));
+        for issue in group {
+            let id = html_escape(&issue.id);
+            let blockers: Vec<String> = issue
+                .blocked_by
+                .iter()
+                .map(|b| {
+                    let b = html_escape(b);
+                    format!("<a href=\"#{}\">{}</a>", b, b)
+                })
+                .collect();
+            out.push_str(&format!(
+                "<tr id=\"{}\" class=\"{}\"><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                id,
+                status,
+                id,
+                html_escape(&issue.title),
+                html_escape(issue.claimed_by.as_deref().unwrap_or("")),
+                blockers.join(", ")
+            ));
+        }
+        out.push_str("</tbody>\n");
+    }
+
+    out.push_str("</table>\n</body>\n</html>\n");
+    out
+}
+
+/// Render issue counts in the Prometheus text exposition format.
+fn render_metrics(issues: &[Issue]) -> String {
+    let statuses = [
+        IssueStatus::Open,
+        IssueStatus::InProgress,
+        IssueStatus::Blocked,
+        IssueStatus::Done,
+    ];
+
+    let mut out = String::new();
+    out.push_str("# HELP manna_issues_total Number of issues by status.\n");
+    out.push_str("# TYPE manna_issues_total gauge\n");
+    for status in &statuses {
+        let count = issues.iter().filter(|i| &i.status == status).count();
+        out.push_str(&format!(
+            "manna_issues_total{{status=\"{}\"}} {}\n",
+            status, count
+        ));
+    }
+
+    let gauges = [
+        (
+            "manna_blocked_total",
+            "Number of issues with at least one blocker.",
+            issues.iter().filter(|i| !i.blocked_by.is_empty()).count(),
+        ),
+        (
+            "manna_claimed_total",
+            "Number of issues currently claimed by a session.",
+            issues.iter().filter(|i| i.claimed_by.is_some()).count(),
+        ),
+        (
+            "manna_claimable_total",
+            "Number of issues ready to be claimed.",
+            issues.iter().filter(|i| i.is_claimable(issues)).count(),
+        ),
+    ];
+    for (name, help, value) in gauges {
+        out.push_str(&format!("# HELP {} {}\n", name, help));
+        out.push_str(&format!("# TYPE {} gauge\n", name));
+        out.push_str(&format!("{} {}\n", name, value));
+    }
+
+    out
+}
+
+/// Look up the status of each blocker, reporting `missing` for unknown IDs.
+fn blocker_states(issue: &Issue, all: &[Issue]) -> Vec<BlockerState> {
+    issue
+        .blocked_by
+        .iter()
+        .map(|blocker_id| BlockerState {
+            id: blocker_id.clone(),
+            status: all
+                .iter()
+                .find(|i| &i.id == blocker_id)
+                .map_or("missing".to_string(), |b| b.status.to_string()),
+        })
+        .collect()
+}
+
+/// Explain an issue's current status from its blockers and claim.
+fn explain_issue(issue: &Issue, all: &[Issue], now: DateTime<Utc>) -> Explanation {
+    let blockers = blocker_states(issue, all);
+    let live: Vec<&BlockerState> = blockers.iter().filter(|b| b.status != "done").collect();
+
+    let reason = match issue.status {
+        IssueStatus::Done => "Issue has been completed".to_string(),
+        IssueStatus::Blocked if live.is_empty() => format!(
+            "All {} blocker(s) are done but still listed in blocked_by",
+            blockers.len()
+        ),
+        IssueStatus::Blocked => format!(
+            "Blocked by {} unresolved issue(s): {}",
+            live.len(),
+            live.iter()
+                .map(|b| b.id.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        IssueStatus::InProgress => format!(
+            "Claimed by {}",
+            issue.claimed_by.as_deref().unwrap_or("unknown session")
+        ),
+        IssueStatus::Open => "Open and unclaimed".to_string(),
+    };
+
+    let to_unblock = blockers
+        .iter()
+        .map(|b| match b.status.as_str() {
+            "missing" => format!(
+                "{} does not exist; run 'relink-blockers --prune' to drop it",
+                b.id
+            ),
+            "done" => format!("{} is done; run 'unblock {} {}'", b.id, issue.id, b.id),
+            status => format!("Complete {} (currently {})", b.id, status),
+        })
+        .collect();
+
+    Explanation {
+        id: issue.id.clone(),
+        status: issue.status.clone(),
+        reason,
+        claimable: issue.is_claimable(all),
+        blockers,
+        claimed_by: issue.claimed_by.clone(),
+        claimed_for_seconds: issue.claimed_at.map(|at| (now - at).num_seconds()),
+        to_unblock,
+    }
+}
+
+/// Remove blocker references that point at nonexistent issues.
+///
+/// Status is recomputed through `Issue::remove_blocker`, so an issue whose
+/// only blockers were dangling returns to open (or in_progress if claimed).
+fn prune_dangling_blockers(issues: &mut [Issue]) -> Vec<DanglingBlocker> {
+    let known: HashSet<String> = issues.iter().map(|i| i.id.clone()).collect();
+    let mut removed = Vec::new();
+
+    for issue in issues.iter_mut() {
+        let dangling: Vec<String> = issue
+            .blocked_by
+            .iter()
+            .filter(|id| !known.contains(*id))
+            .cloned()
+            .collect();
+
+        for blocker_id in dangling {
+            issue.remove_blocker(&blocker_id);
+            removed.push(DanglingBlocker {
+                issue_id: issue.id.clone(),
+                blocker_id,
+            });
+        }
+    }
+
+    removed
+}
+
+/// Remove every issue matching `status` and created before `cutoff`.
+///
+/// Returns the deleted IDs and the stripped blocker references; see
+/// `remove_issues`.
+fn bulk_delete(
+    issues: &mut Vec<Issue>,
+    status: Option<&IssueStatus>,
+    cutoff: Option<DateTime<Utc>>,
+) -> (Vec<String>, Vec<DanglingBlocker>) {
+    remove_issues(issues, |i| {
+        status.is_none_or(|s| &i.status == s) && cutoff.is_none_or(|c| i.created_at < c)
+    })
+}
+
+/// Permanently remove trashed issues, limited to those trashed before
+/// `cutoff` if given.
+///
+/// Returns the removed IDs and the stripped blocker references; see
+/// `remove_issues`.
+fn empty_trash(
+    issues: &mut Vec<Issue>,
+    cutoff: Option<DateTime<Utc>>,
+) -> (Vec<String>, Vec<DanglingBlocker>) {
+    remove_issues(issues, |i| {
+        i.deleted_at.is_some_and(|at| cutoff.is_none_or(|c| at < c))
+    })
+}
+
+/// Remove every issue matching `doomed`.
+///
+/// Surviving issues lose their blocker references to removed issues (with
+/// status recomputed), and children of removed issues become roots.
+/// Returns the removed IDs and the stripped blocker references.
+fn remove_issues(
+    issues: &mut Vec<Issue>,
+    doomed: impl Fn(&Issue) -> bool,
+) -> (Vec<String>, Vec<DanglingBlocker>) {
+    let (doomed, kept): (Vec<Issue>, Vec<Issue>) =
+        std::mem::take(issues).into_iter().partition(doomed);
+    *issues = kept;
+
+    let deleted: Vec<String> = doomed.into_iter().map(|i| i.id).collect();
+    let mut stripped = Vec::new();
+    for issue in issues.iter_mut() {
+        let dangling: Vec<String> = issue
+            .blocked_by
+            .iter()
+            .filter(|id| deleted.contains(id))
+            .cloned()
+            .collect();
+        for blocker_id in dangling {
+            issue.remove_blocker(&blocker_id);
+            stripped.push(DanglingBlocker {
+                issue_id: issue.id.clone(),
+                blocker_id,
+            });
+        }
+        if issue
+            .parent_id
+            .as_ref()
+            .is_some_and(|p| deleted.contains(p))
+        {
+            issue.parent_id = None;
+            issue.updated_at = Utc::now();
+        }
+    }
+
+    (deleted, stripped)
+}
+
+/// Release every in-progress issue claimed by `session_id`.
+///
+/// # Returns
+/// The released issue IDs
+fn release_all(issues: &mut [Issue], session_id: &SessionId) -> Vec<String> {
+    issues
+        .iter_mut()
+        .filter(|i| {
+            i.status == IssueStatus::InProgress
+                && i.claimed_by.as_deref() == Some(session_id.as_str())
+        })
+        .filter_map(|issue| issue.release().ok().map(|()| issue.id.clone()))
+        .collect()
+}
+
+/// Merge `incoming` issues and their session `events` into `issues`.
+///
+/// IDs already present are handled per `strategy`. Renamed IDs get a fresh
+/// unique ID of `id_length` hex characters, and every incoming
+/// `blocked_by`/`parent_id` reference and event (including
+/// `blocker_id`/`parent_id` in event context) is rewritten to match.
+/// Skipped issues' events are dropped from `events`. `merged` lists the
+/// final IDs of every incoming issue that landed.
+///
+/// Where the incoming events do not reproduce a newly landed issue (the
+/// other log is incomplete), events logged as `session_id` are added to
+/// `events` to close the gap.
+fn merge_stores(
+    issues: &mut Vec<Issue>,
+    incoming: Vec<Issue>,
+    events: &mut Vec<SessionEvent>,
+    strategy: MergeStrategy,
+    id_length: usize,
+    session_id: &SessionId,
+) -> MergeStoresData {
+    let mut taken: HashSet<String> = issues
+        .iter()
+        .chain(&incoming)
+        .map(|i| i.id.clone())
+        .collect();
+    let existing: HashSet<String> = issues.iter().map(|i| i.id.clone()).collect();
+
+    let mut renames: HashMap<String, String> = HashMap::new();
+    let mut renamed = Vec::new();
+    let mut skipped = Vec::new();
+    for issue in incoming.iter().filter(|i| existing.contains(&i.id)) {
+        match strategy {
+            MergeStrategy::Skip => skipped.push(issue.id.clone()),
+            MergeStrategy::Overwrite => {}
+            MergeStrategy::Rename => {
+                let fresh = generate_unique_id(&taken, id_length);
+                taken.insert(fresh.clone());
+                renamed.push(RenamedId {
+                    from: issue.id.clone(),
+                    to: fresh.clone(),
+                });
+                renames.insert(issue.id.clone(), fresh);
+            }
+        }
+    }
+    let rename = |id: &str| renames.get(id).cloned().unwrap_or_else(|| id.to_string());
+
+    let mut merged = Vec::new();
+    let mut overwritten = Vec::new();
+    for mut issue in incoming {
+        if skipped.contains(&issue.id) {
+            continue;
+        }
+        issue.id = rename(&issue.id);
+        issue.blocked_by = issue.blocked_by.iter().map(|b| rename(b)).collect();
+        issue.parent_id = issue.parent_id.as_deref().map(rename);
+
+        merged.push(issue.id.clone());
+        match issues.iter_mut().find(|i| i.id == issue.id) {
+            Some(local) => {
+                overwritten.push(issue.id.clone());
+                *local = issue;
+            }
+            None => issues.push(issue),
+        }
+    }
+
+    events.retain(|e| e.issue_id.as_ref().is_none_or(|id| !skipped.contains(id)));
+    for event in events.iter_mut() {
+        event.issue_id = event.issue_id.as_deref().map(rename);
+        if let Some(context) = event.context.as_mut().and_then(|c| c.as_object_mut()) {
+            for key in ["blocker_id", "parent_id"] {
+                if let Some(serde_json::Value::String(id)) = context.get_mut(key) {
+                    *id = rename(id);
+                }
+            }
+        }
+    }
+
+    let rebuilt = replay::replay(events).issues;
+    let mut gaps = Vec::new();
+    for id in merged.iter().filter(|id| !overwritten.contains(id)) {
+        let Some(landed) = issues.iter().find(|i| &i.id == id) else {
+            continue;
+        };
+        let logged = rebuilt.iter().find(|i| &i.id == id);
+        gaps.extend(replay::events_between(session_id, logged, landed));
+    }
+    events.extend(gaps);
+
+    MergeStoresData {
+        merged,
+        renamed,
+        skipped,
+        overwritten,
+        merged_events: events.len(),
+    }
+}
+
+/// Give every issue whose ID starts with `prefix` (every issue when `None`)
+/// a fresh unique ID of `id_length` hex characters, rewriting `blocked_by`
+/// and `parent_id` references to match. Returns the mapping in file order.
+fn reindex_ids(issues: &mut [Issue], prefix: Option<&str>, id_length: usize) -> Vec<RenamedId> {
+    let mut taken: HashSet<String> = issues.iter().map(|i| i.id.clone()).collect();
+    let mut renames: HashMap<String, String> = HashMap::new();
+    let mut reindexed = Vec::new();
+    for issue in issues
+        .iter()
+        .filter(|i| prefix.is_none_or(|p| i.id.starts_with(p)))
+    {
+        let fresh = generate_unique_id(&taken, id_length);
+        taken.insert(fresh.clone());
+        reindexed.push(RenamedId {
+            from: issue.id.clone(),
+            to: fresh.clone(),
+        });
+        renames.insert(issue.id.clone(), fresh);
+    }
+    let rename = |id: &str| renames.get(id).cloned().unwrap_or_else(|| id.to_string());
+
+    for issue in issues.iter_mut() {
+        issue.id = rename(&issue.id);
+        issue.blocked_by = issue.blocked_by.iter().map(|b| rename(b)).collect();
+        issue.parent_id = issue.parent_id.as_deref().map(rename);
+    }
+    reindexed
+}
+
+/// Fold `merge_ids` into `keep_id`.
+///
+/// The kept issue gains the merged issues' blockers and attachments. Every
+/// other issue's `blocked_by` and `parent_id` references to a merged ID are
+/// redirected to `keep_id` (references from the kept issue to a merged one
+/// are dropped instead). Merged issues are then removed when `delete` is
+/// set, otherwise marked done under a claim by `session_id` with their
+/// blockers cleared. A merge whose redirected references would close a
+/// blocker cycle through `keep_id` is refused and leaves `issues` as it was.
+/// Returns the IDs of issues whose references were redirected.
+fn squash(
+    issues: &mut Vec<Issue>,
+    keep_id: &str,
+    merge_ids: &[String],
+    delete: bool,
+    session_id: &SessionId,
+) -> Result<Vec<String>, String> {
+    if !issues.iter().any(|i| i.id == keep_id) {
+        return Err(format!("Issue {} not found", keep_id));
+    }
+    for id in merge_ids {
+        if id == keep_id {
+            return Err(format!("Cannot squash {} into itself", id));
+        }
+        if !issues.iter().any(|i| &i.id == id) {
+            return Err(format!("Issue {} not found", id));
+        }
+    }
+
+    // Snapshot the merged issues before they are rewritten below
+    let merged: Vec<Issue> = issues
+        .iter()
+        .filter(|i| merge_ids.contains(&i.id))
+        .cloned()
+        .collect();
+    let original = issues.clone();
+
+    let now = Utc::now();
+    let mut redirected = Vec::new();
+    for issue in issues.iter_mut() {
+        if merge_ids.contains(&issue.id) {
+            continue;
+        }
+        let is_keep = issue.id == keep_id;
+        let mut touched = false;
+
+        for blocker_id in issue.blocked_by.clone() {
+            if merge_ids.contains(&blocker_id) {
+                issue.remove_blocker(&blocker_id);
+                if !is_keep {
+                    issue.add_blocker(keep_id.to_string());
+                }
+                touched = true;
+            }
+        }
+        if issue
+            .parent_id
+            .as_ref()
+            .is_some_and(|p| merge_ids.contains(p))
+        {
+            issue.parent_id = (!is_keep).then(|| keep_id.to_string());
+            issue.updated_at = now;
+            touched = true;
+        }
+
+        if is_keep {
+            for other in &merged {
+                issue.merge_from(other, FieldMergeStrategy::PreferSelf);
+            }
+            for merge_id in merge_ids {
+                issue.remove_blocker(merge_id);
+            }
+        } else if touched {
+            redirected.push(issue.id.clone());
+        }
+    }
+
+    // Nothing references a merged issue any more, so a new cycle must pass
+    // through the kept one
+    if let Some(path) = BlockerGraph::new(issues).path(keep_id, keep_id) {
+        *issues = original;
+        return Err(format!(
+            "Squashing into {} would create a cycle: {}",
+            keep_id,
+            path.join(" -> ")
+        ));
+    }
+
+    if delete {
+        issues.retain(|i| !merge_ids.contains(&i.id));
+    } else {
+        for issue in issues.iter_mut().filter(|i| merge_ids.contains(&i.id)) {
+            issue.status = IssueStatus::Done;
+            issue.blocked_by.clear();
+            issue.claimed_by = Some(session_id.to_string());
+            issue.claimed_at = Some(now);
+            issue.updated_at = now;
+        }
+    }
+
+    Ok(redirected)
+}
+
+/// Clear `assignee` from every issue assigned to them, returning their IDs.
+fn unassign_all(issues: &mut [Issue], assignee: &str) -> Vec<String> {
+    let now = Utc::now();
+    issues
+        .iter_mut()
+        .filter(|i| i.assignee.as_deref() == Some(assignee))
+        .map(|issue| {
+            issue.assignee = None;
+            issue.updated_at = now;
+            issue.id.clone()
+        })
+        .collect()
+}
+
+// ============================================================================
+// Command Implementations
+// ============================================================================
+
+fn cmd_init(repair: bool) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+    let result = if repair { store.repair() } else { store.init() };
+    match result {
+        Ok(created) => output_success(InitData {
+            initialized: true,
+            path: ".manna".to_string(),
+            created,
+        }),
+        Err(err) => handle_manna_error(err),
+    }
+}
+
+fn cmd_status() -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    let session_id = get_session_id()?;
+
+    let claimed_issues: Vec<String> = match store.load_issues() {
+        Ok(issues) => issues
+            .iter()
+            .filter(|i| i.claimed_by.as_deref() == Some(session_id.as_str()))
+            .map(|i| i.id.clone())
+            .collect(),
+        Err(err) => return handle_manna_error(err),
+    };
+
+    output_success(StatusData {
+        session_id,
+        claimed_issues,
+    })
+}
+
+fn cmd_brief(top: usize) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Load issues
+    let issues = match store.load_issues() {
+        Ok(i) => without_trashed(i),
+        Err(err) => return handle_manna_error(err),
+    };
+
+    output_success(brief(&issues, get_session_id()?, top))
+}
+
+fn cmd_session_event(end: bool) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    let session_id = get_session_id()?;
+    let event = if end {
+        SessionEvent::end(session_id, serde_json::json!({}))
+    } else {
+        SessionEvent::start(session_id, serde_json::json!({}))
+    };
+
+    if let Err(err) = store.append_session(&event) {
+        return handle_manna_error(err);
+    }
+
+    output_success(SessionEventData { event })
+}
+
+fn cmd_active_sessions() -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Load session log and issues
+    let events = match store.load_sessions() {
+        Ok(e) => e,
+        Err(err) => return handle_manna_error(err),
+    };
+    let issues = match store.load_issues() {
+        Ok(i) => without_trashed(i),
+        Err(err) => return handle_manna_error(err),
+    };
+
+    let mut sessions = active_sessions(&events, &issues);
+    let truncated = cap_results(&mut sessions, max_results());
+    let count = sessions.len();
+
+    output_results(
+        ActiveSessionsData {
+            sessions,
+            truncated,
+        },
+        count,
+    )
+}
+
+fn cmd_create(title: String, description: Option<String>, due: Option<String>) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Normalize whitespace when enabled; validation sees the stored form
+    let title = if env_flag("MANNA_NORMALIZE_TITLES") {
+        Issue::normalize_title(&title)
+    } else {
+        title
+    };
+
+    // Validate title
+    if title.is_empty() || title.len() > 500 {
+        return output_error(
+            &format!("Title must be 1-500 characters, got {}", title.len()),
+            EXIT_USER_ERROR,
+        );
+    }
+
+    let due_at = match due.as_deref().map(|d| parse_due(d, Utc::now())).transpose() {
+        Ok(d) => d,
+        Err(e) => return output_error(&e, EXIT_USER_ERROR),
+    };
+
+    // Get existing IDs for unique generation
+    let existing_ids: HashSet<String> = match store.load_issues() {
+        Ok(issues) => issues.into_iter().map(|i| i.id).collect(),
+        Err(err) => return handle_manna_error(err),
+    };
+
+    // Generate unique ID
+    let id = generate_unique_id(&existing_ids, store.id_length());
+
+    // Create issue
+    let mut issue = match Issue::new(id, title) {
+        Ok(i) => i,
+        Err(e) => return output_error(&e, EXIT_USER_ERROR),
+    };
+
+    // Set optional fields if provided
+    issue.description = description;
+    issue.due_at = due_at;
+
+    // Append to store
+    if let Err(err) = store.append_issue(&issue) {
+        return handle_manna_error(err);
+    }
+
+    record_event(
+        &store,
+        &SessionEvent::create(
+            get_session_id()?,
+            issue.id.clone(),
+            serde_json::json!({
+                "title": issue.title,
+                "description": issue.description,
+                "due_at": issue.due_at,
+            }),
+        ),
+    );
+
+    output_success(IssueData { issue })
+}
+
+fn cmd_claim(
+    id: String,
+    force: bool,
+    force_anyway: bool,
+    assign_self: bool,
+    note: Option<String>,
+) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    let session_id = get_session_id()?;
+
+    // Load issues
+    let issues = match store.load_issues() {
+        Ok(i) => i,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    // The session log is only needed to check a takeover
+    let events = if force && !force_anyway {
+        match store.load_sessions() {
+            Ok(e) => e,
+            Err(err) => return handle_manna_error(err),
+        }
+    } else {
+        Vec::new()
+    };
+
+    // Find issue
+    let mut issue = find_issue(&issues, &id)?;
+
+    // Claim it
+    if let Err(e) = claim_issue(
+        &mut issue,
+        session_id.clone(),
+        &events,
+        force || force_anyway,
+        !force_anyway,
+    ) {
+        return handle_manna_error(e);
+    }
+    let note = claim_extras(&mut issue, &session_id, assign_self, note);
+
+    // Update store
+    if let Err(err) = store.update_issue(&issue) {
+        return handle_manna_error(err);
+    }
+
+    record_event(
+        &store,
+        &SessionEvent::claim(session_id.clone(), issue.id.clone()),
+    );
+    if assign_self {
+        record_event(
+            &store,
+            &SessionEvent::edit(
+                session_id.clone(),
+                issue.id.clone(),
+                serde_json::json!({ "assignee": issue.assignee }),
+            ),
+        );
+    }
+    if let Some(event) = note {
+        record_event(&store, &event);
+    }
+
+    output_success(IssueData { issue })
+}
+
+fn cmd_claim_batch(count: usize, wait: Option<String>) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Parse wait timeout
+    let timeout = match wait.as_deref().map(parse_duration).transpose() {
+        Ok(d) => d
+            .and_then(|d| d.to_std().ok())
+            .unwrap_or(std::time::Duration::ZERO),
+        Err(e) => return output_error(&e, EXIT_USER_ERROR),
+    };
+
+    let session_id = get_session_id()?;
+
+    let claimed = match claim_batch_waiting(&store, count, &session_id, timeout) {
+        Ok(c) => c,
+        Err(err) => return handle_manna_error(err),
+    };
+    for issue in &claimed {
+        record_event(
+            &store,
+            &SessionEvent::claim(session_id.clone(), issue.id.clone()),
+        );
+    }
+
+    let shortfall = count - claimed.len();
+    let note = (shortfall > 0).then(|| {
+        format!(
+            "Requested {} but only {} claimable issue(s) were available",
+            count,
+            claimed.len()
+        )
+    });
+
+    output_success(ClaimBatchData {
+        issues: claimed,
+        requested: count,
+        shortfall,
+        note,
+    })
+}
+
+fn cmd_done(id: String, force: bool) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    let cascade = match store.load_project_config() {
+        Ok(c) => c.auto_complete_parents.unwrap_or(false),
+        Err(err) => return handle_manna_error(err),
+    };
+
+    // A cascade rewrites issues other than this one, so hold the lock from
+    // the load through that rewrite
+    let guard = if cascade {
+        match store.lock_issues() {
+            Ok(g) => Some(g),
+            Err(err) => return handle_manna_error(err),
+        }
+    } else {
+        None
+    };
+
+    // Load issues
+    let mut issues = match store.load_issues() {
+        Ok(i) => i,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    // Find issue
+    let mut issue = find_issue(&issues, &id)?;
+
+    // Only the claim holder may complete it
+    let session_id = get_session_id()?;
+    if let Err(e) = check_claim_owner(&issue, &session_id, force) {
+        return output_error(&e, EXIT_USER_ERROR);
+    }
+
+    // Complete it
+    if let Err(err) = issue.complete() {
+        return handle_manna_error(err);
+    }
+
+    let auto_completed = if cascade {
+        if let Some(slot) = issues.iter_mut().find(|i| i.id == issue.id) {
+            *slot = issue.clone();
+        }
+        complete_parents(&mut issues, &issue.id)
+    } else {
+        Vec::new()
+    };
+
+    // Update store; a cascade rewrites every touched parent at once
+    let written = match &guard {
+        Some(guard) => guard.replace_issues(&issues),
+        None => store.update_issue(&issue),
+    };
+    if let Err(err) = written {
+        return handle_manna_error(err);
+    }
+    drop(guard);
+
+    record_event(
+        &store,
+        &SessionEvent::done(session_id.clone(), issue.id.clone()),
+    );
+    for parent_id in &auto_completed {
+        record_event(
+            &store,
+            &SessionEvent::done(session_id.clone(), parent_id.clone()),
+        );
+    }
+
+    output_success(DoneData {
+        issue,
+        auto_completed,
+    })
+}
+
+fn cmd_reopen(id: String) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Load issues
+    let issues = match store.load_issues() {
+        Ok(i) => i,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    // Find issue
+    let mut issue = find_issue(&issues, &id)?;
+
+    // Only done issues can be reopened
+    if let Err(err) = issue.reopen() {
+        return handle_manna_error(err);
+    }
+
+    // Update store
+    if let Err(err) = store.update_issue(&issue) {
+        return handle_manna_error(err);
+    }
+
+    record_event(
+        &store,
+        &SessionEvent::reopen(
+            get_session_id()?,
+            issue.id.clone(),
+            serde_json::json!({ "status": issue.status }),
+        ),
+    );
+
+    output_success(IssueData { issue })
+}
+
+fn cmd_abandon(id: String, force: bool) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Load issues
+    let issues = match store.load_issues() {
+        Ok(i) => i,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    // Find issue
+    let mut issue = find_issue(&issues, &id)?;
+
+    // Only the claim holder may release it
+    let session_id = get_session_id()?;
+    if let Err(e) = check_claim_owner(&issue, &session_id, force) {
+        return output_error(&e, EXIT_USER_ERROR);
+    }
+
+    // Release it
+    if let Err(err) = issue.release() {
+        return handle_manna_error(err);
+    }
+
+    // Update store
+    if let Err(err) = store.update_issue(&issue) {
+        return handle_manna_error(err);
+    }
+
+    record_event(&store, &SessionEvent::release(session_id, issue.id.clone()));
+
+    output_success(IssueData { issue })
+}
+
+fn cmd_abandon_all() -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Load issues, holding the lock through the rewrite
+    let guard = match store.lock_issues() {
+        Ok(g) => g,
+        Err(err) => return handle_manna_error(err),
+    };
+    let mut issues = match store.load_issues() {
+        Ok(i) => i,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    let session_id = get_session_id()?;
+    let released = release_all(&mut issues, &session_id);
+
+    // Single rewrite for the whole batch
+    if !released.is_empty() {
+        if let Err(err) = guard.replace_issues(&issues) {
+            return handle_manna_error(err);
+        }
+    }
+    drop(guard);
+
+    for id in &released {
+        record_event(
+            &store,
+            &SessionEvent::release(session_id.clone(), id.clone()),
+        );
+    }
+
+    output_success(AbandonAllData {
+        released_count: released.len(),
+        released,
+    })
+}
+
+fn cmd_rank(id: String, before: Option<String>, after: Option<String>) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Load issues, holding the lock through the rewrite
+    let guard = match store.lock_issues() {
+        Ok(g) => g,
+        Err(err) => return handle_manna_error(err),
+    };
+    let mut issues = match store.load_issues() {
+        Ok(i) => i,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    let (target, place_after) = match (before, after) {
+        (Some(t), _) => (t, false),
+        (None, Some(t)) => (t, true),
+        (None, None) => return output_error("Pass --before or --after", EXIT_USER_ERROR),
+    };
+    if let Err(e) = place_rank(&mut issues, &id, &target, place_after) {
+        return output_error(&e, EXIT_USER_ERROR);
+    }
+
+    // The target may have been ranked too, so rewrite in one go
+    if let Err(err) = guard.replace_issues(&issues) {
+        return handle_manna_error(err);
+    }
+    drop(guard);
+
+    let issue = find_issue(&issues, &id)?;
+    record_event(
+        &store,
+        &SessionEvent::edit(
+            get_session_id()?,
+            issue.id.clone(),
+            serde_json::json!({ "rank": issue.rank }),
+        ),
+    );
+
+    output_success(IssueData { issue })
+}
+
+fn cmd_reparent(id: String, parent: String) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Load issues, holding the lock through the rewrite so a concurrent
+    // reparent cannot close a cycle this check has not seen
+    let guard = match store.lock_issues() {
+        Ok(g) => g,
+        Err(err) => return handle_manna_error(err),
+    };
+    let mut issues = match store.load_issues() {
+        Ok(i) => i,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    // Find issue
+    let mut issue = find_issue(&issues, &id)?;
+
+    let parent = (parent != "none").then_some(parent);
+    if let Err(e) = check_reparent(&issues, &issue.id, parent.as_deref()) {
+        return output_error(&e, EXIT_USER_ERROR);
+    }
+
+    issue.parent_id = parent.clone();
+    issue.updated_at = Utc::now();
+    if let Some(slot) = issues.iter_mut().find(|i| i.id == issue.id) {
+        *slot = issue.clone();
+    }
+
+    // Update store
+    if let Err(err) = guard.replace_issues(&issues) {
+        return handle_manna_error(err);
+    }
+    drop(guard);
+
+    record_event(
+        &store,
+        &SessionEvent::edit(
+            get_session_id()?,
+            issue.id.clone(),
+            serde_json::json!({ "parent_id": parent }),
+        ),
+    );
+
+    output_success(IssueData { issue })
+}
+
+fn cmd_attach(id: String, path: String, desc: Option<String>) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Only checked now; a file that later moves is reported by show
+    if !Path::new(&path).exists() {
+        return output_error(&format!("File {} not found", path), EXIT_USER_ERROR);
+    }
+
+    // Load issues
+    let issues = match store.load_issues() {
+        Ok(i) => i,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    // Find issue
+    let mut issue = find_issue(&issues, &id)?;
+
+    if let Err(e) = issue.attach(path.clone(), desc) {
+        return output_error(&e, EXIT_USER_ERROR);
+    }
+
+    // Update store
+    if let Err(err) = store.update_issue(&issue) {
+        return handle_manna_error(err);
+    }
+
+    record_event(
+        &store,
+        &SessionEvent::edit(
+            get_session_id()?,
+            issue.id.clone(),
+            serde_json::json!({ "attached": path }),
+        ),
+    );
+
+    output_success(IssueData { issue })
+}
+
+fn cmd_detach(id: String, path: String) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Load issues
+    let issues = match store.load_issues() {
+        Ok(i) => i,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    // Find issue
+    let mut issue = find_issue(&issues, &id)?;
+
+    if let Err(e) = issue.detach(&path) {
+        return output_error(&e, EXIT_USER_ERROR);
+    }
+
+    // Update store
+    if let Err(err) = store.update_issue(&issue) {
+        return handle_manna_error(err);
+    }
+
+    record_event(
+        &store,
+        &SessionEvent::edit(
+            get_session_id()?,
+            issue.id.clone(),
+            serde_json::json!({ "detached": path }),
+        ),
+    );
+
+    output_success(IssueData { issue })
+}
+
+fn cmd_block(
+    id: String,
+    blocker_ids: Vec<String>,
+    create_stub: bool,
+    warn_redundant: bool,
+    skip_redundant: bool,
+) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Load issues, holding the lock through the rewrite
+    let guard = match store.lock_issues() {
+        Ok(g) => g,
+        Err(err) => return handle_manna_error(err),
+    };
+    let mut issues = match store.load_issues() {
+        Ok(i) => i,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    // Validate every blocker before writing anything
+    let outcome = match add_blockers(&mut issues, &id, &blocker_ids, create_stub, skip_redundant) {
+        Ok(o) => o,
+        Err(e) => return output_error(&e, EXIT_USER_ERROR),
+    };
+
+    // A blocker already reachable through other blockers adds nothing
+    if warn_redundant || skip_redundant {
+        for (blocker_id, path) in &outcome.redundant {
+            eprintln!(
+                "Warning: {} already waits on {} via {}",
+                id,
+                blocker_id,
+                path.join(" -> ")
+            );
+        }
+    }
+
+    // Single rewrite for the issue and any stubs
+    if !outcome.added.is_empty() || !outcome.stubs.is_empty() {
+        if let Err(err) = guard.replace_issues(&issues) {
+            return handle_manna_error(err);
+        }
+    }
+    drop(guard);
+
+    let session_id = get_session_id()?;
+    for stub in &outcome.stubs {
+        record_event(
+            &store,
+            &SessionEvent::create(
+                session_id.clone(),
+                stub.id.clone(),
+                serde_json::json!({ "title": stub.title, "stub": true }),
+            ),
+        );
+    }
+    for blocker_id in &outcome.added {
+        record_event(
+            &store,
+            &SessionEvent::block(
+                session_id.clone(),
+                id.clone(),
+                serde_json::json!({ "blocker_id": blocker_id }),
+            ),
+        );
+    }
+
+    output_success(BlockData {
+        issue: find_issue(&issues, &id)?,
+        added: outcome.added,
+        already_present: outcome.already_present,
+    })
+}
+
+fn cmd_unblock(id: String, blocker_id: String) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Load issues
+    let issues = match store.load_issues() {
+        Ok(i) => i,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    // Find issue
+    let mut issue = find_issue(&issues, &id)?;
+
+    // Remove blocker
+    issue.remove_blocker(&blocker_id);
+
+    // Update store
+    if let Err(err) = store.update_issue(&issue) {
+        return handle_manna_error(err);
+    }
+
+    record_event(
+        &store,
+        &SessionEvent::unblock(
+            get_session_id()?,
+            issue.id.clone(),
+            serde_json::json!({ "blocker_id": blocker_id }),
+        ),
+    );
+
+    output_success(IssueData { issue })
+}
+
+fn cmd_list(args: ListArgs) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Taken before loading, so a write racing the load is caught next poll
+    let as_of = args.since.is_some().then(Utc::now);
+
+    // Load issues
+    let issues = match store.load_issues() {
+        Ok(i) => i,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    // Parse filters
+    let claimed = match (args.claimed, args.unclaimed) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    };
+    let described = match (args.has_description, args.no_description) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    };
+    let since = match args.since.as_deref().map(parse_since).transpose() {
+        Ok(t) => t,
+        Err(e) => return output_error(&e, EXIT_USER_ERROR),
+    };
+    let config = match store.load_project_config() {
+        Ok(c) => c,
+        Err(err) => return handle_manna_error(err),
+    };
+    let filter = match ListFilter::for_list(
+        args.status.as_deref(),
+        &args.exclude_status,
+        args.all,
+        config.default_list_filter.as_deref(),
+    ) {
+        Ok(f) => f
+            .with_claimed(claimed)
+            .with_claimed_by(args.claimed_by.clone())
+            .with_described(described)
+            .with_since(since)
+            .with_trashed(args.trashed),
+        Err(e) => return output_error(&e, EXIT_USER_ERROR),
+    };
+
+    let fields = match parse_fields(&args.fields) {
+        Ok(f) => f,
+        Err(e) => return output_error(&e, EXIT_USER_ERROR),
+    };
+    let buckets = if args.bucket {
+        match parse_buckets(&args.buckets) {
+            Ok(b) => Some(b),
+            Err(e) => return output_error(&e, EXIT_USER_ERROR),
+        }
+    } else {
+        None
+    };
+
+    let mut matching: Vec<Issue> = issues
+        .iter()
+        .filter(|i| filter.matches(i))
+        .cloned()
+        .collect();
+    if let Some(sort) = args.sort {
+        let graph = BlockerGraph::new(&issues);
+        sort_issues(&mut matching, sort, &graph.dependent_counts());
+    }
+    let truncated = cap_results(&mut matching, max_results());
+    let count = matching.len();
+
+    // Buckets always use the envelope, whatever the format
+    if let Some(bounds) = buckets {
+        return output_results(
+            BucketedListData {
+                buckets: age_buckets(matching, &bounds, Utc::now()),
+                truncated,
+                as_of,
+            },
+            count,
+        );
+    }
+    if truncated
+        && matches!(
+            output_format(),
+            OutputFormat::Jsonl | OutputFormat::AgentPrompt
+        )
+    {
+        // No envelope to carry the marker
+        eprintln!(
+            "Warning: results truncated to --max-results {}",
+            max_results()
+        );
+    }
+
+    // The prompt has no token budget here; --max-results bounds it
+    if output_format() == OutputFormat::AgentPrompt {
+        return output_raw(
+            build_agent_prompt(&matching, &issues, usize::MAX),
+            results_exit_code(count, exit_nonzero_on_empty()),
+        );
+    }
+
+    // Projection replaces the summary (and the full JSONL record) shape
+    if !fields.is_empty() {
+        let projected: Vec<_> = matching
+            .iter()
+            .map(|i| project_fields(i, &fields))
+            .collect();
+        if output_format() == OutputFormat::Jsonl {
+            let lines: String = projected
+                .into_iter()
+                .map(|record| format!("{}\n", serde_json::Value::Object(record)))
+                .collect();
+            return output_raw(lines, results_exit_code(count, exit_nonzero_on_empty()));
+        }
+        return output_results(
+            ProjectedListData {
+                issues: projected,
+                truncated,
+                as_of,
+            },
+            count,
+        );
+    }
+
+    // JSONL gets full records in the on-disk line format, no envelope
+    if output_format() == OutputFormat::Jsonl {
+        let bytes = match export::to_jsonl(&matching) {
+            Ok(b) => b,
+            Err(err) => return handle_manna_error(err),
+        };
+        return output_raw(bytes, results_exit_code(count, exit_nonzero_on_empty()));
+    }
+
+    // Map to summaries
+    let summaries: Vec<IssueSummary> = matching
+        .into_iter()
+        .map(|i| IssueSummary {
+            id: i.id,
+            title: i.title,
+            status: i.status,
+            claimed_by: i.claimed_by,
+        })
+        .collect();
+
+    output_results(
+        IssueListData {
+            issues: summaries,
+            truncated,
+            as_of,
+        },
+        count,
+    )
+}
+
+/// Full records of `id`'s transitive blockers up to `depth`, shallowest
+/// first. Blocker IDs with no matching issue are left out.
+fn followed_blockers(issues: &[Issue], id: &str, depth: usize) -> Vec<FollowedBlocker> {
+    BlockerGraph::new(issues)
+        .blockers_within(id, depth)
+        .into_iter()
+        .filter_map(|(blocker_id, depth)| {
+            issues
+                .iter()
+                .find(|i| i.id == blocker_id)
+                .map(|issue| FollowedBlocker {
+                    depth,
+                    issue: issue.clone(),
+                })
+        })
+        .collect()
+}
+
+fn cmd_show(
+    id: String,
+    limit_description: Option<usize>,
+    plain: bool,
+    follow_blockers: Option<usize>,
+) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Load issues
+    let issues = match store.load_issues() {
+        Ok(i) => i,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    // Find issue
+    let mut issue = find_issue(&issues, &id)?;
+
+    if let Some(limit) = limit_description {
+        issue.description = issue.description.map(|d| truncate_chars(&d, limit));
+    }
+
+    for attachment in &issue.attachments {
+        if !Path::new(&attachment.path).exists() {
+            eprintln!(
+                "Warning: attachment {} on {} no longer exists",
+                attachment.path, issue.id
+            );
+        }
+    }
+
+    if plain {
+        return output_raw(
+            plain_issue_view(&issue, std::io::stdout().is_terminal()),
+            EXIT_SUCCESS,
+        );
+    }
+
+    if let Some(depth) = follow_blockers {
+        let blockers = followed_blockers(&issues, &issue.id, depth);
+        return output_success(ShowWithBlockersData { issue, blockers });
+    }
+
+    output_success(IssueData { issue })
+}
+
+fn cmd_explain(id: String) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Load issues
+    let issues = match store.load_issues() {
+        Ok(i) => i,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    // Find issue
+    let issue = find_issue(&issues, &id)?;
+
+    output_success(explain_issue(&issue, &issues, Utc::now()))
+}
+
+fn cmd_blame(id: String) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Load issues and session log
+    let issues = match store.load_issues() {
+        Ok(i) => i,
+        Err(err) => return handle_manna_error(err),
+    };
+    let events = match store.load_sessions() {
+        Ok(e) => e,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    let issue = find_issue(&issues, &id)?;
+
+    output_success(blame_issue(&issue.id, &events))
+}
+
+fn cmd_context(
+    max_tokens: usize,
+    sections: Option<String>,
+    exclude_status: Vec<String>,
+    sort_blocked_last: bool,
+    structured: bool,
+    budget_report: bool,
+    since_generation: Option<String>,
+) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Parse section order if provided
+    let explicit_sections = sections.is_some();
+    let mut sections = match sections {
+        Some(spec) => match parse_sections(&spec) {
+            Ok(s) => s,
+            Err(e) => return output_error(&e, EXIT_USER_ERROR),
+        },
+        None => ContextSection::DEFAULT_ORDER.to_vec(),
+    };
+
+    // Drop sections for excluded statuses
+    for name in &exclude_status {
+        let status = match parse_status(name) {
+            Ok(s) => s,
+            Err(e) => return output_error(&e, EXIT_USER_ERROR),
+        };
+        if explicit_sections && sections.iter().any(|s| s.status() == status) {
+            return output_error(
+                &format!("Status '{}' cannot be both included and excluded", status),
+                EXIT_USER_ERROR,
+            );
+        }
+        sections.retain(|s| s.status() != status);
+    }
+
+    let now = Utc::now();
+    let options = ContextOptions {
+        max_tokens,
+        sections,
+        sort_blocked_last,
+        now,
+    };
+    // Everything that decides which issues the blob lists
+    let selection = format!(
+        "{}|{:?}|{}",
+        max_tokens, options.sections, sort_blocked_last
+    );
+
+    if let Some(since) = since_generation {
+        let Some(snapshot) = store.load_generation(&since, &selection) else {
+            return output_error(
+                &format!(
+                    "Unknown generation '{}' for these options; it is too old or was never returned. Run context without --since-generation for the full context",
+                    since
+                ),
+                EXIT_USER_ERROR,
+            );
+        };
+        let (issues, generation) = match store.load_issues_with_generation() {
+            Ok((i, g)) => (without_trashed(i), g),
+            Err(err) => return handle_manna_error(err),
+        };
+
+        // The diff is not truncated, so the agent now has every selected issue
+        let selected = context_members(&issues, &options);
+        remember_generation(&store, &generation, &selection, &selected, now);
+        return output_success(ContextDiffData {
+            diff: context_diff(&snapshot.issues, &selected, now),
+            generation,
+            since_generation: since,
+        });
+    }
+
+    // Reuse the last blob if nothing it depends on has changed; the budget
+    // report needs the issues, so it always rebuilds. The key names the
+    // generation, so a blob cached for any other one is a miss.
+    let agent_prompt = output_format() == OutputFormat::AgentPrompt;
+    let cacheable = !(structured || agent_prompt || budget_report);
+    if cacheable {
+        let generation = match store.generation() {
+            Ok(g) => g,
+            Err(err) => return handle_manna_error(err),
+        };
+        let key = context_cache_key(&generation, &selection);
+        if let Some(context) = store.load_context_cache(&key, now) {
+            return output_success(ContextData {
+                context,
+                generation,
+                cached: true,
+                budget: None,
+            });
+        }
+    }
+
+    // Load issues, with the generation they are
+    let (issues, generation) = match store.load_issues_with_generation() {
+        Ok((i, g)) => (without_trashed(i), g),
+        Err(err) => return handle_manna_error(err),
+    };
+    let cache_key = cacheable.then(|| context_cache_key(&generation, &selection));
+
+    // Build context blob
+    if structured {
+        return output_success(build_structured_context(&issues, &options));
+    }
+    // The prompt is the whole output, with no envelope
+    if agent_prompt {
+        return output_raw(build_agent_context(&issues, &options), EXIT_SUCCESS);
+    }
+    let (context, budget) = build_context_with_budget(&issues, &options);
+
+    if let Some(key) = cache_key {
+        let cache = ContextCache {
+            key,
+            expires_at: next_overdue_at(&issues, now),
+            context: context.clone(),
+        };
+        if let Err(err) = store.save_context_cache(&cache) {
+            eprintln!("Warning: Failed to cache context: {}", err);
+        }
+    }
+
+    let shown = shown_members(&issues, &options, &budget);
+    remember_generation(&store, &generation, &selection, &shown, now);
+    output_success(ContextData {
+        context,
+        generation,
+        cached: false,
+        budget: budget_report.then_some(budget),
+    })
+}
+
+/// Record which issues an agent was given at `generation` with the options
+/// in `selection`, so a later `context --since-generation` with the same
+/// options can diff against them. Warns on failure.
+fn remember_generation(
+    store: &MannaStore,
+    generation: &str,
+    selection: &str,
+    given: &[&Issue],
+    now: DateTime<Utc>,
+) {
+    let snapshot = GenerationSnapshot {
+        generation: generation.to_string(),
+        selection: selection.to_string(),
+        issues: issue_fingerprints(given, now),
+    };
+    if let Err(err) = store.save_generation(snapshot) {
+        eprintln!("Warning: Failed to record context generation: {}", err);
+    }
+}
+
+fn cmd_summary(plain: bool) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Load issues
+    let issues = match store.load_issues() {
+        Ok(i) => without_trashed(i),
+        Err(err) => return handle_manna_error(err),
+    };
+
+    let summary = summary_line(&issues);
+
+    if plain {
+        return output_raw(format!("{}\n", summary), EXIT_SUCCESS);
+    }
+
+    output_success(SummaryData { summary })
+}
+
+fn cmd_blocked() -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Load issues and session log
+    let issues = match store.load_issues() {
+        Ok(i) => without_trashed(i),
+        Err(err) => return handle_manna_error(err),
+    };
+    let events = match store.load_sessions() {
+        Ok(e) => e,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    let mut blocked = blocked_report(&issues, &events);
+    let truncated = cap_results(&mut blocked, max_results());
+    let count = blocked.len();
+
+    output_results(
+        BlockedData {
+            issues: blocked,
+            truncated,
+        },
+        count,
+    )
+}
+
+fn cmd_metrics() -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Load issues
+    let issues = match store.load_issues() {
+        Ok(i) => without_trashed(i),
+        Err(err) => return handle_manna_error(err),
+    };
+
+    // Plain text for textfile collectors, no YAML envelope
+    output_raw(render_metrics(&issues), EXIT_SUCCESS)
+}
+
+fn cmd_overdue() -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Load issues
+    let issues = match store.load_issues() {
+        Ok(i) => without_trashed(i),
+        Err(err) => return handle_manna_error(err),
+    };
+
+    let mut overdue = overdue_report(&issues, Utc::now());
+    let truncated = cap_results(&mut overdue, max_results());
+    let count = overdue.len();
+
+    output_results(
+        OverdueData {
+            issues: overdue,
+            truncated,
+        },
+        count,
+    )
+}
+
+fn cmd_report() -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Load issues
+    let issues = match store.load_issues() {
+        Ok(i) => without_trashed(i),
+        Err(err) => return handle_manna_error(err),
+    };
+
+    // A standalone document, no YAML envelope
+    output_raw(render_html_report(&issues), EXIT_SUCCESS)
+}
+
+fn cmd_completed(group_by_day: bool) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Load issues
+    let issues = match store.load_issues() {
+        Ok(i) => without_trashed(i),
+        Err(err) => return handle_manna_error(err),
+    };
+
+    let total = issues
+        .iter()
+        .filter(|i| i.status == IssueStatus::Done)
+        .count();
+
+    if group_by_day {
+        let mut days = group_done_by_day(&issues);
+        let truncated = cap_results(&mut days, max_results());
+        return output_results(
+            CompletedData {
+                issues: None,
+                days: Some(days),
+                total,
+                truncated,
+            },
+            total,
+        );
+    }
+
+    let mut done = done_summaries(&issues);
+    let truncated = cap_results(&mut done, max_results());
+
+    output_results(
+        CompletedData {
+            issues: Some(done),
+            days: None,
+            total,
+            truncated,
+        },
+        total,
+    )
+}
+
+fn cmd_velocity(window: String) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Parse window; the histogram is per day, so at least one day is required
+    // and the first day must be a representable date
+    let window_days = match parse_duration(&window) {
+        Ok(d) if d.num_days() < 1 => {
+            return output_error("Window must be at least 1d", EXIT_USER_ERROR)
+        }
+        Ok(d) if Utc::now().checked_sub_signed(d).is_none() => {
+            return output_error(
+                &format!(
+                    "Window '{}' reaches before the earliest supported date",
+                    window.trim()
+                ),
+                EXIT_USER_ERROR,
+            )
+        }
+        Ok(d) => d.num_days(),
+        Err(e) => return output_error(&e, EXIT_USER_ERROR),
+    };
+
+    // Load issues
+    let issues = match store.load_issues() {
+        Ok(i) => without_trashed(i),
+        Err(err) => return handle_manna_error(err),
+    };
+
+    let mut days = completions_per_day(&issues, window_days, Utc::now().date_naive());
+    let total: usize = days.iter().map(|d| d.completed).sum();
+    let truncated = cap_results(&mut days, max_results());
+
+    output_success(VelocityData {
+        window_days,
+        total,
+        average_per_day: total as f64 / window_days as f64,
+        days,
+        truncated,
+    })
+}
+
+fn cmd_find_duplicates(threshold: f64) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    if threshold <= 0.0 || threshold > 1.0 {
+        return output_error(
+            &format!("Threshold must be in (0.0, 1.0], got {}", threshold),
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Load issues
+    let issues = match store.load_issues() {
+        Ok(i) => without_trashed(i),
+        Err(err) => return handle_manna_error(err),
+    };
+
+    let mut clusters = find_duplicate_clusters(&issues, threshold);
+    let truncated = cap_results(&mut clusters, max_results());
+    let clusters = clusters
+        .into_iter()
+        .map(|cluster| {
+            Ok(DuplicateClusterData {
+                similarity: (cluster.max_similarity * 100.0).round() / 100.0,
+                issues: cluster
+                    .issue_ids
+                    .iter()
+                    .map(|id| {
+                        let i = find_issue(&issues, id)?;
+                        Ok(IssueSummary {
+                            id: i.id,
+                            title: i.title,
+                            status: i.status,
+                            claimed_by: i.claimed_by,
+                        })
+                    })
+                    .collect::<CommandResult<_>>()?,
+            })
+        })
+        .collect::<CommandResult<Vec<DuplicateClusterData>>>()?;
+
+    let count = clusters.len();
+    output_results(
+        DuplicatesData {
+            clusters,
+            truncated,
+        },
+        count,
+    )
+}
+
+fn cmd_relink_blockers(prune: bool) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Load issues, holding the lock through the rewrite
+    let guard = match store.lock_issues() {
+        Ok(g) => g,
+        Err(err) => return handle_manna_error(err),
+    };
+    let mut issues = match store.load_issues() {
+        Ok(i) => i,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    let before = issues.clone();
+    let dangling = prune_dangling_blockers(&mut issues);
+
+    // Only rewrite when asked and something changed
+    let mut updated = Vec::new();
+    if prune && !dangling.is_empty() {
+        if let Err(err) = guard.replace_issues(&issues) {
+            return handle_manna_error(err);
+        }
+        drop(guard);
+        record_changes(&store, &get_session_id()?, &before, &issues);
+        updated = dangling.iter().map(|d| d.issue_id.clone()).collect();
+        updated.dedup();
+    }
+
+    output_success(RelinkData {
+        dangling,
+        pruned: prune,
+        updated,
+    })
+}
+
+fn cmd_doctor() -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Load issues
+    let issues = match store.load_issues() {
+        Ok(i) => i,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    let cycles = BlockerGraph::new(&issues).cycles();
+
+    output_success(DoctorData {
+        healthy: cycles.is_empty(),
+        cycles,
+    })
+}
+
+/// Append `per_thread` issues from each of `threads` threads to a scratch
+/// store under `dir`, then count what landed. The scratch store is removed
+/// afterwards.
+fn concurrency_self_test(
+    dir: &Path,
+    config: MannaStoreConfig,
+    threads: usize,
+    per_thread: usize,
+) -> ConcurrencyReport {
+    let lock_strategy = format!("{:?}", config.lock_strategy).to_lowercase();
+    let expected = threads * per_thread;
+    let scratch = dir.join(format!("self-test-{}", std::process::id()));
+    let store = std::sync::Arc::new(MannaStore::with_config(&scratch, config));
+
+    let mut errors = Vec::new();
+    let mut failed_appends = 0;
+    let mut found = 0;
+    match store.init() {
+        Err(err) => errors.push(format!("init: {}", err)),
+        Ok(_) => {
+            let handles: Vec<_> = (0..threads)
+                .map(|t| {
+                    let store = std::sync::Arc::clone(&store);
+                    std::thread::spawn(move || {
+                        let mut failures = Vec::new();
+                        for i in 0..per_thread {
+                            let appended = Issue::new(
+                                format!("mn-{:04x}{:04x}", t, i),
+                                format!("Self-test thread {} issue {}", t, i),
+                            )
+                            .and_then(|issue| {
+                                store.append_issue(&issue).map_err(|e| e.to_string())
+                            });
+                            if let Err(e) = appended {
+                                failures.push(e);
+                            }
+                        }
+                        failures
+                    })
+                })
+                .collect();
+            for handle in handles {
+                match handle.join() {
+                    Ok(failures) => {
+                        failed_appends += failures.len();
+                        errors.extend(failures);
+                    }
+                    Err(_) => errors.push("writer thread panicked".to_string()),
+                }
+            }
+            match store.load_issues() {
+                Ok(issues) => found = issues.len(),
+                Err(err) => errors.push(format!("load: {}", err)),
+            }
+        }
+    }
+    let _ = std::fs::remove_dir_all(&scratch);
+
+    errors.dedup();
+    let lost_writes = expected.saturating_sub(found + failed_appends);
+    ConcurrencyReport {
+        passed: errors.is_empty() && found == expected,
+        lock_strategy,
+        threads,
+        per_thread,
+        expected,
+        found,
+        lost_writes,
+        failed_appends,
+        errors,
+    }
+}
+
+fn cmd_capabilities() -> CommandResult {
+    output_success(capabilities())
+}
+
+fn cmd_self_test(threads: usize, per_thread: usize) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Run inside .manna so the check hits the same filesystem as real data
+    let report = concurrency_self_test(&store.manna_dir(), store_config()?, threads, per_thread);
+    let exit_code = if report.passed {
+        EXIT_SUCCESS
+    } else {
+        EXIT_SYSTEM_ERROR
+    };
+    output_success_with_code(report, exit_code)
+}
+
+fn cmd_bulk_transition(status: Option<String>, to: String, force: bool) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Parse statuses
+    let from = match status.as_deref().map(parse_status).transpose() {
+        Ok(s) => s,
+        Err(e) => return output_error(&e, EXIT_USER_ERROR),
+    };
+    let to = match parse_status(&to) {
+        Ok(s) => s,
+        Err(e) => return output_error(&e, EXIT_USER_ERROR),
+    };
+
+    if !confirmed(force) {
+        return output_error(
+            "bulk-transition rewrites many issues at once; pass --force or -y to confirm",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Load issues, holding the lock through the rewrite
+    let guard = match store.lock_issues() {
+        Ok(g) => g,
+        Err(err) => return handle_manna_error(err),
+    };
+    let mut issues = match store.load_issues() {
+        Ok(i) => i,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    let session_id = get_session_id()?;
+    let before = issues.clone();
+    let (transitioned, skipped) = bulk_transition(&mut issues, from.as_ref(), &to, &session_id);
+
+    // Single rewrite for the whole batch
+    if !transitioned.is_empty() {
+        if let Err(err) = guard.replace_issues(&issues) {
+            return handle_manna_error(err);
+        }
+        drop(guard);
+        record_changes(&store, &get_session_id()?, &before, &issues);
+    }
+
+    output_success(BulkTransitionData {
+        to,
+        transitioned_count: transitioned.len(),
+        transitioned,
+        skipped_count: skipped.len(),
+        skipped,
+    })
+}
+
+fn cmd_bulk_delete(
+    status: Option<String>,
+    older_than: Option<String>,
+    all: bool,
+    dry_run: bool,
+    force: bool,
+) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // An unfiltered run would delete the whole store; make that explicit
+    if status.is_none() && older_than.is_none() && !all {
+        return output_error(
+            "bulk-delete needs a filter (--status, --older-than) or --all to delete every issue",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Parse filters
+    let status = match status.as_deref().map(parse_status).transpose() {
+        Ok(s) => s,
+        Err(e) => return output_error(&e, EXIT_USER_ERROR),
+    };
+    let cutoff = match older_than.as_deref().map(parse_cutoff).transpose() {
+        Ok(c) => c,
+        Err(e) => return output_error(&e, EXIT_USER_ERROR),
+    };
+
+    if !dry_run && !confirmed(force) {
+        return output_error(
+            "bulk-delete removes issues permanently; pass --force or -y to confirm, or preview with --dry-run",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Load issues, holding the lock through the rewrite
+    let guard = match store.lock_issues() {
+        Ok(g) => g,
+        Err(err) => return handle_manna_error(err),
+    };
+    let mut issues = match store.load_issues() {
+        Ok(i) => i,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    let (deleted, stripped_blockers) = bulk_delete(&mut issues, status.as_ref(), cutoff);
+
+    // Single rewrite for the whole batch
+    if !dry_run && !deleted.is_empty() {
+        if let Err(err) = guard.replace_issues(&issues) {
+            return handle_manna_error(err);
+        }
+        drop(guard);
+        record_deletes(&store, &get_session_id()?, &deleted);
+    }
+
+    output_success(BulkDeleteData {
+        dry_run,
+        deleted_count: deleted.len(),
+        deleted,
+        stripped_blockers,
+    })
+}
+
+/// Move an issue to the trash, or restore it when `trash` is false.
+fn cmd_trash(id: String, trash: bool) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Load issues
+    let issues = match store.load_issues() {
+        Ok(i) => i,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    // Find issue
+    let mut issue = find_issue(&issues, &id)?;
+
+    let result = if trash {
+        issue.trash()
+    } else {
+        issue.restore()
+    };
+    if let Err(e) = result {
+        return output_error(&e, EXIT_USER_ERROR);
+    }
+
+    // Update store
+    if let Err(err) = store.update_issue(&issue) {
+        return handle_manna_error(err);
+    }
+
+    record_event(
+        &store,
+        &SessionEvent::edit(
+            get_session_id()?,
+            issue.id.clone(),
+            serde_json::json!({ "deleted_at": issue.deleted_at }),
+        ),
+    );
+
+    output_success(IssueData { issue })
+}
+
+fn cmd_empty_trash(older_than: Option<String>, dry_run: bool, force: bool) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    let cutoff = match older_than.as_deref().map(parse_cutoff).transpose() {
+        Ok(c) => c,
+        Err(e) => return output_error(&e, EXIT_USER_ERROR),
+    };
+
+    if !dry_run && !confirmed(force) {
+        return output_error(
+            "empty-trash removes issues permanently; pass --force or -y to confirm, or preview with --dry-run",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Load issues, holding the lock through the rewrite
+    let guard = match store.lock_issues() {
+        Ok(g) => g,
+        Err(err) => return handle_manna_error(err),
+    };
+    let mut issues = match store.load_issues() {
+        Ok(i) => i,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    let (deleted, stripped_blockers) = empty_trash(&mut issues, cutoff);
+
+    // Single rewrite for the whole batch
+    if !dry_run && !deleted.is_empty() {
+        if let Err(err) = guard.replace_issues(&issues) {
+            return handle_manna_error(err);
+        }
+        drop(guard);
+        record_deletes(&store, &get_session_id()?, &deleted);
+    }
+
+    output_success(BulkDeleteData {
+        dry_run,
+        deleted_count: deleted.len(),
+        deleted,
+        stripped_blockers,
+    })
+}
+
+fn cmd_purge_sessions(
+    older_than: Option<String>,
+    keep_last: Option<usize>,
+    dry_run: bool,
+    force: bool,
+) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // clap guarantees exactly one policy
+    let retention = match (older_than.as_deref().map(parse_cutoff), keep_last) {
+        (Some(Ok(cutoff)), _) => SessionRetention::Since(cutoff),
+        (Some(Err(e)), _) => return output_error(&e, EXIT_USER_ERROR),
+        (None, Some(n)) => SessionRetention::KeepLast(n),
+        (None, None) => return output_error("Pass --older-than or --keep-last", EXIT_USER_ERROR),
+    };
+
+    if !dry_run && !confirmed(force) {
+        return output_error(
+            "purge-sessions discards session events permanently; pass --force or -y to confirm, or preview with --dry-run",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Load session log, holding the lock through the rewrite so events
+    // appended meanwhile are not lost
+    let guard = match store.lock_sessions() {
+        Ok(g) => g,
+        Err(err) => return handle_manna_error(err),
+    };
+    let mut events = match store.load_sessions() {
+        Ok(e) => e,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    let (purged, preserved_starts) = purge_sessions(&mut events, retention);
+
+    // Single rewrite for the whole log
+    if !dry_run && purged > 0 {
+        if let Err(err) = guard.replace_sessions(&events) {
+            return handle_manna_error(err);
+        }
+    }
+
+    output_success(PurgeSessionsData {
+        dry_run,
+        purged,
+        kept: events.len(),
+        preserved_starts,
+    })
+}
+
+fn cmd_replay(force: bool, allow_incomplete: bool) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    // A lost issues.jsonl is exactly the case this recovers from, so only
+    // the directory itself has to exist
+    if let Err(err) = store.repair() {
+        return handle_manna_error(err);
+    }
+
+    if !confirmed(force) {
+        return output_error(
+            "replay overwrites issues.jsonl with state rebuilt from the session log; pass --force or -y to confirm",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    let events = match store.load_sessions() {
+        Ok(e) => e,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    let replayed = replay::replay(&events);
+
+    // On a live store, anything the log does not reproduce would be
+    // silently rewritten; a lost or empty issues file has nothing to lose
+    let guard = match store.lock_issues() {
+        Ok(g) => g,
+        Err(err) => return handle_manna_error(err),
+    };
+    let current = match store.load_issues() {
+        Ok(i) => i,
+        Err(err) => return handle_manna_error(err),
+    };
+    let diverged = replay::diverged(&current, &replayed.issues);
+    if !current.is_empty() && !diverged.is_empty() && !allow_incomplete {
+        return output_error(
+            &format!(
+                "The session log does not reproduce {} issue(s) ({}); replay would rewrite them. Pass --allow-incomplete to replace them anyway",
+                diverged.len(),
+                diverged.join(", ")
+            ),
+            EXIT_USER_ERROR,
+        );
+    }
+
+    if let Err(err) = guard.replace_issues(&replayed.issues) {
+        return handle_manna_error(err);
+    }
+
+    output_success(ReplayData {
+        replayed_events: events.len(),
+        skipped_events: replayed.skipped_events,
+        issue_count: replayed.issues.len(),
+        diverged: if current.is_empty() {
+            Vec::new()
+        } else {
+            diverged
+        },
+    })
+}
+
+fn cmd_squash(keep_id: String, merge_ids: Vec<String>, delete: bool) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Load issues, holding the lock so the cycle check sees what is written
+    let guard = match store.lock_issues() {
+        Ok(g) => g,
+        Err(err) => return handle_manna_error(err),
+    };
+    let mut issues = match store.load_issues() {
+        Ok(i) => i,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    // Drop repeated IDs, keeping the order given
+    let mut seen = HashSet::new();
+    let mut merge_ids = merge_ids;
+    merge_ids.retain(|id| seen.insert(id.clone()));
+
+    let session_id = get_session_id()?;
+    let before = issues.clone();
+    let redirected = match squash(&mut issues, &keep_id, &merge_ids, delete, &session_id) {
+        Ok(r) => r,
+        Err(e) => return output_error(&e, EXIT_USER_ERROR),
+    };
+
+    // Single rewrite for the whole merge
+    if let Err(err) = guard.replace_issues(&issues) {
+        return handle_manna_error(err);
+    }
+    drop(guard);
+
+    for id in &merge_ids {
+        record_event(
+            &store,
+            &SessionEvent::note(
+                session_id.clone(),
+                id.clone(),
+                format!("merged into {}", keep_id),
+            ),
+        );
+    }
+    record_changes(&store, &get_session_id()?, &before, &issues);
+
+    output_success(SquashData {
+        issue: find_issue(&issues, &keep_id)?,
+        merged: merge_ids,
+        deleted: delete,
+        redirected,
+    })
+}
+
+fn cmd_unassign_all(assignee: String, force: bool) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    if !confirmed(force) {
+        return output_error(
+            "unassign-all clears every assignment for the assignee; pass --force or -y to confirm",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Load issues, holding the lock through the rewrite
+    let guard = match store.lock_issues() {
+        Ok(g) => g,
+        Err(err) => return handle_manna_error(err),
+    };
+    let mut issues = match store.load_issues() {
+        Ok(i) => i,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    let before = issues.clone();
+    let unassigned = unassign_all(&mut issues, &assignee);
+
+    // Single rewrite for the whole batch
+    if !unassigned.is_empty() {
+        if let Err(err) = guard.replace_issues(&issues) {
+            return handle_manna_error(err);
+        }
+        drop(guard);
+        record_changes(&store, &get_session_id()?, &before, &issues);
+    }
+
+    output_success(UnassignAllData {
+        assignee,
+        unassigned_count: unassigned.len(),
+        unassigned,
+    })
+}
+
+fn cmd_config() -> CommandResult {
+    let cwd = match std::env::current_dir() {
+        Ok(dir) => dir,
+        Err(err) => return handle_manna_error(err.into()),
+    };
+
+    // Rejects an invalid MANNA_LOCK_STRATEGY or MANNA_ID_LENGTH before it
+    // is reported
+    let store = MannaStore::with_config(&cwd, store_config()?);
+
+    output_success(resolve_config(&store)?)
+}
+
+fn cmd_normalize() -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    let (records, changed) = match store.normalize_issues() {
+        Ok(r) => r,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    output_success(NormalizeData { records, changed })
+}
+
+fn cmd_migrate_layout() -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    let per_issue = match store.load_project_config() {
+        Ok(c) => c.per_issue_files.unwrap_or(false),
+        Err(err) => return handle_manna_error(err),
+    };
+    let (issues, changed) = match store.migrate_layout(per_issue) {
+        Ok(r) => r,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    output_success(MigrateLayoutData {
+        layout: if per_issue {
+            "per_issue_files"
+        } else {
+            "jsonl"
+        },
+        issues,
+        changed,
+    })
+}
+
+fn cmd_verify_checksum(update: bool, strict: bool) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    if update {
+        if let Err(err) = store.update_checksum() {
+            return handle_manna_error(err);
+        }
+    }
+
+    let status = match store.verify_checksum() {
+        Ok(s) => s,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    if !status.is_match() {
+        let message = if store.per_issue_layout() {
+            ".manna/issues/ changed outside manna (checksum mismatch)"
+        } else {
+            "issues.jsonl changed outside manna (checksum mismatch)"
+        };
+        if strict {
+            return output_error(message, EXIT_USER_ERROR);
+        }
+        eprintln!("Warning: {}", message);
+    }
+
+    output_success(ChecksumData {
+        enabled: status.expected.is_some(),
+        matches: status.is_match(),
+        expected: status.expected,
+        actual: status.actual,
+    })
+}
+
+fn cmd_export(format: ExportFormat, output: Option<String>) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Load issues
+    let issues = match store.load_issues() {
+        Ok(i) => i,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    // Encode
+    let encoded = match format {
+        ExportFormat::Jsonl => export::to_jsonl(&issues),
+        ExportFormat::Msgpack => export::to_msgpack(&issues),
+    };
+    let bytes = match encoded {
+        Ok(b) => b,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    match output {
+        Some(path) => {
+            if let Err(e) = std::fs::write(&path, &bytes) {
+                return handle_manna_error(e.into());
+            }
+            output_success(ExportData {
+                exported: issues.len(),
+                path,
+            })
+        }
+        None => {
+            // Raw payload on stdout, no YAML envelope
+            output_raw(bytes, EXIT_SUCCESS)
+        }
+    }
+}
+
+fn cmd_import(format: ExportFormat, input: Option<String>) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Read payload
+    let read = match input {
+        Some(path) => std::fs::read(path),
+        None => {
+            let mut buf = Vec::new();
+            std::io::stdin().read_to_end(&mut buf).map(|_| buf)
+        }
+    };
+    let bytes = match read {
+        Ok(b) => b,
+        Err(e) => return handle_manna_error(e.into()),
+    };
+
+    // Decode
+    let decoded = match format {
+        ExportFormat::Jsonl => export::from_jsonl(&bytes),
+        ExportFormat::Msgpack => export::from_msgpack(&bytes),
+    };
+    let incoming = match decoded {
+        Ok(i) => i,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    // Validate everything before writing anything
+    for issue in &incoming {
+        if let Err(e) = issue.validate() {
+            return output_error(
+                &format!("Invalid issue {}: {}", issue.id, e),
+                EXIT_USER_ERROR,
+            );
+        }
+    }
+
+    let mut existing_ids: HashSet<String> = match store.load_issues() {
+        Ok(issues) => issues.into_iter().map(|i| i.id).collect(),
+        Err(err) => return handle_manna_error(err),
+    };
+
+    // Append new issues, skipping IDs already present
+    let mut imported = Vec::new();
+    let mut skipped = Vec::new();
+    for issue in incoming {
+        if !existing_ids.insert(issue.id.clone()) {
+            skipped.push(issue.id);
+            continue;
+        }
+        if let Err(err) = store.append_issue(&issue) {
+            return handle_manna_error(err);
+        }
+        imported.push(issue);
+    }
+    record_changes(&store, &get_session_id()?, &[], &imported);
+    let imported = imported.into_iter().map(|i| i.id).collect();
+
+    output_success(ImportData { imported, skipped })
+}
+
+fn cmd_merge_stores(other_dir: String, strategy: MergeStrategy) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    let other = MannaStore::with_config(Path::new(&other_dir), store_config()?);
+    if !other.is_initialized() {
+        return output_error(
+            &format!("No initialized .manna store in {}", other_dir),
+            EXIT_USER_ERROR,
+        );
+    }
+    let same = match (
+        store.manna_dir().canonicalize(),
+        other.manna_dir().canonicalize(),
+    ) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    };
+    if same {
+        return output_error("Cannot merge a store into itself", EXIT_USER_ERROR);
+    }
+
+    // Load both sides, holding this store's lock through the rewrite
+    let guard = match store.lock_issues() {
+        Ok(g) => g,
+        Err(err) => return handle_manna_error(err),
+    };
+    let mut issues = match store.load_issues() {
+        Ok(i) => i,
+        Err(err) => return handle_manna_error(err),
+    };
+    let incoming = match other.load_issues() {
+        Ok(i) => i,
+        Err(err) => return handle_manna_error(err),
+    };
+    let mut events = match other.load_sessions() {
+        Ok(e) => e,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    let data = merge_stores(
+        &mut issues,
+        incoming,
+        &mut events,
+        strategy,
+        store.id_length(),
+        &get_session_id()?,
+    );
+
+    // Single rewrite for the whole batch
+    if let Err(err) = guard.replace_issues(&issues) {
+        return handle_manna_error(err);
+    }
+    drop(guard);
+    if let Err(err) = store.append_sessions(&events) {
+        return handle_manna_error(err);
+    }
+
+    output_success(data)
+}
+
+fn cmd_reindex_ids(prefix: Option<String>, force: bool) -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    if !confirmed(force) {
+        return output_error(
+            "reindex-ids rewrites issue IDs and every reference to them; pass --force or -y to confirm",
+            EXIT_USER_ERROR,
+        );
+    }
+
+    // Load issues, holding the lock through the rewrite
+    let guard = match store.lock_issues() {
+        Ok(g) => g,
+        Err(err) => return handle_manna_error(err),
+    };
+    let mut issues = match store.load_issues() {
+        Ok(i) => i,
+        Err(err) => return handle_manna_error(err),
+    };
+
+    let reindexed = reindex_ids(&mut issues, prefix.as_deref(), store.id_length());
+
+    // Single rewrite for the whole batch
+    if !reindexed.is_empty() {
+        if let Err(err) = guard.replace_issues(&issues) {
+            return handle_manna_error(err);
+        }
+    }
+    drop(guard);
+
+    // The session log keeps old IDs; link each issue's history to its new one
+    let session_id = get_session_id()?;
+    for pair in &reindexed {
+        record_event(
+            &store,
+            &SessionEvent::note(
+                session_id.clone(),
+                pair.to.clone(),
+                format!("reindexed from {}", pair.from),
+            ),
+        );
+    }
+
+    output_success(ReindexIdsData {
+        reindexed_count: reindexed.len(),
+        reindexed,
+    })
+}
+
+/// Split a `repl` line into words, shell style: whitespace separates words,
+/// single quotes take everything literally, and a backslash escapes the next
+/// character outside single quotes.
+fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote: Option<char> = None;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('\''), c) => word.get_or_insert_with(String::new).push(c),
+            (_, '\\') => {
+                let escaped = chars
+                    .next()
+                    .ok_or_else(|| "Trailing backslash".to_string())?;
+                word.get_or_insert_with(String::new).push(escaped);
+            }
+            (Some(_), c) => word.get_or_insert_with(String::new).push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    if let Some(q) = quote {
+        return Err(format!("Unclosed {} quote", q));
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// Print an error response for a `repl` line without leaving the REPL.
+fn repl_error(error: &str) {
+    println!(
+        "{}",
+        render_response(&ErrorResponse {
+            success: false,
+            error: error.to_string(),
+            transition: None,
+        })
+    );
+}
+
+/// Run one parsed `repl` command and print its response.
+fn run_in_repl(command: Commands) {
+    // Each command's `--profile` report covers that command alone, not
+    // the time spent waiting for input or running earlier commands
+    if profile_start().is_some() {
+        set_profile_start(Some(std::time::Instant::now()));
+        reset_store_timings();
+    }
+    emit(run(command));
+}
+
+fn cmd_repl() -> CommandResult {
+    let store = MannaStore::with_config(Path::new("."), store_config()?);
+
+    if !store.is_initialized() {
+        return output_error(
+            "Storage not initialized. Run 'manna-core init' first.",
+            EXIT_USER_ERROR,
+        );
+    }
+    let _ = IN_REPL.set(true);
+
+    // Responses form a YAML stream, one document per command
+    let separate = output_format() == OutputFormat::Yaml;
+    for line in std::io::stdin().lock().lines() {
+        let line = match line {
+            Ok(l) => l,
+            Err(err) => {
+                return output_error(&format!("Failed to read input: {}", err), EXIT_SYSTEM_ERROR)
+            }
+        };
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if separate {
+            println!("---");
+        }
+        match split_words(line).and_then(|words| {
+            ReplLine::try_parse_from(words).map_err(|e| e.to_string().trim_end().to_string())
+        }) {
+            Ok(ReplLine {
+                command: Commands::Repl,
+            }) => repl_error("repl cannot be nested"),
+            Ok(ReplLine { command }) => run_in_repl(command),
+            Err(e) => repl_error(&e),
+        }
+    }
+
+    // Every command has reported its own timings
+    set_profile_start(None);
+    output_raw(Vec::new(), EXIT_SUCCESS)
+}
+
+// ============================================================================
+// Main Entry Point
+// ============================================================================
+
+fn main() {
+    let cli = Cli::parse();
+    let _ = OUTPUT_FORMAT.set(cli.format);
+    let _ = EXPLICIT_NULLS.set(cli.explicit_nulls);
+    let _ = TIME_FORMAT.set(cli.time_format);
+    let _ = ASSUME_YES.set(cli.assume_yes);
+    let _ = MAX_RESULTS.set(cli.max_results);
+    let _ = EXIT_NONZERO_ON_EMPTY.set(cli.exit_nonzero_on_empty);
+    if cli.profile {
+        set_profile_start(Some(std::time::Instant::now()));
+    }
+
+    std::process::exit(emit(run(cli.command)));
+}
+
+/// Run one command, returning its response for the caller to print.
+fn run(command: Commands) -> CommandResult {
+    match command {
+        Commands::Init => cmd_init(false),
+        Commands::RepairInit => cmd_init(true),
+        Commands::Status => cmd_status(),
+        Commands::Brief { top } => cmd_brief(top),
+        Commands::SessionStart => cmd_session_event(false),
+        Commands::SessionEnd => cmd_session_event(true),
+        Commands::ActiveSessions => cmd_active_sessions(),
+        Commands::Create {
+            title,
+            description,
+            due,
+        } => cmd_create(title, description, due),
+        Commands::Claim {
+            id,
+            force,
+            force_anyway,
+            assign_self,
+            note,
+        } => cmd_claim(id, force, force_anyway, assign_self, note),
+        Commands::ClaimBatch { count, wait } => cmd_claim_batch(count, wait),
+        Commands::Done { id, force } => cmd_done(id, force),
+        Commands::Reopen { id } => cmd_reopen(id),
+        // clap guarantees exactly one of <id> and --all
+        Commands::Abandon { id, force, .. } => match id {
+            Some(id) => cmd_abandon(id, force),
+            None => cmd_abandon_all(),
+        },
+        Commands::Rank { id, before, after } => cmd_rank(id, before, after),
+        Commands::Reparent { id, parent } => cmd_reparent(id, parent),
+        Commands::Attach { id, path, desc } => cmd_attach(id, path, desc),
+        Commands::Detach { id, path } => cmd_detach(id, path),
+        Commands::Block {
+            id,
+            blocker_ids,
+            create_stub,
+            warn_redundant,
+            skip_redundant,
+        } => cmd_block(id, blocker_ids, create_stub, warn_redundant, skip_redundant),
+        Commands::Unblock { id, blocker_id } => cmd_unblock(id, blocker_id),
+        Commands::List(args) => cmd_list(args),
+        Commands::Show {
+            id,
+            limit_description,
+            plain,
+            follow_blockers,
+        } => cmd_show(id, limit_description, plain, follow_blockers),
+        Commands::Explain { id } => cmd_explain(id),
+        Commands::Blame { id } => cmd_blame(id),
+        Commands::Context {
+            max_tokens,
+            sections,
+            exclude_status,
+            sort_blocked_last,
+            structured,
+            budget_report,
+            since_generation,
+        } => cmd_context(
+            max_tokens,
+            sections,
+            exclude_status,
+            sort_blocked_last,
+            structured,
+            budget_report,
+            since_generation,
+        ),
+        Commands::Summary { plain } => cmd_summary(plain),
+        Commands::Blocked => cmd_blocked(),
+        Commands::Overdue => cmd_overdue(),
+        Commands::Metrics => cmd_metrics(),
+        Commands::Report => cmd_report(),
+        Commands::Completed { group_by_day } => cmd_completed(group_by_day),
+        Commands::Velocity { window } => cmd_velocity(window),
+        Commands::FindDuplicates { threshold } => cmd_find_duplicates(threshold),
+        Commands::Trash { id } => cmd_trash(id, true),
+        Commands::Restore { id } => cmd_trash(id, false),
+        Commands::EmptyTrash {
+            older_than,
+            dry_run,
+            force,
+        } => cmd_empty_trash(older_than, dry_run, force),
+        Commands::PurgeSessions {
+            older_than,
+            keep_last,
+            dry_run,
+            force,
+        } => cmd_purge_sessions(older_than, keep_last, dry_run, force),
+        Commands::Replay {
+            force,
+            allow_incomplete,
+        } => cmd_replay(force, allow_incomplete),
+        Commands::Squash {
+            keep_id,
+            merge_ids,
+            delete,
+        } => cmd_squash(keep_id, merge_ids, delete),
+        Commands::RelinkBlockers { prune } => cmd_relink_blockers(prune),
+        Commands::Doctor => cmd_doctor(),
+        Commands::SelfTest {
+            concurrency: _,
+            threads,
+            per_thread,
+        } => cmd_self_test(threads, per_thread),
+        Commands::BulkTransition { status, to, force } => cmd_bulk_transition(status, to, force),
+        Commands::BulkDelete {
+            status,
+            older_than,
+            all,
+            dry_run,
+            force,
+        } => cmd_bulk_delete(status, older_than, all, dry_run, force),
+        Commands::UnassignAll { assignee, force } => cmd_unassign_all(assignee, force),
+        Commands::Config => cmd_config(),
+        Commands::Capabilities => cmd_capabilities(),
+        Commands::Normalize => cmd_normalize(),
+        Commands::VerifyChecksum { update, strict } => cmd_verify_checksum(update, strict),
+        Commands::Export { encoding, output } => cmd_export(encoding, output),
+        Commands::Import { encoding, input } => cmd_import(encoding, input),
+        Commands::MergeStores {
+            other_dir,
+            strategy,
+        } => cmd_merge_stores(other_dir, strategy),
+        Commands::ReindexIds { prefix, force } => cmd_reindex_ids(prefix, force),
+        Commands::MigrateLayout => cmd_migrate_layout(),
+        Commands::Repl => cmd_repl(),
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use tempfile::TempDir;
+
+    // Mutex to serialize tests that modify MANNA_SESSION_ID env var
+    static ENV_MUTEX: Mutex<()> = Mutex::new(());
+
+    fn setup_store() -> (TempDir, MannaStore) {
+        let temp_dir = TempDir::new().unwrap();
+        let store = MannaStore::new(temp_dir.path());
+        store.init().unwrap();
+        (temp_dir, store)
+    }
+
+    #[test]
+    fn test_get_session_id_default() {
+        let _lock = ENV_MUTEX.lock().unwrap();
+        // Clear env var if set
+        std::env::remove_var("MANNA_SESSION_ID");
+
+        let session_id = get_session_id().unwrap();
+        assert!(session_id.as_str().starts_with("ses_pid"));
+    }
+
+    #[test]
+    fn test_get_session_id_from_env() {
+        let _lock = ENV_MUTEX.lock().unwrap();
+        std::env::set_var("MANNA_SESSION_ID", "ses_test_123");
+        let session_id = get_session_id().unwrap();
+        assert_eq!(session_id, "ses_test_123");
+        std::env::remove_var("MANNA_SESSION_ID");
+    }
+
+    #[test]
+    fn test_generate_session_id_host_format() {
+        let id1 = generate_session_id("host");
+        let id2 = generate_session_id("host");
+
+        let parts: Vec<&str> = id1.split('_').collect();
+        assert_eq!(parts.len(), 4);
+        assert_eq!(parts[0], "ses");
+        assert_eq!(parts[1], hostname());
+        assert_eq!(parts[2], std::process::id().to_string());
+        assert_eq!(parts[3].len(), 8);
+        assert!(parts[3].chars().all(|c| c.is_ascii_hexdigit()));
+
+        // Same host and pid, but the random component keeps them apart
+        assert_ne!(id1, id2);
+    }
+
+    #[test]
+    fn test_generate_session_id_default_format() {
+        assert!(generate_session_id("").starts_with("ses_pid"));
+        assert!(generate_session_id("pid").starts_with("ses_pid"));
+    }
+
+    #[test]
+    fn test_parse_status_valid() {
+        assert_eq!(parse_status("open").unwrap(), IssueStatus::Open);
+        assert_eq!(
+            parse_status("in_progress").unwrap(),
+            IssueStatus::InProgress
+        );
+        assert_eq!(parse_status("blocked").unwrap(), IssueStatus::Blocked);
+        assert_eq!(parse_status("done").unwrap(), IssueStatus::Done);
+        // Case insensitive
+        assert_eq!(parse_status("OPEN").unwrap(), IssueStatus::Open);
+        assert_eq!(
+            parse_status("In_Progress").unwrap(),
+            IssueStatus::InProgress
+        );
+    }
+
+    #[test]
+    fn test_parse_status_invalid() {
+        let result = parse_status("invalid");
+        assert!(result.is_err());
+        assert!(result.unwrap_err().contains("Invalid status"));
+    }
+
+    #[test]
+    fn test_error_to_exit_code_user_errors() {
+        assert_eq!(
+            error_to_exit_code(&MannaError::IssueNotFound("x".to_string())),
+            EXIT_USER_ERROR
+        );
+        assert_eq!(
+            error_to_exit_code(&MannaError::IssueAlreadyExists("x".to_string())),
+            EXIT_USER_ERROR
+        );
+        assert_eq!(
+            error_to_exit_code(&MannaError::InvalidId("x".to_string())),
+            EXIT_USER_ERROR
+        );
+        assert_eq!(
+            error_to_exit_code(&MannaError::NotInitialized),
+            EXIT_USER_ERROR
+        );
+    }
+
+    #[test]
+    fn test_error_to_exit_code_system_errors() {
+        assert_eq!(
+            error_to_exit_code(&MannaError::LockFailed("x".to_string())),
+            EXIT_SYSTEM_ERROR
+        );
+    }
+
+    #[test]
+    fn test_find_issue_found() {
+        let issues = vec![
+            Issue::new("mn-abc123".to_string(), "Test 1".to_string()).unwrap(),
+            Issue::new("mn-def456".to_string(), "Test 2".to_string()).unwrap(),
+        ];
+
+        let found = find_issue(&issues, "mn-def456").unwrap();
+        assert_eq!(found.id, "mn-def456");
+        assert_eq!(found.title, "Test 2");
+    }
+
+    #[test]
+    fn test_check_claim_owner_allows_owner() {
+        let mut issue = Issue::new("mn-own001".to_string(), "Owned".to_string()).unwrap();
+        issue.claim(SessionId::new("ses_owner").unwrap()).unwrap();
+
+        assert!(check_claim_owner(&issue, &SessionId::new("ses_owner").unwrap(), false).is_ok());
+    }
+
+    #[test]
+    fn test_check_claim_owner_rejects_other_session() {
+        let mut issue = Issue::new("mn-own002".to_string(), "Owned".to_string()).unwrap();
+        issue.claim(SessionId::new("ses_owner").unwrap()).unwrap();
+
+        let result = check_claim_owner(&issue, &SessionId::new("ses_intruder").unwrap(), false);
+        assert!(result.is_err());
+        assert!(result.unwrap_err().contains("claimed by ses_owner"));
+    }
+
+    #[test]
+    fn test_check_claim_owner_force_override() {
+        let mut issue = Issue::new("mn-own003".to_string(), "Owned".to_string()).unwrap();
+        issue.claim(SessionId::new("ses_owner").unwrap()).unwrap();
+
+        assert!(check_claim_owner(&issue, &SessionId::new("ses_admin").unwrap(), true).is_ok());
+    }
+
+    #[test]
+    fn test_issue_summary_serialization() {
+        let summary = IssueSummary {
+            id: "mn-abc123".to_string(),
+            title: "Test".to_string(),
+            status: IssueStatus::Open,
+            claimed_by: None,
+        };
+
+        let yaml = serde_yaml::to_string(&summary).unwrap();
+        assert!(yaml.contains("id: mn-abc123"));
+        assert!(yaml.contains("title: Test"));
+        assert!(yaml.contains("status: open"));
+        // claimed_by should be skipped when None
+        assert!(!yaml.contains("claimed_by"));
+    }
+
+    #[test]
+    fn test_brief_has_every_section() {
+        let session = SessionId::new("ses_brief").unwrap();
+        let mut mine = Issue::new("mn-b1e001".to_string(), "Mine".to_string()).unwrap();
+        mine.claim(session.clone()).unwrap();
+        let mut theirs = Issue::new("mn-b1e002".to_string(), "Theirs".to_string()).unwrap();
+        theirs.claim(SessionId::new("ses_other").unwrap()).unwrap();
+        let mut ready: Vec<Issue> = (3..6)
+            .map(|n| Issue::new(format!("mn-b1e00{}", n), "Ready".to_string()).unwrap())
+            .collect();
+        for (n, issue) in ready.iter_mut().enumerate() {
+            issue.created_at -= chrono::Duration::minutes(10 - n as i64);
+        }
+        let mut waiting = Issue::new("mn-b1e006".to_string(), "Waiting".to_string()).unwrap();
+        waiting.add_blocker("mn-b1e002".to_string());
+        let mut issues = vec![mine, theirs, waiting];
+        issues.extend(ready);
+
+        let data = brief(&issues, session, 2);
+
+        let claimed: Vec<&str> = data.claimed_issues.iter().map(|i| i.id.as_str()).collect();
+        assert_eq!(claimed, vec!["mn-b1e001"]);
+        assert_eq!(data.ready_count, 3);
+        let ready: Vec<&str> = data.ready.iter().map(|i| i.id.as_str()).collect();
+        assert_eq!(ready, vec!["mn-b1e003", "mn-b1e004"]);
+        assert_eq!(
+            (
+                data.counts.open,
+                data.counts.in_progress,
+                data.counts.blocked
+            ),
+            (3, 2, 1)
+        );
+
+        let value: serde_json::Value = serde_json::to_value(&data).unwrap();
+        for section in [
+            "session_id",
+            "claimed_issues",
+            "ready_count",
+            "ready",
+            "counts",
+        ] {
+            assert!(value.get(section).is_some(), "missing {}", section);
+        }
+    }
+
+    #[test]
+    fn test_issue_summary_with_claimed_by() {
+        let summary = IssueSummary {
+            id: "mn-abc123".to_string(),
+            title: "Test".to_string(),
+            status: IssueStatus::InProgress,
+            claimed_by: Some("ses_123".to_string()),
+        };
+
+        let yaml = serde_yaml::to_string(&summary).unwrap();
+        assert!(yaml.contains("claimed_by: ses_123"));
+    }
+
+    #[test]
+    fn test_success_response_serialization() {
+        let response = SuccessResponse {
+            success: true,
+            data: InitData {
+                initialized: true,
+                path: ".manna".to_string(),
+                created: Vec::new(),
+            },
+        };
+
+        let yaml = serde_yaml::to_string(&response).unwrap();
+        assert!(yaml.contains("success: true"));
+        assert!(yaml.contains("initialized: true"));
+        assert!(yaml.contains("path: .manna"));
+    }
+
+    #[test]
+    fn test_render_env_scalar_fields() {
+        let mut issue =
+            Issue::new("mn-env001".to_string(), "It's $HOME `now`".to_string()).unwrap();
+        issue.add_blocker("mn-env002".to_string());
+
+        let response = SuccessResponse {
+            success: true,
+            data: IssueData { issue },
+        };
+        let env = render_env(&serde_json::to_value(&response).unwrap());
+        let lines: Vec<&str> = env.lines().collect();
+
+        assert!(lines.contains(&"MANNA_SUCCESS='true'"));
+        assert!(lines.contains(&"MANNA_ID='mn-env001'"));
+        assert!(lines.contains(&"MANNA_STATUS='blocked'"));
+        assert!(lines.contains(&r#"MANNA_TITLE='It'\''s $HOME `now`'"#));
+        // Collections are skipped
+        assert!(!env.contains("BLOCKED_BY"));
+
+        // Every line is NAME='...' with a shell-safe name
+        for line in lines {
+            let (name, value) = line.split_once('=').unwrap();
+            assert!(name
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c == '_' || c.is_ascii_digit()));
+            assert!(value.starts_with('\'') && value.ends_with('\''));
+        }
+    }
+
+    #[test]
+    fn test_transition_error_response_is_structured() {
+        let mut done = Issue::new("mn-e4a001".to_string(), "Finished".to_string()).unwrap();
+        done.claim(SessionId::new("ses").unwrap()).unwrap();
+        done.complete().unwrap();
+
+        let err = done.claim(SessionId::new("ses").unwrap()).unwrap_err();
+        let value = serde_json::to_value(error_response(&err)).unwrap();
+
+        assert_eq!(value["success"], false);
+        assert_eq!(value["transition"]["from"], "done");
+        assert_eq!(value["transition"]["to"], "in_progress");
+        assert_eq!(value["transition"]["reason"], "wrong_status");
+        assert_eq!(error_to_exit_code(&err), EXIT_USER_ERROR);
+
+        let other = error_response(&MannaError::NotInitialized);
+        assert!(serde_json::to_value(other)
+            .unwrap()
+            .get("transition")
+            .is_none());
+    }
+
+    #[test]
+    fn test_error_response_serialization() {
+        let response = ErrorResponse {
+            success: false,
+            error: "Test error".to_string(),
+            transition: None,
+        };
+
+        let yaml = serde_yaml::to_string(&response).unwrap();
+        assert!(yaml.contains("success: false"));
+        assert!(yaml.contains("error: Test error"));
+    }
+
+    // Integration tests using temp directory
+    #[test]
+    fn test_store_init_and_load() {
+        let (_temp_dir, store) = setup_store();
+
+        assert!(store.is_initialized());
+        let issues = store.load_issues().unwrap();
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn test_create_and_retrieve_issue() {
+        let (_temp_dir, store) = setup_store();
+
+        let issue = Issue::new("mn-test01".to_string(), "Test Issue".to_string()).unwrap();
+        store.append_issue(&issue).unwrap();
+
+        let issues = store.load_issues().unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].id, "mn-test01");
+        assert_eq!(issues[0].title, "Test Issue");
+    }
+
+    #[test]
+    fn test_claim_and_release_workflow() {
+        let (_temp_dir, store) = setup_store();
+
+        let mut issue = Issue::new("mn-claim1".to_string(), "Claim Test".to_string()).unwrap();
+        store.append_issue(&issue).unwrap();
+
+        // Claim
+        issue.claim(SessionId::new("ses_test").unwrap()).unwrap();
+        store.update_issue(&issue).unwrap();
+
+        let issues = store.load_issues().unwrap();
+        assert_eq!(issues[0].status, IssueStatus::InProgress);
+        assert_eq!(issues[0].claimed_by, Some("ses_test".to_string()));
+
+        // Release
+        issue.release().unwrap();
+        store.update_issue(&issue).unwrap();
+
+        let issues = store.load_issues().unwrap();
+        assert_eq!(issues[0].status, IssueStatus::Open);
+        assert!(issues[0].claimed_by.is_none());
+    }
+
+    #[test]
+    fn test_complete_workflow() {
+        let (_temp_dir, store) = setup_store();
+
+        let mut issue = Issue::new("mn-done01".to_string(), "Complete Test".to_string()).unwrap();
+        store.append_issue(&issue).unwrap();
+
+        issue.claim(SessionId::new("ses_test").unwrap()).unwrap();
+        store.update_issue(&issue).unwrap();
+
+        issue.complete().unwrap();
+        store.update_issue(&issue).unwrap();
+
+        let issues = store.load_issues().unwrap();
+        assert_eq!(issues[0].status, IssueStatus::Done);
+    }
+
+    #[test]
+    fn test_reopen_workflow() {
+        let (_temp_dir, store) = setup_store();
+
+        let mut issue = Issue::new("mn-5e0001".to_string(), "Reopen Test".to_string()).unwrap();
+        issue.claim(SessionId::new("ses_test").unwrap()).unwrap();
+        issue.complete().unwrap();
+        store.append_issue(&issue).unwrap();
+        let completed_at = issue.updated_at;
+
+        issue.reopen().unwrap();
+        store.update_issue(&issue).unwrap();
+
+        let issues = store.load_issues().unwrap();
+        assert_eq!(issues[0].status, IssueStatus::Open);
+        assert!(issues[0].claimed_by.is_none());
+        assert!(issues[0].claimed_at.is_none());
+        assert!(issues[0].updated_at >= completed_at);
+    }
+
+    #[test]
+    fn test_reopen_rejects_unfinished_issue() {
+        let mut issue = Issue::new("mn-5e0002".to_string(), "Still open".to_string()).unwrap();
+
+        let err = issue.reopen().unwrap_err();
+        assert_eq!(error_to_exit_code(&err), EXIT_USER_ERROR);
+        assert_eq!(issue.status, IssueStatus::Open);
+
+        issue.claim(SessionId::new("ses_test").unwrap()).unwrap();
+        assert!(issue.reopen().is_err());
+        assert_eq!(issue.claimed_by.as_deref(), Some("ses_test"));
+    }
+
+    #[test]
+    fn test_config_reports_env_overrides() {
+        let _lock = ENV_MUTEX.lock().unwrap();
+        let (_temp_dir, store) = setup_store();
+
+        std::env::set_var("MANNA_LOCK_STRATEGY", "lockfile");
+        std::env::set_var("MANNA_SESSION_ID", "ses_config");
+        let config = resolve_config(&store).unwrap();
+        std::env::remove_var("MANNA_LOCK_STRATEGY");
+        std::env::remove_var("MANNA_SESSION_ID");
+
+        assert_eq!(config.lock_strategy.value, "lockfile");
+        assert_eq!(config.lock_strategy.source, "env MANNA_LOCK_STRATEGY");
+        assert_eq!(config.session_id.value, "ses_config");
+        assert_eq!(config.session_id.source, "env MANNA_SESSION_ID");
+        assert_eq!(config.normalize_titles.source, "default");
+        assert_eq!(config.title_limit.value, "500");
+        assert!(config.storage_dir.value.ends_with(".manna"));
+    }
+
+    #[test]
+    fn test_create_stores_normalized_title() {
+        let _lock = ENV_MUTEX.lock().unwrap();
+        std::env::set_var("MANNA_NORMALIZE_TITLES", "1");
+        let enabled = env_flag("MANNA_NORMALIZE_TITLES");
+        std::env::remove_var("MANNA_NORMALIZE_TITLES");
+        assert!(enabled);
+        assert!(!env_flag("MANNA_NORMALIZE_TITLES"));
+
+        let (_temp_dir, store) = setup_store();
+        let title = Issue::normalize_title("Fix  login   bug");
+        let issue = Issue::new("mn-e0a001".to_string(), title).unwrap();
+        store.append_issue(&issue).unwrap();
+
+        let issues = store.load_issues().unwrap();
+        assert_eq!(issues[0].title, "Fix login bug");
+    }
+
+    #[test]
+    fn test_block_workflow() {
+        let (_temp_dir, store) = setup_store();
+
+        let blocker = Issue::new("mn-block1".to_string(), "Blocker".to_string()).unwrap();
+        store.append_issue(&blocker).unwrap();
+
+        let mut issue = Issue::new("mn-block2".to_string(), "Blocked Issue".to_string()).unwrap();
+        store.append_issue(&issue).unwrap();
+
+        issue.add_blocker("mn-block1".to_string());
+        store.update_issue(&issue).unwrap();
+
+        let issues = store.load_issues().unwrap();
+        let blocked_issue = issues.iter().find(|i| i.id == "mn-block2").unwrap();
+        assert_eq!(blocked_issue.status, IssueStatus::Blocked);
+        assert!(blocked_issue.blocked_by.contains(&"mn-block1".to_string()));
+    }
+
+    #[test]
+    fn test_block_creates_stub_blocker() {
+        let issues = [Issue::new("mn-5cb001".to_string(), "Waiting".to_string()).unwrap()];
+
+        let stub = resolve_blocker(&issues, "mn-5cb002", true)
+            .unwrap()
+            .unwrap();
+        assert_eq!(stub.id, "mn-5cb002");
+        assert_eq!(stub.title, "(stub)");
+        assert_eq!(stub.status, IssueStatus::Open);
+
+        // Existing blockers need no stub; malformed IDs are refused
+        assert!(resolve_blocker(&issues, "mn-5cb001", true)
+            .unwrap()
+            .is_none());
+        assert!(resolve_blocker(&issues, "not-an-id", true).is_err());
+    }
+
+    #[test]
+    fn test_block_rejects_missing_blocker_by_default() {
+        let issues = [Issue::new("mn-5cb003".to_string(), "Waiting".to_string()).unwrap()];
+
+        let err = resolve_blocker(&issues, "mn-5cb004", false).unwrap_err();
+        assert!(err.contains("mn-5cb004 not found"));
+    }
+
+    #[test]
+    fn test_block_on_three_blockers_at_once() {
+        let mut issues: Vec<Issue> = ["mn-fa0001", "mn-fa0002", "mn-fa0003", "mn-fa0004"]
+            .iter()
+            .map(|id| Issue::new(id.to_string(), "Fan-in".to_string()).unwrap())
+            .collect();
+        issues[3].add_blocker("mn-fa0001".to_string());
+
+        let cli = Cli::try_parse_from([
+            "manna-core",
+            "block",
+            "mn-fa0004",
+            "mn-fa0001",
+            "mn-fa0002",
+            "mn-fa0003",
+        ])
+        .unwrap();
+        let Commands::Block { blocker_ids, .. } = cli.command else {
+            panic!("expected block");
+        };
+
+        let outcome = add_blockers(&mut issues, "mn-fa0004", &blocker_ids, false, false).unwrap();
+        assert_eq!(outcome.added, vec!["mn-fa0002", "mn-fa0003"]);
+        assert_eq!(outcome.already_present, vec!["mn-fa0001"]);
+        assert_eq!(
+            issues[3].blocked_by,
+            vec!["mn-fa0001", "mn-fa0002", "mn-fa0003"]
+        );
+        assert_eq!(issues[3].status, IssueStatus::Blocked);
+
+        // Any bad blocker rejects the whole batch
+        let before = issues.clone();
+        let ids = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let err = add_blockers(
+            &mut issues,
+            "mn-fa0001",
+            &ids(&["mn-fa0002", "mn-fa0004"]),
+            false,
+            false,
+        )
+        .unwrap_err();
+        assert!(err.contains("would create a cycle"));
+        let err =
+            add_blockers(&mut issues, "mn-fa0002", &ids(&["mn-fa0002"]), false, false).unwrap_err();
+        assert!(err.contains("cannot block itself"));
+        assert_eq!(issues[0].blocked_by, before[0].blocked_by);
+        assert_eq!(issues[1].blocked_by, before[1].blocked_by);
+    }
+
+    #[test]
+    fn test_done_last_child_completes_parents() {
+        let mut issues: Vec<Issue> = ["mn-ca0001", "mn-ca0002", "mn-ca0003", "mn-ca0004"]
+            .iter()
+            .map(|id| Issue::new(id.to_string(), "Cascade".to_string()).unwrap())
+            .collect();
+        // mn-ca0001 <- mn-ca0002 <- {mn-ca0003, mn-ca0004}
+        issues[1].parent_id = Some("mn-ca0001".to_string());
+        issues[2].parent_id = Some("mn-ca0002".to_string());
+        issues[3].parent_id = Some("mn-ca0002".to_string());
+        for issue in issues.iter_mut() {
+            issue.claim(SessionId::new("ses_cascade").unwrap()).unwrap();
+        }
+
+        // A sibling is still open, so nothing cascades
+        issues[2].complete().unwrap();
+        assert!(complete_parents(&mut issues, "mn-ca0003").is_empty());
+        assert_eq!(issues[1].status, IssueStatus::InProgress);
+
+        // The last child completes the parent and the grandparent
+        issues[3].complete().unwrap();
+        assert_eq!(
+            complete_parents(&mut issues, "mn-ca0004"),
+            vec!["mn-ca0002", "mn-ca0001"]
+        );
+        assert_eq!(issues[0].status, IssueStatus::Done);
+        assert_eq!(issues[1].status, IssueStatus::Done);
+
+        // A parent that is not in progress stops the chain
+        issues[0].reopen().unwrap();
+        issues[1].reopen().unwrap();
+        issues[0]
+            .claim(SessionId::new("ses_cascade").unwrap())
+            .unwrap();
+        assert!(complete_parents(&mut issues, "mn-ca0004").is_empty());
+        assert_eq!(issues[0].status, IssueStatus::InProgress);
+        assert_eq!(issues[1].status, IssueStatus::Open);
+    }
+
+    #[test]
+    fn test_redundant_blocker_detected() {
+        let base = Issue::new("mn-ed0001".to_string(), "Base".to_string()).unwrap();
+        let mut middle = Issue::new("mn-ed0002".to_string(), "Middle".to_string()).unwrap();
+        middle.add_blocker("mn-ed0001".to_string());
+        let mut top = Issue::new("mn-ed0003".to_string(), "Top".to_string()).unwrap();
+        top.add_blocker("mn-ed0002".to_string());
+        let issues = [base, middle, top];
+
+        let graph = BlockerGraph::new(&issues);
+
+        // Adding base directly to top would be redundant
+        let path = graph.path("mn-ed0003", "mn-ed0001").unwrap();
+        assert_eq!(path.join(" -> "), "mn-ed0003 -> mn-ed0002 -> mn-ed0001");
+        // The reverse edge is new information
+        assert!(graph.path("mn-ed0001", "mn-ed0003").is_none());
+    }
+
+    #[test]
+    fn test_unblock_workflow() {
+        let (_temp_dir, store) = setup_store();
+
+        let blocker = Issue::new("mn-unblk1".to_string(), "Blocker".to_string()).unwrap();
+        store.append_issue(&blocker).unwrap();
+
+        let mut issue = Issue::new("mn-unblk2".to_string(), "Blocked".to_string()).unwrap();
+        issue.add_blocker("mn-unblk1".to_string());
+        store.append_issue(&issue).unwrap();
+
+        issue.remove_blocker("mn-unblk1");
+        store.update_issue(&issue).unwrap();
+
+        let issues = store.load_issues().unwrap();
+        let unblocked = issues.iter().find(|i| i.id == "mn-unblk2").unwrap();
+        assert_eq!(unblocked.status, IssueStatus::Open);
+        assert!(unblocked.blocked_by.is_empty());
+    }
+
+    #[test]
+    fn test_profile_report_phases() {
+        let store = StoreTimings {
+            load: std::time::Duration::from_millis(2),
+            lock_wait: std::time::Duration::from_millis(1),
+            write: std::time::Duration::from_millis(3),
+            lock_waits: 2,
+        };
+
+        let report = profile_report(std::time::Duration::from_millis(10), store);
+        assert!(!report.contains('\n'));
+
+        let value: serde_json::Value = serde_json::from_str(&report).unwrap();
+        let profile = &value["profile"];
+        for phase in [
+            "total_ms",
+            "load_ms",
+            "compute_ms",
+            "lock_wait_ms",
+            "write_ms",
+        ] {
+            assert!(profile[phase].is_f64(), "missing phase {}", phase);
+        }
+        assert!((profile["compute_ms"].as_f64().unwrap() - 4.0).abs() < 1e-6);
+        assert_eq!(profile["lock_waits"], 2);
+    }
+
+    #[test]
+    fn test_split_repl_words() {
+        let words = split_words(r#"create "Fix the bug" 'C:\tmp' a\ b "say \"hi\"""#).unwrap();
+        assert_eq!(
+            words,
+            vec!["create", "Fix the bug", r"C:\tmp", "a b", r#"say "hi""#]
+        );
+        assert_eq!(split_words(r#"create "" "#).unwrap(), vec!["create", ""]);
+        assert!(split_words(r#"create "open"#).is_err());
+
+        let line = ReplLine::try_parse_from(split_words("done mn-abc123 --force").unwrap());
+        assert!(matches!(
+            line.map(|l| l.command),
+            Ok(Commands::Done { force: true, .. })
+        ));
+    }
+
+    #[test]
+    fn test_unix_time_format_emits_integers() {
+        let (_temp_dir, store) = setup_store();
+        let mut issue =
+            Issue::new("mn-0e0c01".to_string(), "2025-01-15T10:00:00Z".to_string()).unwrap();
+        issue.claim(SessionId::new("ses_unix").unwrap()).unwrap();
+        store.append_issue(&issue).unwrap();
+
+        let mut value = serde_json::to_value(SuccessResponse {
+            success: true,
+            data: IssueData { issue },
+        })
+        .unwrap();
+        unix_timestamps(&mut value);
+
+        let stored = &store.load_issues().unwrap()[0];
+        assert_eq!(value["issue"]["created_at"], stored.created_at.timestamp());
+        assert!(value["issue"]["updated_at"].is_i64());
+        assert!(value["issue"]["claimed_at"].is_i64());
+        // A date-shaped title is not a timestamp field
+        assert_eq!(value["issue"]["title"], "2025-01-15T10:00:00Z");
+    }
+
+    #[test]
+    fn test_explicit_nulls_for_unclaimed_issue() {
+        let issue = Issue::new("mn-nul001".to_string(), "Unclaimed".to_string()).unwrap();
+        let mut value = serde_json::to_value(SuccessResponse {
+            success: true,
+            data: IssueData { issue },
+        })
+        .unwrap();
+        assert!(value["issue"].get("claimed_by").is_none());
+
+        fill_explicit_nulls(&mut value);
+        let yaml = serde_yaml::to_string(&value).unwrap();
+
+        assert!(yaml.contains("claimed_by: null"));
+        assert!(yaml.contains("claimed_at: null"));
+        assert!(yaml.contains("description: null"));
+    }
+
+    #[test]
+    fn test_default_list_filter_and_all() {
+        let (_temp_dir, store) = setup_store();
+        std::fs::write(
+            store.manna_dir().join("config.toml"),
+            "default_list_filter = \"active\"\n",
+        )
+        .unwrap();
+        let mut done = Issue::new("mn-df1001".to_string(), "Done".to_string()).unwrap();
+        done.claim(SessionId::new("ses_df").unwrap()).unwrap();
+        done.complete().unwrap();
+        let open = Issue::new("mn-df1002".to_string(), "Open".to_string()).unwrap();
+        let issues = [done, open];
+
+        let default = store.load_project_config().unwrap().default_list_filter;
+        let listed = |status: Option<&str>, all: bool| -> Vec<String> {
+            let filter = ListFilter::for_list(status, &[], all, default.as_deref()).unwrap();
+            issues
+                .iter()
+                .filter(|i| filter.matches(i))
+                .map(|i| i.id.clone())
+                .collect()
+        };
+
+        assert_eq!(listed(None, false), vec!["mn-df1002"]);
+        assert_eq!(listed(None, true), vec!["mn-df1001", "mn-df1002"]);
+        assert_eq!(listed(Some("done"), false), vec!["mn-df1001"]);
+
+        std::fs::write(
+            store.manna_dir().join("config.toml"),
+            "default_list_filter = \"finished\"\n",
+        )
+        .unwrap();
+        let bad = store.load_project_config().unwrap().default_list_filter;
+        assert!(ListFilter::for_list(None, &[], false, bad.as_deref()).is_err());
+        assert!(ListFilter::for_list(None, &[], true, bad.as_deref()).is_ok());
+    }
+
+    #[test]
+    fn test_results_exit_code_on_empty() {
+        let issues = [Issue::new("mn-e3e001".to_string(), "Open".to_string()).unwrap()];
+        let count = |status: &str| {
+            let filter = ListFilter::new(Some(status), &[]).unwrap();
+            issues.iter().filter(|i| filter.matches(i)).count()
+        };
+
+        assert_eq!(results_exit_code(count("done"), true), EXIT_EMPTY);
+        assert_eq!(results_exit_code(count("open"), true), EXIT_SUCCESS);
+        // Off by default: empty is still success
+        assert_eq!(results_exit_code(count("done"), false), EXIT_SUCCESS);
+    }
+
+    #[test]
+    fn test_cap_results_marks_truncation() {
+        let summary = |n: usize| IssueSummary {
+            id: format!("mn-ca{:04}", n),
+            title: "Flood".to_string(),
+            status: IssueStatus::Open,
+            claimed_by: None,
+        };
+
+        let mut at_cap: Vec<IssueSummary> = (0..3).map(summary).collect();
+        assert!(!cap_results(&mut at_cap, 3));
+        assert_eq!(at_cap.len(), 3);
+
+        let mut issues: Vec<IssueSummary> = (0..5).map(summary).collect();
+        let truncated = cap_results(&mut issues, 3);
+        let value = serde_json::to_value(IssueListData {
+            issues,
+            truncated,
+            as_of: None,
+        })
+        .unwrap();
+
+        assert_eq!(value["issues"].as_array().unwrap().len(), 3);
+        assert_eq!(value["issues"][2]["id"], "mn-ca0002");
+        assert_eq!(value["truncated"], true);
+    }
+
+    #[test]
+    fn test_toml_list_round_trip() {
+        let response = SuccessResponse {
+            success: true,
+            data: IssueListData {
+                issues: vec![
+                    IssueSummary {
+                        id: "mn-tom001".to_string(),
+                        title: "Claimed".to_string(),
+                        status: IssueStatus::InProgress,
+                        claimed_by: Some("ses_toml".to_string()),
+                    },
+                    IssueSummary {
+                        id: "mn-tom002".to_string(),
+                        title: "Open".to_string(),
+                        status: IssueStatus::Open,
+                        claimed_by: None,
+                    },
+                ],
+                truncated: false,
+                as_of: None,
+            },
+        };
+        let rendered = render_toml(serde_json::to_value(&response).unwrap());
+        assert!(rendered.contains("[[issues]]"));
+
+        let parsed: toml::Value = toml::from_str(&rendered).unwrap();
+        assert_eq!(parsed["success"].as_bool(), Some(true));
+        let issues = parsed["issues"].as_array().unwrap();
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0]["id"].as_str(), Some("mn-tom001"));
+        assert_eq!(issues[0]["claimed_by"].as_str(), Some("ses_toml"));
+        assert!(issues[1].get("claimed_by").is_none());
+    }
+
+    fn ranked(ranks: &[Option<f64>]) -> Vec<Issue> {
+        ranks
+            .iter()
+            .enumerate()
+            .map(|(n, rank)| {
+                let mut issue = Issue::new(format!("mn-4a0c0{}", n), "Ranked".to_string()).unwrap();
+                issue.rank = *rank;
+                issue
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_age_buckets_place_known_ages() {
+        let now = Utc::now();
+        let aged = |id: &str, age: chrono::Duration| {
+            let mut issue = Issue::new(id.to_string(), "Aging".to_string()).unwrap();
+            issue.created_at = now - age;
+            issue
+        };
+        let issues = vec![
+            aged("mn-a9e001", chrono::Duration::hours(3)),
+            aged("mn-a9e002", chrono::Duration::days(40)),
+            aged("mn-a9e003", chrono::Duration::days(3)),
+            aged("mn-a9e004", chrono::Duration::days(7)),
+            aged("mn-a9e005", chrono::Duration::days(10)),
+        ];
+
+        let cli = Cli::try_parse_from(["manna-core", "list", "--bucket"]).unwrap();
+        let Commands::List(args) = cli.command else {
+            panic!("expected list");
+        };
+        let bounds = parse_buckets(&args.buckets).unwrap();
+        let buckets = age_buckets(issues, &bounds, now);
+
+        let layout: Vec<(&str, Vec<&str>)> = buckets
+            .iter()
+            .map(|b| {
+                let ids = b.issues.iter().map(|i| i.id.as_str()).collect();
+                (b.age.as_str(), ids)
+            })
+            .collect();
+        assert_eq!(
+            layout,
+            vec![
+                ("<1d", vec!["mn-a9e001"]),
+                ("1d-7d", vec!["mn-a9e003"]),
+                ("7d-30d", vec!["mn-a9e004", "mn-a9e005"]),
+                (">=30d", vec!["mn-a9e002"]),
+            ]
+        );
+
+        assert!(parse_buckets(&["7d".to_string(), "1d".to_string()]).is_err());
+        assert!(Cli::try_parse_from(["manna-core", "list", "--buckets", "1d"]).is_err());
+    }
+
+    #[test]
+    fn test_sort_blockers_puts_bottleneck_first() {
+        let mut issues = ranked(&[None, None, None, None, None, None]);
+        for waiting in [2, 3, 5] {
+            issues[waiting].add_blocker("mn-4a0c04".to_string());
+        }
+        issues[3].add_blocker("mn-4a0c01".to_string());
+
+        let graph = BlockerGraph::new(&issues);
+        let dependents = graph.dependent_counts();
+        let filter = ListFilter::new(Some("open"), &[]).unwrap();
+        let mut matching: Vec<Issue> = issues
+            .iter()
+            .filter(|i| filter.matches(i))
+            .cloned()
+            .collect();
+        sort_issues(&mut matching, ListSort::Blockers, &dependents);
+
+        let ids: Vec<&str> = matching.iter().map(|i| i.id.as_str()).collect();
+        assert_eq!(ids, vec!["mn-4a0c04", "mn-4a0c01", "mn-4a0c00"]);
     }
 
-    // Load issues
-    let issues = match store.load_issues() {
-        Ok(i) => i,
-        Err(err) => handle_manna_error(err),
-    };
+    #[test]
+    fn test_rank_between_two_ranked_issues() {
+        let mut issues = ranked(&[Some(1.0), Some(2.0), None]);
 
-    // Find issue
-    let mut issue = find_issue(&issues, &id);
+        let changed = place_rank(&mut issues, "mn-4a0c02", "mn-4a0c00", true).unwrap();
+        assert_eq!(changed, vec!["mn-4a0c02".to_string()]);
+        assert_eq!(issues[2].rank, Some(1.5));
 
-    // Complete it
-    if let Err(e) = issue.complete() {
-        output_error(&e, EXIT_USER_ERROR);
-    }
+        // Before the head of the queue steps one below it
+        place_rank(&mut issues, "mn-4a0c01", "mn-4a0c00", false).unwrap();
+        assert_eq!(issues[1].rank, Some(0.0));
 
-    // Update store
-    if let Err(err) = store.update_issue(&issue) {
-        handle_manna_error(err);
+        sort_issues(&mut issues, ListSort::Rank, &HashMap::new());
+        let ids: Vec<&str> = issues.iter().map(|i| i.id.as_str()).collect();
+        assert_eq!(ids, vec!["mn-4a0c01", "mn-4a0c00", "mn-4a0c02"]);
     }
 
-    output_success(IssueData { issue });
-}
-
-fn cmd_abandon(id: String) -> ! {
-    let store = MannaStore::new(Path::new("."));
+    #[test]
+    fn test_rank_unranked_target_and_sort() {
+        let mut issues = ranked(&[None, Some(5.0), None, None]);
 
-    if !store.is_initialized() {
-        output_error(
-            "Storage not initialized. Run 'manna-core init' first.",
-            EXIT_USER_ERROR,
+        let changed = place_rank(&mut issues, "mn-4a0c00", "mn-4a0c02", false).unwrap();
+        assert_eq!(
+            changed,
+            vec!["mn-4a0c02".to_string(), "mn-4a0c00".to_string()]
         );
-    }
+        assert_eq!(issues[2].rank, Some(6.0));
+        assert_eq!(issues[0].rank, Some(5.5));
 
-    // Load issues
-    let issues = match store.load_issues() {
-        Ok(i) => i,
-        Err(err) => handle_manna_error(err),
-    };
+        sort_issues(&mut issues, ListSort::Rank, &HashMap::new());
+        let ids: Vec<&str> = issues.iter().map(|i| i.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec!["mn-4a0c01", "mn-4a0c00", "mn-4a0c02", "mn-4a0c03"]
+        );
 
-    // Find issue
-    let mut issue = find_issue(&issues, &id);
+        assert!(place_rank(&mut issues, "mn-4a0c00", "mn-4a0c00", true).is_err());
+        assert!(place_rank(&mut issues, "mn-4a0c00", "mn-4a0cff", true).is_err());
+    }
 
-    // Release it
-    if let Err(e) = issue.release() {
-        output_error(&e, EXIT_USER_ERROR);
+    fn family() -> Vec<Issue> {
+        // root <- child <- grandchild, plus an unrelated root
+        let mut issues: Vec<Issue> = ["mn-fa0001", "mn-fa0002", "mn-fa0003", "mn-fa0004"]
+            .iter()
+            .map(|id| Issue::new(id.to_string(), "Issue".to_string()).unwrap())
+            .collect();
+        issues[1].parent_id = Some("mn-fa0001".to_string());
+        issues[2].parent_id = Some("mn-fa0002".to_string());
+        issues
     }
 
-    // Update store
-    if let Err(err) = store.update_issue(&issue) {
-        handle_manna_error(err);
+    #[test]
+    fn test_reparent_valid_move() {
+        let issues = family();
+        assert!(check_reparent(&issues, "mn-fa0003", Some("mn-fa0004")).is_ok());
+        assert!(check_reparent(&issues, "mn-fa0002", Some("mn-fa0004")).is_ok());
     }
 
-    output_success(IssueData { issue });
-}
+    #[test]
+    fn test_reparent_rejects_cycle_and_unknown_parent() {
+        let issues = family();
 
-fn cmd_block(id: String, blocker_id: String) -> ! {
-    let store = MannaStore::new(Path::new("."));
+        let err = check_reparent(&issues, "mn-fa0001", Some("mn-fa0003")).unwrap_err();
+        assert!(err.contains("already beneath"));
+        assert!(check_reparent(&issues, "mn-fa0001", Some("mn-fa0001")).is_err());
 
-    if !store.is_initialized() {
-        output_error(
-            "Storage not initialized. Run 'manna-core init' first.",
-            EXIT_USER_ERROR,
-        );
+        let err = check_reparent(&issues, "mn-fa0003", Some("mn-fa00ff")).unwrap_err();
+        assert!(err.contains("not found"));
     }
 
-    // Load issues
-    let issues = match store.load_issues() {
-        Ok(i) => i,
-        Err(err) => handle_manna_error(err),
-    };
+    #[test]
+    fn test_reparent_detach_to_root() {
+        let (_temp_dir, store) = setup_store();
+        for issue in family() {
+            store.append_issue(&issue).unwrap();
+        }
+        let issues = store.load_issues().unwrap();
+        assert!(check_reparent(&issues, "mn-fa0003", None).is_ok());
 
-    // Verify blocker exists
-    if !issues.iter().any(|i| i.id == blocker_id) {
-        output_error(
-            &format!("Blocker issue {} not found", blocker_id),
-            EXIT_USER_ERROR,
-        );
+        let mut grandchild = issues[2].clone();
+        grandchild.parent_id = None;
+        store.update_issue(&grandchild).unwrap();
+
+        let stored = store.load_issues().unwrap();
+        assert_eq!(stored[2].parent_id, None);
+        assert_eq!(stored[1].parent_id.as_deref(), Some("mn-fa0001"));
     }
 
-    // Find issue
-    let mut issue = find_issue(&issues, &id);
+    #[test]
+    fn test_claim_assign_self_and_note() {
+        let (_temp_dir, store) = setup_store();
+        let issue = Issue::new("mn-a55001".to_string(), "Assign me".to_string()).unwrap();
+        store.append_issue(&issue).unwrap();
+        let me = SessionId::new("ses_me").unwrap();
 
-    // Add blocker
-    issue.add_blocker(blocker_id);
+        let mut issue = store.load_issues().unwrap().remove(0);
+        claim_issue(&mut issue, me.clone(), &[], false, true).unwrap();
+        let note = claim_extras(&mut issue, &me, true, Some("Starting now".to_string()));
+        store.update_issue(&issue).unwrap();
+        store.append_session(&note.unwrap()).unwrap();
+
+        let stored = &store.load_issues().unwrap()[0];
+        assert_eq!(stored.status, IssueStatus::InProgress);
+        assert_eq!(stored.claimed_by.as_deref(), Some("ses_me"));
+        assert_eq!(stored.assignee.as_deref(), Some("ses_me"));
+
+        let events = store.load_sessions().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event, SessionEventType::Note);
+        assert_eq!(events[0].issue_id.as_deref(), Some("mn-a55001"));
+        assert_eq!(events[0].context.as_ref().unwrap()["text"], "Starting now");
+
+        // Neither extra is implied by the other
+        let mut other = Issue::new("mn-a55002".to_string(), "Plain".to_string()).unwrap();
+        assert!(claim_extras(&mut other, &me, false, None).is_none());
+        assert!(other.assignee.is_none());
+    }
 
-    // Update store
-    if let Err(err) = store.update_issue(&issue) {
-        handle_manna_error(err);
+    #[test]
+    fn test_claim_force_steals_only_from_ended_sessions() {
+        let (_temp_dir, store) = setup_store();
+        for event in [
+            SessionEvent::start(SessionId::new("ses_dead").unwrap(), serde_json::json!({})),
+            SessionEvent::end(SessionId::new("ses_dead").unwrap(), serde_json::json!({})),
+            SessionEvent::start(SessionId::new("ses_live").unwrap(), serde_json::json!({})),
+        ] {
+            store.append_session(&event).unwrap();
+        }
+        let events = store.load_sessions().unwrap();
+        let me = SessionId::new("ses_me").unwrap();
+
+        let mut stale = Issue::new("mn-57ea01".to_string(), "Stale".to_string()).unwrap();
+        stale.claim(SessionId::new("ses_dead").unwrap()).unwrap();
+        claim_issue(&mut stale, me.clone(), &events, true, true).unwrap();
+        assert_eq!(stale.claimed_by.as_deref(), Some("ses_me"));
+
+        let mut held = Issue::new("mn-57ea02".to_string(), "Held".to_string()).unwrap();
+        held.claim(SessionId::new("ses_live").unwrap()).unwrap();
+        let err = claim_issue(&mut held, me.clone(), &events, true, true).unwrap_err();
+        assert!(matches!(err, MannaError::ClaimHeld { .. }));
+        assert!(err.to_string().contains("still active"));
+        assert_eq!(held.claimed_by.as_deref(), Some("ses_live"));
+
+        // Without --force the claim is simply refused
+        assert!(claim_issue(&mut held, me.clone(), &events, false, true).is_err());
+
+        // --force-anyway skips the liveness check
+        claim_issue(&mut held, me, &events, true, false).unwrap();
+        assert_eq!(held.claimed_by.as_deref(), Some("ses_me"));
     }
 
-    output_success(IssueData { issue });
-}
+    #[test]
+    fn test_claim_records_session_event() {
+        let (_temp_dir, store) = setup_store();
+        let ses = SessionId::new("ses_logged").unwrap();
+        let mut issue = Issue::new("mn-e7e001".to_string(), "Logged".to_string()).unwrap();
+        store.append_issue(&issue).unwrap();
 
-fn cmd_unblock(id: String, blocker_id: String) -> ! {
-    let store = MannaStore::new(Path::new("."));
+        // Same sequence as cmd_claim: update the issue, then log the claim
+        claim_issue(&mut issue, ses.clone(), &[], false, true).unwrap();
+        store.update_issue(&issue).unwrap();
+        record_event(&store, &SessionEvent::claim(ses.clone(), issue.id.clone()));
 
-    if !store.is_initialized() {
-        output_error(
-            "Storage not initialized. Run 'manna-core init' first.",
-            EXIT_USER_ERROR,
+        let events = store.load_sessions().unwrap();
+        let claim = events.last().unwrap();
+        assert_eq!(claim.event, SessionEventType::Claim);
+        assert_eq!(claim.session_id, ses);
+        assert_eq!(claim.issue_id.as_deref(), Some("mn-e7e001"));
+        assert_eq!(
+            store.load_issues().unwrap()[0].claimed_by.as_deref(),
+            Some("ses_logged")
         );
     }
 
-    // Load issues
-    let issues = match store.load_issues() {
-        Ok(i) => i,
-        Err(err) => handle_manna_error(err),
-    };
+    #[test]
+    fn test_purge_sessions_keeps_active_start() {
+        let now = Utc::now();
+        let at = |mut event: SessionEvent, days_ago: i64| {
+            event.timestamp = now - chrono::Duration::days(days_ago);
+            event
+        };
+        let live = SessionId::new("ses_live").unwrap();
+        let gone = SessionId::new("ses_gone").unwrap();
+        let mut events = vec![
+            at(SessionEvent::start(live.clone(), serde_json::json!({})), 60),
+            at(SessionEvent::start(gone.clone(), serde_json::json!({})), 50),
+            at(
+                SessionEvent::note(live.clone(), "mn-9e0001".to_string(), "old".to_string()),
+                45,
+            ),
+            at(SessionEvent::end(gone, serde_json::json!({})), 40),
+            at(
+                SessionEvent::note(live, "mn-9e0001".to_string(), "new".to_string()),
+                1,
+            ),
+        ];
+        let before = active_sessions(&events, &[]);
 
-    // Find issue
-    let mut issue = find_issue(&issues, &id);
+        let mut by_age = events.clone();
+        let (purged, preserved) = purge_sessions(
+            &mut by_age,
+            SessionRetention::Since(now - chrono::Duration::days(30)),
+        );
+        assert_eq!((purged, preserved), (3, 1));
+        assert_eq!(by_age.len(), 2);
+        assert_eq!(by_age[0].event, SessionEventType::Start);
+
+        let after = active_sessions(&by_age, &[]);
+        assert_eq!(after.len(), 1);
+        assert_eq!(after[0].session_id, before[0].session_id);
+        assert_eq!(after[0].started_at, before[0].started_at);
+
+        let (purged, preserved) = purge_sessions(&mut events, SessionRetention::KeepLast(1));
+        assert_eq!((purged, preserved), (3, 1));
+        assert_eq!(active_sessions(&events, &[]).len(), 1);
+    }
 
-    // Remove blocker
-    issue.remove_blocker(&blocker_id);
+    #[test]
+    fn test_active_sessions_folds_start_and_end() {
+        let (_temp_dir, store) = setup_store();
 
-    // Update store
-    if let Err(err) = store.update_issue(&issue) {
-        handle_manna_error(err);
+        let ended_start =
+            SessionEvent::start(SessionId::new("ses_ended").unwrap(), serde_json::json!({}));
+        let live_start =
+            SessionEvent::start(SessionId::new("ses_live").unwrap(), serde_json::json!({}));
+        let ended_end =
+            SessionEvent::end(SessionId::new("ses_ended").unwrap(), serde_json::json!({}));
+        // The live session started twice; it should appear once
+        let live_restart =
+            SessionEvent::start(SessionId::new("ses_live").unwrap(), serde_json::json!({}));
+        for event in [&ended_start, &live_start, &ended_end, &live_restart] {
+            store.append_session(event).unwrap();
+        }
+
+        let mut held = Issue::new("mn-act001".to_string(), "Held".to_string()).unwrap();
+        held.claim(SessionId::new("ses_live").unwrap()).unwrap();
+        store.append_issue(&held).unwrap();
+
+        let events = store.load_sessions().unwrap();
+        let issues = store.load_issues().unwrap();
+        let active = active_sessions(&events, &issues);
+
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].session_id, "ses_live");
+        assert_eq!(active[0].started_at, live_restart.timestamp);
+        assert_eq!(active[0].claimed_issues, vec!["mn-act001".to_string()]);
     }
 
-    output_success(IssueData { issue });
-}
+    #[test]
+    fn test_claim_batch_waiting_picks_up_new_issue() {
+        let (_temp_dir, store) = setup_store();
 
-fn cmd_list(status_filter: Option<String>) -> ! {
-    let store = MannaStore::new(Path::new("."));
+        let writer = store.clone();
+        let producer = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(150));
+            let issue = Issue::new("mn-wait01".to_string(), "Late work".to_string()).unwrap();
+            writer.append_issue(&issue).unwrap();
+        });
+
+        let claimed = claim_batch_waiting(
+            &store,
+            1,
+            &SessionId::new("ses_waiter").unwrap(),
+            std::time::Duration::from_secs(10),
+        )
+        .unwrap();
+        producer.join().unwrap();
+
+        assert_eq!(claimed.len(), 1);
+        assert_eq!(claimed[0].id, "mn-wait01");
+        assert_eq!(claimed[0].claimed_by.as_deref(), Some("ses_waiter"));
+    }
 
-    if !store.is_initialized() {
-        output_error(
-            "Storage not initialized. Run 'manna-core init' first.",
-            EXIT_USER_ERROR,
-        );
+    #[test]
+    fn test_concurrent_claim_batches_never_share_an_issue() {
+        let (_temp_dir, store) = setup_store();
+        for n in 1..=5 {
+            let issue = Issue::new(format!("mn-c0c00{}", n), format!("Work {}", n)).unwrap();
+            store.append_issue(&issue).unwrap();
+        }
+
+        // 20 claimers race for 5 issues, one each
+        let handles: Vec<_> = (0..20)
+            .map(|n| {
+                let store = store.clone();
+                std::thread::spawn(move || {
+                    let session = SessionId::new(format!("ses_racer{}", n)).unwrap();
+                    let claimed =
+                        claim_batch_waiting(&store, 1, &session, std::time::Duration::ZERO)
+                            .unwrap();
+                    (session, claimed)
+                })
+            })
+            .collect();
+        let mut winners = HashMap::new();
+        for handle in handles {
+            let (session, claimed) = handle.join().unwrap();
+            for issue in claimed {
+                assert!(
+                    winners.insert(issue.id.clone(), session.clone()).is_none(),
+                    "{} handed out twice",
+                    issue.id
+                );
+            }
+        }
+
+        assert_eq!(winners.len(), 5);
+        for issue in store.load_issues().unwrap() {
+            assert_eq!(
+                issue.claimed_by.as_deref(),
+                winners.get(&issue.id).map(|s| s.as_str())
+            );
+        }
     }
 
-    // Load issues
-    let issues = match store.load_issues() {
-        Ok(i) => i,
-        Err(err) => handle_manna_error(err),
-    };
+    #[test]
+    fn test_claim_batch_waiting_times_out_empty() {
+        let (_temp_dir, store) = setup_store();
 
-    // Parse filter if provided
-    let filter: Option<IssueStatus> = match status_filter {
-        Some(s) => match parse_status(&s) {
-            Ok(status) => Some(status),
-            Err(e) => output_error(&e, EXIT_USER_ERROR),
-        },
-        None => None,
-    };
+        let claimed = claim_batch_waiting(
+            &store,
+            1,
+            &SessionId::new("ses").unwrap(),
+            std::time::Duration::from_millis(100),
+        )
+        .unwrap();
 
-    // Filter and map to summaries
-    let summaries: Vec<IssueSummary> = issues
-        .into_iter()
-        .filter(|i| filter.as_ref().map_or(true, |f| &i.status == f))
-        .map(|i| IssueSummary {
-            id: i.id,
-            title: i.title,
-            status: i.status,
-            claimed_by: i.claimed_by,
-        })
-        .collect();
+        assert!(claimed.is_empty());
+    }
 
-    output_success(IssueListData { issues: summaries });
-}
+    #[test]
+    fn test_claim_batch_shortfall() {
+        let (_temp_dir, store) = setup_store();
 
-fn cmd_show(id: String) -> ! {
-    let store = MannaStore::new(Path::new("."));
+        let first = Issue::new("mn-bat001".to_string(), "First".to_string()).unwrap();
+        let second = Issue::new("mn-bat002".to_string(), "Second".to_string()).unwrap();
+        let mut taken = Issue::new("mn-bat003".to_string(), "Taken".to_string()).unwrap();
+        taken.claim(SessionId::new("ses_other").unwrap()).unwrap();
+        let mut blocked = Issue::new("mn-bat004".to_string(), "Blocked".to_string()).unwrap();
+        blocked.add_blocker("mn-bat001".to_string());
+        for issue in [&first, &second, &taken, &blocked] {
+            store.append_issue(issue).unwrap();
+        }
+
+        let mut issues = store.load_issues().unwrap();
+        let claimed = claim_batch(&mut issues, 3, &SessionId::new("ses_batch").unwrap()).unwrap();
+        store
+            .lock_issues()
+            .unwrap()
+            .replace_issues(&issues)
+            .unwrap();
+
+        // Only two were claimable, so the request falls one short
+        assert_eq!(claimed.len(), 2);
 
-    if !store.is_initialized() {
-        output_error(
-            "Storage not initialized. Run 'manna-core init' first.",
-            EXIT_USER_ERROR,
-        );
+        let issues = store.load_issues().unwrap();
+        for id in ["mn-bat001", "mn-bat002"] {
+            let issue = issues.iter().find(|i| i.id == id).unwrap();
+            assert_eq!(issue.status, IssueStatus::InProgress);
+            assert_eq!(issue.claimed_by.as_deref(), Some("ses_batch"));
+        }
+        let taken = issues.iter().find(|i| i.id == "mn-bat003").unwrap();
+        assert_eq!(taken.claimed_by.as_deref(), Some("ses_other"));
     }
 
-    // Load issues
-    let issues = match store.load_issues() {
-        Ok(i) => i,
-        Err(err) => handle_manna_error(err),
-    };
+    #[test]
+    fn test_summary_line_format() {
+        let mut issues = vec![
+            Issue::new("mn-sum001".to_string(), "Open 1".to_string()).unwrap(),
+            Issue::new("mn-sum002".to_string(), "Open 2".to_string()).unwrap(),
+        ];
 
-    // Find issue
-    let issue = find_issue(&issues, &id);
+        let mut working = Issue::new("mn-sum003".to_string(), "Working".to_string()).unwrap();
+        working.claim(SessionId::new("ses").unwrap()).unwrap();
+        issues.push(working);
 
-    output_success(IssueData { issue });
-}
+        let mut blocked = Issue::new("mn-sum004".to_string(), "Blocked".to_string()).unwrap();
+        blocked.add_blocker("mn-sum001".to_string());
+        issues.push(blocked);
 
-fn cmd_context(max_tokens: usize) -> ! {
-    let store = MannaStore::new(Path::new("."));
+        for n in 0..3 {
+            let mut done = Issue::new(format!("mn-sumd{:02}", n), "Done".to_string()).unwrap();
+            done.claim(SessionId::new("ses").unwrap()).unwrap();
+            done.complete().unwrap();
+            issues.push(done);
+        }
 
-    if !store.is_initialized() {
-        output_error(
-            "Storage not initialized. Run 'manna-core init' first.",
-            EXIT_USER_ERROR,
+        assert_eq!(
+            summary_line(&issues),
+            "2 open, 1 in progress, 1 blocked, 3 done"
+        );
+        assert_eq!(
+            summary_line(&[]),
+            "0 open, 0 in progress, 0 blocked, 0 done"
         );
     }
 
-    // Load issues
-    let issues = match store.load_issues() {
-        Ok(i) => i,
-        Err(err) => handle_manna_error(err),
-    };
-
-    // Build context blob
-    let mut context = String::new();
-    context.push_str("# Manna Context\n\n");
+    #[test]
+    fn test_blame_claim_then_done_by_different_sessions() {
+        let claimer = SessionId::new("ses_claimer").unwrap();
+        let finisher = SessionId::new("ses_finisher").unwrap();
+        let claim = SessionEvent::claim(claimer, "mn-b1a000".to_string());
+        let mut done = SessionEvent::done(finisher, "mn-b1a000".to_string());
+        done.timestamp = claim.timestamp + chrono::Duration::seconds(1);
+        let other = SessionEvent::claim(
+            SessionId::new("ses_other").unwrap(),
+            "mn-b1a001".to_string(),
+        );
 
-    // Separate issues by status
-    let open: Vec<_> = issues
-        .iter()
-        .filter(|i| i.status == IssueStatus::Open)
-        .collect();
-    let in_progress: Vec<_> = issues
-        .iter()
-        .filter(|i| i.status == IssueStatus::InProgress)
-        .collect();
-    let blocked: Vec<_> = issues
-        .iter()
-        .filter(|i| i.status == IssueStatus::Blocked)
-        .collect();
+        let blame = blame_issue("mn-b1a000", &[claim.clone(), done.clone(), other]);
 
-    // Open issues
-    context.push_str(&format!("## Open Issues ({})\n", open.len()));
-    for issue in &open {
-        context.push_str(&format!("- {}: {} [open]\n", issue.id, issue.title));
+        assert_eq!(blame.status.session_id, "ses_finisher");
+        assert_eq!(blame.status.event, Some(SessionEventType::Done));
+        assert_eq!(blame.status.at, Some(done.timestamp));
+        assert_eq!(blame.claim.session_id, "ses_claimer");
+        assert_eq!(blame.claim.at, Some(claim.timestamp));
+        assert_eq!(
+            blame.blockers,
+            BlameEntry {
+                session_id: "unknown".to_string(),
+                event: None,
+                at: None,
+            }
+        );
     }
-    context.push('\n');
 
-    // In-progress issues
-    context.push_str(&format!("## In Progress Issues ({})\n", in_progress.len()));
-    for issue in &in_progress {
-        let claimed = issue
-            .claimed_by
-            .as_ref()
-            .map_or("".to_string(), |s| format!(", claimed by {}", s));
-        context.push_str(&format!(
-            "- {}: {} [in_progress{}]\n",
-            issue.id, issue.title, claimed
-        ));
-    }
-    context.push('\n');
+    #[test]
+    fn test_blocked_report_blocker_statuses_and_order() {
+        let mut done = Issue::new("mn-blk001".to_string(), "Done blocker".to_string()).unwrap();
+        done.claim(SessionId::new("ses").unwrap()).unwrap();
+        done.complete().unwrap();
 
-    // Blocked issues
-    context.push_str(&format!("## Blocked Issues ({})\n", blocked.len()));
-    for issue in &blocked {
-        let blockers = issue.blocked_by.join(", ");
-        context.push_str(&format!(
-            "- {}: {} [blocked by: {}]\n",
-            issue.id, issue.title, blockers
-        ));
-    }
+        let open = Issue::new("mn-blk002".to_string(), "Open blocker".to_string()).unwrap();
 
-    // Truncate if needed (rough estimate: 1 token ≈ 4 chars)
-    let max_chars = max_tokens * 4;
-    if context.len() > max_chars {
-        context.truncate(max_chars - 20);
-        context.push_str("\n\n[truncated]");
-    }
+        let mut newer = Issue::new("mn-blk003".to_string(), "Newer".to_string()).unwrap();
+        newer.add_blocker("mn-blk002".to_string());
 
-    output_success(ContextData { context });
-}
+        let mut older = Issue::new("mn-blk004".to_string(), "Older".to_string()).unwrap();
+        older.add_blocker("mn-blk001".to_string());
+        older.add_blocker("mn-gone00".to_string());
 
-// ============================================================================
-// Main Entry Point
-// ============================================================================
+        // The log says the older issue was blocked first
+        let mut early = SessionEvent::block(
+            SessionId::new("ses").unwrap(),
+            "mn-blk004".to_string(),
+            serde_json::json!({ "blocker_id": "mn-blk001" }),
+        );
+        early.timestamp = Utc::now() - chrono::Duration::hours(2);
 
-fn main() {
-    let cli = Cli::parse();
+        let issues = vec![done, open, newer, older];
+        let report = blocked_report(&issues, &[early]);
 
-    match cli.command {
-        Commands::Init => cmd_init(),
-        Commands::Status => cmd_status(),
-        Commands::Create { title, description } => cmd_create(title, description),
-        Commands::Claim { id } => cmd_claim(id),
-        Commands::Done { id } => cmd_done(id),
-        Commands::Abandon { id } => cmd_abandon(id),
-        Commands::Block { id, blocker_id } => cmd_block(id, blocker_id),
-        Commands::Unblock { id, blocker_id } => cmd_unblock(id, blocker_id),
-        Commands::List { status } => cmd_list(status),
-        Commands::Show { id } => cmd_show(id),
-        Commands::Context { max_tokens } => cmd_context(max_tokens),
-    }
-}
+        assert_eq!(report.len(), 2);
+        assert_eq!(report[0].id, "mn-blk004");
+        assert_eq!(report[1].id, "mn-blk003");
 
-// ============================================================================
-// Tests
-// ============================================================================
+        let statuses: Vec<&str> = report[0]
+            .blockers
+            .iter()
+            .map(|b| b.status.as_str())
+            .collect();
+        assert_eq!(statuses, vec!["done", "missing"]);
+        assert_eq!(report[1].blockers[0].status, "open");
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::sync::Mutex;
-    use tempfile::TempDir;
+    #[test]
+    fn test_explain_blocked_and_claimed() {
+        let live = Issue::new("mn-exp001".to_string(), "Live blocker".to_string()).unwrap();
+        let mut done = Issue::new("mn-exp002".to_string(), "Done blocker".to_string()).unwrap();
+        done.claim(SessionId::new("ses").unwrap()).unwrap();
+        done.complete().unwrap();
+
+        let mut issue = Issue::new("mn-exp003".to_string(), "Stuck".to_string()).unwrap();
+        issue.claim(SessionId::new("ses_worker").unwrap()).unwrap();
+        issue.add_blocker("mn-exp001".to_string());
+        issue.add_blocker("mn-exp002".to_string());
+
+        let now = issue.claimed_at.unwrap() + chrono::Duration::minutes(5);
+        let all = vec![live, done, issue.clone()];
+        let explanation = explain_issue(&issue, &all, now);
+
+        assert_eq!(explanation.status, IssueStatus::Blocked);
+        assert!(explanation.reason.contains("mn-exp001"));
+        assert!(!explanation.reason.contains("mn-exp002"));
+        assert!(!explanation.claimable);
+        assert_eq!(explanation.claimed_by.as_deref(), Some("ses_worker"));
+        assert_eq!(explanation.claimed_for_seconds, Some(300));
+
+        assert_eq!(explanation.blockers.len(), 2);
+        assert_eq!(explanation.blockers[0].status, "open");
+        assert!(explanation.to_unblock[0].starts_with("Complete mn-exp001"));
+        assert!(explanation.to_unblock[1].contains("unblock mn-exp003 mn-exp002"));
+    }
 
-    // Mutex to serialize tests that modify MANNA_SESSION_ID env var
-    static ENV_MUTEX: Mutex<()> = Mutex::new(());
+    #[test]
+    fn test_plain_view_renders_markdown_only_on_tty() {
+        let mut issue = Issue::new("mn-3d0001".to_string(), "Docs".to_string()).unwrap();
+        let description = "# Steps\n- run **init**\n- check output";
+        issue.description = Some(description.to_string());
+
+        let tty = plain_issue_view(&issue, true);
+        assert!(tty.contains("\x1b[1;4mSteps\x1b[0m"));
+        assert!(tty.contains("  • run \x1b[1minit\x1b[22m"));
+        assert!(!tty.contains("**"));
+
+        let piped = plain_issue_view(&issue, false);
+        assert!(piped.ends_with(&format!("{}\n", description)));
+        assert!(!piped.contains('\x1b'));
+    }
 
-    fn setup_store() -> (TempDir, MannaStore) {
-        let temp_dir = TempDir::new().unwrap();
-        let store = MannaStore::new(temp_dir.path());
-        store.init().unwrap();
-        (temp_dir, store)
+    #[test]
+    fn test_render_bold_keeps_unpaired_marker() {
+        assert_eq!(render_bold("a **b** c **d"), "a \x1b[1mb\x1b[22m c **d");
     }
 
     #[test]
-    fn test_get_session_id_default() {
-        let _lock = ENV_MUTEX.lock().unwrap();
-        // Clear env var if set
-        std::env::remove_var("MANNA_SESSION_ID");
+    fn test_truncate_chars_multibyte() {
+        let description = "日本語の説明文がとても長い".repeat(10);
+
+        let truncated = truncate_chars(&description, 5);
+        assert_eq!(truncated, "日本語の説…");
 
-        let session_id = get_session_id();
-        assert!(session_id.starts_with("ses_pid"));
+        assert_eq!(truncate_chars("short", 5), "short");
+        assert_eq!(truncate_chars("émoji 🎉 text", 7), "émoji 🎉…");
     }
 
     #[test]
-    fn test_get_session_id_from_env() {
-        let _lock = ENV_MUTEX.lock().unwrap();
-        std::env::set_var("MANNA_SESSION_ID", "ses_test_123");
-        let session_id = get_session_id();
-        assert_eq!(session_id, "ses_test_123");
-        std::env::remove_var("MANNA_SESSION_ID");
+    fn test_parse_cutoff_rejects_out_of_range_duration() {
+        let cutoff = parse_cutoff("7d").unwrap();
+        let expected = Utc::now() - chrono::Duration::days(7);
+        assert!((expected - cutoff).num_seconds().abs() < 5);
+        assert!(parse_cutoff("100000000d").is_err());
+        assert!(parse_cutoff("soon").is_err());
     }
 
     #[test]
-    fn test_parse_status_valid() {
-        assert_eq!(parse_status("open").unwrap(), IssueStatus::Open);
-        assert_eq!(
-            parse_status("in_progress").unwrap(),
-            IssueStatus::InProgress
-        );
-        assert_eq!(parse_status("blocked").unwrap(), IssueStatus::Blocked);
-        assert_eq!(parse_status("done").unwrap(), IssueStatus::Done);
-        // Case insensitive
-        assert_eq!(parse_status("OPEN").unwrap(), IssueStatus::Open);
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("7d").unwrap(), chrono::Duration::days(7));
+        assert_eq!(parse_duration("2w").unwrap(), chrono::Duration::days(14));
         assert_eq!(
-            parse_status("In_Progress").unwrap(),
-            IssueStatus::InProgress
+            parse_duration("90m").unwrap(),
+            chrono::Duration::minutes(90)
         );
+        assert!(parse_duration("7").is_err());
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("-1d").is_err());
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("999999999999999d").is_err());
+        assert!(parse_duration("9223372036854775807w").is_err());
     }
 
     #[test]
-    fn test_parse_status_invalid() {
-        let result = parse_status("invalid");
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("Invalid status"));
+    fn test_completions_per_day_fills_zero_days() {
+        let today = chrono::NaiveDate::from_ymd_opt(2026, 3, 10).unwrap();
+        let at = |day: u32| {
+            chrono::NaiveDate::from_ymd_opt(2026, 3, day)
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap()
+                .and_utc()
+        };
+
+        let mut issues = Vec::new();
+        for (n, day) in [10, 10, 8, 1].iter().enumerate() {
+            let mut issue = Issue::new(format!("mn-vel00{}", n), "Done".to_string()).unwrap();
+            issue.claim(SessionId::new("ses").unwrap()).unwrap();
+            issue.complete().unwrap();
+            issue.updated_at = at(*day);
+            issues.push(issue);
+        }
+        // Open issues do not count even if updated in the window
+        let mut open = Issue::new("mn-vel009".to_string(), "Open".to_string()).unwrap();
+        open.updated_at = at(9);
+        issues.push(open);
+
+        let days = completions_per_day(&issues, 3, today);
+
+        let counts: Vec<(u32, usize)> = days
+            .iter()
+            .map(|d| (chrono::Datelike::day(&d.date), d.completed))
+            .collect();
+        assert_eq!(counts, vec![(8, 1), (9, 0), (10, 2)]);
     }
 
     #[test]
-    fn test_error_to_exit_code_user_errors() {
-        assert_eq!(
-            error_to_exit_code(&MannaError::IssueNotFound("x".to_string())),
-            EXIT_USER_ERROR
-        );
-        assert_eq!(
-            error_to_exit_code(&MannaError::IssueAlreadyExists("x".to_string())),
-            EXIT_USER_ERROR
-        );
-        assert_eq!(
-            error_to_exit_code(&MannaError::InvalidId("x".to_string())),
-            EXIT_USER_ERROR
-        );
+    fn test_group_done_by_day() {
+        let at = |day: u32, hour: u32| {
+            chrono::NaiveDate::from_ymd_opt(2026, 3, day)
+                .unwrap()
+                .and_hms_opt(hour, 30, 0)
+                .unwrap()
+                .and_utc()
+        };
+
+        let mut issues = Vec::new();
+        for (id, day, hour) in [
+            ("mn-d00b02", 4, 23),
+            ("mn-d00a01", 5, 0),
+            ("mn-d00a02", 4, 1),
+        ] {
+            let mut issue = Issue::new(id.to_string(), "Done".to_string()).unwrap();
+            issue.claim(SessionId::new("ses").unwrap()).unwrap();
+            issue.complete().unwrap();
+            issue.updated_at = at(day, hour);
+            issues.push(issue);
+        }
+        issues.push(Issue::new("mn-d00c03".to_string(), "Open".to_string()).unwrap());
+
+        let days = group_done_by_day(&issues);
+
+        assert_eq!(days.len(), 2);
         assert_eq!(
-            error_to_exit_code(&MannaError::NotInitialized),
-            EXIT_USER_ERROR
+            days[0].date,
+            chrono::NaiveDate::from_ymd_opt(2026, 3, 4).unwrap()
         );
+        let first: Vec<&str> = days[0].issues.iter().map(|i| i.id.as_str()).collect();
+        assert_eq!(first, vec!["mn-d00a02", "mn-d00b02"]);
+        assert_eq!(days[1].issues.len(), 1);
+        assert_eq!(days[1].issues[0].id, "mn-d00a01");
     }
 
     #[test]
-    fn test_error_to_exit_code_system_errors() {
-        assert_eq!(
-            error_to_exit_code(&MannaError::LockFailed("x".to_string())),
-            EXIT_SYSTEM_ERROR
-        );
+    fn test_render_html_report_table() {
+        let open = Issue::new("mn-e7a001".to_string(), "Fix <login> & logout".to_string()).unwrap();
+        let mut blocked = Issue::new("mn-e7a002".to_string(), "Ship".to_string()).unwrap();
+        blocked.add_blocker("mn-e7a001".to_string());
+        let mut done = Issue::new("mn-e7a003".to_string(), "Plan".to_string()).unwrap();
+        done.claim(SessionId::new("ses_html").unwrap()).unwrap();
+        done.complete().unwrap();
+
+        let html = render_html_report(&[open, blocked, done]);
+
+        assert_eq!(html.matches("<table>").count(), 1);
+        assert_eq!(html.matches("</table>").count(), 1);
+        assert!(html.find("<table>") < html.find("</table>"));
+        assert_eq!(html.matches("<tr").count(), html.matches("</tr>").count());
+        assert_eq!(html.matches("<tr id=").count(), 3);
+
+        // Grouped by status, in lifecycle order
+        let open_at = html.find("id=\"mn-e7a001\"").unwrap();
+        let blocked_at = html.find("id=\"mn-e7a002\"").unwrap();
+        let done_at = html.find("id=\"mn-e7a003\"").unwrap();
+        assert!(open_at < blocked_at && blocked_at < done_at);
+
+        assert!(html.contains("<a href=\"#mn-e7a001\">mn-e7a001</a>"));
+        assert!(html.contains("Fix &lt;login&gt; &amp; logout"));
+        assert!(!html.contains("<link") && !html.contains("<script"));
+    }
+
+    #[test]
+    fn test_overdue_report_future_and_past_due() {
+        let now = Utc::now();
+        let mut future = Issue::new("mn-d0e001".to_string(), "Later".to_string()).unwrap();
+        future.due_at = Some(parse_due("2d", now).unwrap());
+        let mut past = Issue::new("mn-d0e002".to_string(), "Late".to_string()).unwrap();
+        past.due_at = Some(parse_due("2020-01-01T00:00:00Z", now).unwrap());
+        let mut later_past = Issue::new("mn-d0e003".to_string(), "Slip".to_string()).unwrap();
+        later_past.due_at = Some(now - chrono::Duration::hours(1));
+
+        let report = overdue_report(&[future, later_past, past], now);
+
+        let ids: Vec<&str> = report.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, vec!["mn-d0e002", "mn-d0e003"]);
+        assert_eq!(report[1].overdue_seconds, 3600);
+        assert!(parse_due("soon", now).is_err());
+        assert!(parse_due("100000000d", now).is_err());
     }
 
     #[test]
-    fn test_find_issue_found() {
-        let issues = vec![
-            Issue::new("mn-abc123".to_string(), "Test 1".to_string()).unwrap(),
-            Issue::new("mn-def456".to_string(), "Test 2".to_string()).unwrap(),
-        ];
-
-        let found = find_issue(&issues, "mn-def456");
-        assert_eq!(found.id, "mn-def456");
-        assert_eq!(found.title, "Test 2");
+    fn test_render_metrics_well_formed() {
+        let open = Issue::new("mn-met001".to_string(), "Open".to_string()).unwrap();
+        let mut claimed = Issue::new("mn-met002".to_string(), "Claimed".to_string()).unwrap();
+        claimed.claim(SessionId::new("ses").unwrap()).unwrap();
+        let mut blocked = Issue::new("mn-met003".to_string(), "Blocked".to_string()).unwrap();
+        blocked.add_blocker("mn-met001".to_string());
+
+        let text = render_metrics(&[open, claimed, blocked]);
+
+        let mut samples = std::collections::HashMap::new();
+        for line in text.lines() {
+            if line.starts_with('#') {
+                assert!(line.starts_with("# HELP manna_") || line.starts_with("# TYPE manna_"));
+                continue;
+            }
+            let (name, value) = line.rsplit_once(' ').unwrap();
+            assert!(name.starts_with("manna_"));
+            let value: u64 = value.parse().unwrap();
+            samples.insert(name.to_string(), value);
+        }
+
+        assert_eq!(samples["manna_issues_total{status=\"open\"}"], 1);
+        assert_eq!(samples["manna_issues_total{status=\"in_progress\"}"], 1);
+        assert_eq!(samples["manna_issues_total{status=\"blocked\"}"], 1);
+        assert_eq!(samples["manna_issues_total{status=\"done\"}"], 0);
+        assert_eq!(samples["manna_blocked_total"], 1);
+        assert_eq!(samples["manna_claimed_total"], 1);
+        assert_eq!(samples["manna_claimable_total"], 1);
     }
 
     #[test]
-    fn test_issue_summary_serialization() {
-        let summary = IssueSummary {
-            id: "mn-abc123".to_string(),
-            title: "Test".to_string(),
-            status: IssueStatus::Open,
-            claimed_by: None,
-        };
+    fn test_relink_blockers_frees_dangling() {
+        let (_temp_dir, store) = setup_store();
 
-        let yaml = serde_yaml::to_string(&summary).unwrap();
-        assert!(yaml.contains("id: mn-abc123"));
-        assert!(yaml.contains("title: Test"));
-        assert!(yaml.contains("status: open"));
-        // claimed_by should be skipped when None
-        assert!(!yaml.contains("claimed_by"));
+        let blocker = Issue::new("mn-live01".to_string(), "Live blocker".to_string()).unwrap();
+        store.append_issue(&blocker).unwrap();
+
+        let mut stuck = Issue::new("mn-stuck1".to_string(), "Stuck".to_string()).unwrap();
+        stuck.add_blocker("mn-gone01".to_string());
+        store.append_issue(&stuck).unwrap();
+
+        let mut partial = Issue::new("mn-part01".to_string(), "Partial".to_string()).unwrap();
+        partial.add_blocker("mn-live01".to_string());
+        partial.add_blocker("mn-gone01".to_string());
+        store.append_issue(&partial).unwrap();
+
+        let mut issues = store.load_issues().unwrap();
+        let removed = prune_dangling_blockers(&mut issues);
+        store
+            .lock_issues()
+            .unwrap()
+            .replace_issues(&issues)
+            .unwrap();
+
+        assert_eq!(removed.len(), 2);
+        assert!(removed.iter().all(|d| d.blocker_id == "mn-gone01"));
+
+        let issues = store.load_issues().unwrap();
+        let stuck = issues.iter().find(|i| i.id == "mn-stuck1").unwrap();
+        assert_eq!(stuck.status, IssueStatus::Open);
+        assert!(stuck.blocked_by.is_empty());
+
+        let partial = issues.iter().find(|i| i.id == "mn-part01").unwrap();
+        assert_eq!(partial.status, IssueStatus::Blocked);
+        assert_eq!(partial.blocked_by, vec!["mn-live01".to_string()]);
     }
 
     #[test]
-    fn test_issue_summary_with_claimed_by() {
-        let summary = IssueSummary {
-            id: "mn-abc123".to_string(),
-            title: "Test".to_string(),
-            status: IssueStatus::InProgress,
-            claimed_by: Some("ses_123".to_string()),
+    fn test_assume_yes_confirms_bulk_transition() {
+        let cli =
+            Cli::try_parse_from(["manna-core", "-y", "bulk-transition", "--to", "done"]).unwrap();
+        let Commands::BulkTransition { force, .. } = cli.command else {
+            panic!("expected bulk-transition");
         };
+        assert!(!force);
 
-        let yaml = serde_yaml::to_string(&summary).unwrap();
-        assert!(yaml.contains("claimed_by: ses_123"));
+        let _ = ASSUME_YES.set(cli.assume_yes);
+        assert!(confirmed(force));
     }
 
     #[test]
-    fn test_success_response_serialization() {
-        let response = SuccessResponse {
-            success: true,
-            data: InitData {
-                initialized: true,
-                path: ".manna".to_string(),
-            },
-        };
+    fn test_squash_redirects_references() {
+        let mut keep = Issue::new("mn-5a0001".to_string(), "Fix login".to_string()).unwrap();
+        keep.add_blocker("mn-5a0002".to_string());
+        let mut dup = Issue::new("mn-5a0002".to_string(), "Fix the login".to_string()).unwrap();
+        dup.add_blocker("mn-5a0009".to_string());
+        let upstream = Issue::new("mn-5a0009".to_string(), "Upstream".to_string()).unwrap();
+        let mut waiting = Issue::new("mn-5a0003".to_string(), "Waiting".to_string()).unwrap();
+        waiting.add_blocker("mn-5a0002".to_string());
+        let mut child = Issue::new("mn-5a0004".to_string(), "Child".to_string()).unwrap();
+        child.parent_id = Some("mn-5a0002".to_string());
+        let mut issues = vec![keep, dup, upstream, waiting, child];
+
+        let session_id = SessionId::new("ses_squash").unwrap();
+        let redirected = squash(
+            &mut issues,
+            "mn-5a0001",
+            &["mn-5a0002".to_string()],
+            false,
+            &session_id,
+        )
+        .unwrap();
+
+        assert_eq!(redirected, vec!["mn-5a0003", "mn-5a0004"]);
+        let find = |id: &str| issues.iter().find(|i| i.id == id).unwrap();
+        assert_eq!(find("mn-5a0001").blocked_by, vec!["mn-5a0009"]);
+        assert_eq!(find("mn-5a0003").blocked_by, vec!["mn-5a0001"]);
+        assert_eq!(find("mn-5a0004").parent_id.as_deref(), Some("mn-5a0001"));
+        assert_eq!(find("mn-5a0002").status, IssueStatus::Done);
+        assert_eq!(find("mn-5a0002").claimed_by.as_deref(), Some("ses_squash"));
+        assert!(find("mn-5a0002").blocked_by.is_empty());
+
+        squash(
+            &mut issues,
+            "mn-5a0001",
+            &["mn-5a0002".to_string()],
+            true,
+            &session_id,
+        )
+        .unwrap();
+        assert!(issues.iter().all(|i| i.id != "mn-5a0002"));
+    }
 
-        let yaml = serde_yaml::to_string(&response).unwrap();
-        assert!(yaml.contains("success: true"));
-        assert!(yaml.contains("initialized: true"));
-        assert!(yaml.contains("path: .manna"));
+    #[test]
+    fn test_squash_refuses_to_close_a_cycle() {
+        // K waits on X, X waits on D: redirecting X to K would loop
+        let mut keep = Issue::new("mn-5a0011".to_string(), "Keep".to_string()).unwrap();
+        keep.add_blocker("mn-5a0012".to_string());
+        let mut middle = Issue::new("mn-5a0012".to_string(), "Middle".to_string()).unwrap();
+        middle.add_blocker("mn-5a0013".to_string());
+        let dup = Issue::new("mn-5a0013".to_string(), "Dup".to_string()).unwrap();
+        let mut issues = vec![keep, middle, dup];
+        let before = issues.clone();
+
+        let err = squash(
+            &mut issues,
+            "mn-5a0011",
+            &["mn-5a0013".to_string()],
+            false,
+            &SessionId::new("ses_squash").unwrap(),
+        )
+        .unwrap_err();
+
+        assert!(err.contains("would create a cycle"), "{}", err);
+        assert!(BlockerGraph::new(&issues).cycles().is_empty());
+        let blockers: Vec<&Vec<String>> = issues.iter().map(|i| &i.blocked_by).collect();
+        assert_eq!(
+            blockers,
+            before.iter().map(|i| &i.blocked_by).collect::<Vec<_>>()
+        );
     }
 
     #[test]
-    fn test_error_response_serialization() {
-        let response = ErrorResponse {
-            success: false,
-            error: "Test error".to_string(),
-        };
+    fn test_reindex_ids_keeps_blocker_links() {
+        let mut base = Issue::new("bd-1".to_string(), "Base".to_string()).unwrap();
+        base.parent_id = Some("bd-3".to_string());
+        let mut follow = Issue::new("bd-2".to_string(), "Follow-up".to_string()).unwrap();
+        follow.add_blocker("bd-1".to_string());
+        let epic = Issue::new("bd-3".to_string(), "Epic".to_string()).unwrap();
+        let current = Issue::new("mn-4ed001".to_string(), "Current".to_string()).unwrap();
+        let mut issues = vec![base, follow, epic, current];
+
+        let reindexed = reindex_ids(&mut issues, Some("bd-"), MIN_ID_LENGTH);
+
+        let from: Vec<&str> = reindexed.iter().map(|r| r.from.as_str()).collect();
+        assert_eq!(from, vec!["bd-1", "bd-2", "bd-3"]);
+        assert!(issues[..3]
+            .iter()
+            .all(|i| manna_core::id::parse(&i.id).is_ok()));
+        assert_eq!(issues[3].id, "mn-4ed001");
 
-        let yaml = serde_yaml::to_string(&response).unwrap();
-        assert!(yaml.contains("success: false"));
-        assert!(yaml.contains("error: Test error"));
+        assert_eq!(issues[1].blocked_by, vec![issues[0].id.clone()]);
+        assert_eq!(issues[1].status, IssueStatus::Blocked);
+        assert_eq!(issues[0].parent_id.as_deref(), Some(issues[2].id.as_str()));
+
+        let graph = BlockerGraph::new(&issues);
+        assert!(graph.reaches(&issues[1].id, &issues[0].id));
     }
 
-    // Integration tests using temp directory
     #[test]
-    fn test_store_init_and_load() {
+    fn test_merge_stores_renames_colliding_id() {
         let (_temp_dir, store) = setup_store();
+        let (_other_dir, other) = setup_store();
+        let ses = SessionId::new("ses_other").unwrap();
+
+        store
+            .append_issue(&Issue::new("mn-3e0001".to_string(), "Local".to_string()).unwrap())
+            .unwrap();
+        let clash = Issue::new("mn-3e0001".to_string(), "Theirs".to_string()).unwrap();
+        let mut waiting = Issue::new("mn-3e0002".to_string(), "Waiting".to_string()).unwrap();
+        waiting.add_blocker(clash.id.clone());
+        other.append_issue(&clash).unwrap();
+        other.append_issue(&waiting).unwrap();
+        other
+            .append_session(&SessionEvent::block(
+                ses.clone(),
+                waiting.id.clone(),
+                serde_json::json!({ "blocker_id": clash.id }),
+            ))
+            .unwrap();
+
+        let mut issues = store.load_issues().unwrap();
+        let mut events = other.load_sessions().unwrap();
+        let data = merge_stores(
+            &mut issues,
+            other.load_issues().unwrap(),
+            &mut events,
+            MergeStrategy::Rename,
+            MIN_ID_LENGTH,
+            &ses,
+        );
+        store
+            .lock_issues()
+            .unwrap()
+            .replace_issues(&issues)
+            .unwrap();
+        store.append_sessions(&events).unwrap();
+
+        assert_eq!(data.renamed.len(), 1);
+        assert!(data.skipped.is_empty() && data.overwritten.is_empty());
+        let fresh = data.renamed[0].to.clone();
+        assert_eq!(data.renamed[0].from, "mn-3e0001");
+        assert_eq!(data.merged, vec![fresh.clone(), "mn-3e0002".to_string()]);
+
+        let merged = store.load_issues().unwrap();
+        let find = |id: &str| merged.iter().find(|i| i.id == id).unwrap();
+        assert_eq!(find("mn-3e0001").title, "Local");
+        assert_eq!(find(&fresh).title, "Theirs");
+        assert_eq!(find("mn-3e0002").blocked_by, vec![fresh.clone()]);
+        let logged = store.load_sessions().unwrap();
+        assert_eq!(
+            logged[0].context.as_ref().unwrap()["blocker_id"],
+            serde_json::json!(fresh)
+        );
 
-        assert!(store.is_initialized());
-        let issues = store.load_issues().unwrap();
-        assert!(issues.is_empty());
+        // The other log had no creates; the gap was logged so replay agrees
+        let replayed = replay::replay(&logged).issues;
+        let theirs: Vec<Issue> = merged
+            .iter()
+            .filter(|i| i.id != "mn-3e0001")
+            .cloned()
+            .collect();
+        assert!(replay::diverged(&theirs, &replayed).is_empty());
     }
 
     #[test]
-    fn test_create_and_retrieve_issue() {
-        let (_temp_dir, store) = setup_store();
-
-        let issue = Issue::new("mn-test01".to_string(), "Test Issue".to_string()).unwrap();
-        store.append_issue(&issue).unwrap();
+    fn test_followed_blockers_two_levels() {
+        let root = Issue::new("mn-f0b001".to_string(), "Root".to_string()).unwrap();
+        let mut middle = Issue::new("mn-f0b002".to_string(), "Middle".to_string()).unwrap();
+        middle.add_blocker(root.id.clone());
+        let mut top = Issue::new("mn-f0b003".to_string(), "Top".to_string()).unwrap();
+        top.add_blocker(middle.id.clone());
+        // Dangling blocker: no record to include
+        top.add_blocker("mn-f0b0ff".to_string());
+        let issues = vec![root, middle, top];
+
+        let followed = followed_blockers(&issues, "mn-f0b003", 2);
+        let found: Vec<(&str, usize)> = followed
+            .iter()
+            .map(|b| (b.issue.id.as_str(), b.depth))
+            .collect();
+        assert_eq!(found, vec![("mn-f0b002", 1), ("mn-f0b001", 2)]);
+        assert_eq!(followed[1].issue.title, "Root");
 
-        let issues = store.load_issues().unwrap();
-        assert_eq!(issues.len(), 1);
-        assert_eq!(issues[0].id, "mn-test01");
-        assert_eq!(issues[0].title, "Test Issue");
+        assert_eq!(followed_blockers(&issues, "mn-f0b003", 1).len(), 1);
     }
 
     #[test]
-    fn test_claim_and_release_workflow() {
-        let (_temp_dir, store) = setup_store();
-
-        let mut issue = Issue::new("mn-claim1".to_string(), "Claim Test".to_string()).unwrap();
-        store.append_issue(&issue).unwrap();
+    fn test_capabilities_lists_known_features() {
+        let caps = capabilities();
+
+        assert!(caps.fields.contains(&"due_at"));
+        assert!(caps.optional_fields.contains(&"deleted_at"));
+        assert!(caps.statuses.contains(&"in_progress".to_string()));
+        assert!(caps.formats.contains(&"agent-prompt".to_string()));
+        assert!(caps.global_flags.contains(&"--max-results".to_string()));
+        assert!(caps.commands.contains(&"capabilities".to_string()));
+        assert!(caps.commands.contains(&"merge-stores".to_string()));
+        // Hidden commands stay hidden
+        assert!(!caps.commands.contains(&"self-test".to_string()));
+    }
 
-        // Claim
-        issue.claim("ses_test".to_string()).unwrap();
-        store.update_issue(&issue).unwrap();
+    #[test]
+    fn test_concurrency_self_test_harness() {
+        let temp_dir = TempDir::new().unwrap();
 
-        let issues = store.load_issues().unwrap();
-        assert_eq!(issues[0].status, IssueStatus::InProgress);
-        assert_eq!(issues[0].claimed_by, Some("ses_test".to_string()));
+        let report = concurrency_self_test(temp_dir.path(), MannaStoreConfig::default(), 4, 5);
 
-        // Release
-        issue.release().unwrap();
-        store.update_issue(&issue).unwrap();
+        assert!(report.passed, "errors: {:?}", report.errors);
+        assert_eq!(report.lock_strategy, "flock");
+        assert_eq!((report.expected, report.found), (20, 20));
+        assert_eq!(report.lost_writes, 0);
+        // The scratch store is cleaned up
+        assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+    }
 
-        let issues = store.load_issues().unwrap();
-        assert_eq!(issues[0].status, IssueStatus::Open);
-        assert!(issues[0].claimed_by.is_none());
+    #[test]
+    fn test_squash_rejects_self_merge() {
+        let mut issues = vec![Issue::new("mn-5a0005".to_string(), "Solo".to_string()).unwrap()];
+        let err = squash(
+            &mut issues,
+            "mn-5a0005",
+            &["mn-5a0005".to_string()],
+            false,
+            &SessionId::new("ses_squash").unwrap(),
+        )
+        .unwrap_err();
+        assert!(err.contains("into itself"));
     }
 
     #[test]
-    fn test_complete_workflow() {
+    fn test_release_all_frees_only_own_claims() {
         let (_temp_dir, store) = setup_store();
+        let me = SessionId::new("ses_leaving").unwrap();
+        let other = SessionId::new("ses_other").unwrap();
+        for (id, holder) in [
+            ("mn-ab0001", &me),
+            ("mn-ab0002", &me),
+            ("mn-ab0003", &other),
+        ] {
+            let mut issue = Issue::new(id.to_string(), "Held".to_string()).unwrap();
+            issue.claim(holder.clone()).unwrap();
+            store.append_issue(&issue).unwrap();
+        }
+
+        let mut issues = store.load_issues().unwrap();
+        let released = release_all(&mut issues, &me);
+        store
+            .lock_issues()
+            .unwrap()
+            .replace_issues(&issues)
+            .unwrap();
+
+        assert_eq!(released, vec!["mn-ab0001", "mn-ab0002"]);
+        let stored = store.load_issues().unwrap();
+        assert!(stored[..2]
+            .iter()
+            .all(|i| i.status == IssueStatus::Open && i.claimed_by.is_none()));
+        assert_eq!(stored[2].claimed_by.as_deref(), Some("ses_other"));
+    }
 
-        let mut issue = Issue::new("mn-done01".to_string(), "Complete Test".to_string()).unwrap();
-        store.append_issue(&issue).unwrap();
+    #[test]
+    fn test_abandon_requires_id_or_all() {
+        assert!(Cli::try_parse_from(["manna-core", "abandon"]).is_err());
+        assert!(Cli::try_parse_from(["manna-core", "abandon", "--all"]).is_ok());
+        assert!(Cli::try_parse_from(["manna-core", "abandon", "mn-ab0001", "--all"]).is_err());
+    }
 
-        issue.claim("ses_test".to_string()).unwrap();
-        store.update_issue(&issue).unwrap();
+    #[test]
+    fn test_unassign_all_clears_every_assignment() {
+        let (_temp_dir, store) = setup_store();
+        for id in ["mn-ua1001", "mn-ua1002", "mn-ua1003", "mn-ua1004"] {
+            let mut issue = Issue::new(id.to_string(), "Work".to_string()).unwrap();
+            if id != "mn-ua1004" {
+                issue.assignee = Some("agent-leaving".to_string());
+            } else {
+                issue.assignee = Some("agent-staying".to_string());
+            }
+            store.append_issue(&issue).unwrap();
+        }
+
+        let mut issues = store.load_issues().unwrap();
+        let unassigned = unassign_all(&mut issues, "agent-leaving");
+        store
+            .lock_issues()
+            .unwrap()
+            .replace_issues(&issues)
+            .unwrap();
+
+        assert_eq!(unassigned, vec!["mn-ua1001", "mn-ua1002", "mn-ua1003"]);
+        let stored = store.load_issues().unwrap();
+        assert!(stored[..3].iter().all(|i| i.assignee.is_none()));
+        assert_eq!(stored[3].assignee.as_deref(), Some("agent-staying"));
+    }
 
-        issue.complete().unwrap();
-        store.update_issue(&issue).unwrap();
+    /// Three live issues, one of which is then trashed.
+    fn trash_mix(store: &MannaStore) -> Vec<Issue> {
+        for id in ["mn-7a6001", "mn-7a6002", "mn-7a6003"] {
+            let issue = Issue::new(id.to_string(), "Maybe".to_string()).unwrap();
+            store.append_issue(&issue).unwrap();
+        }
+        let mut oops = store.load_issues().unwrap().remove(1);
+        oops.trash().unwrap();
+        store.update_issue(&oops).unwrap();
+        store.load_issues().unwrap()
+    }
 
-        let issues = store.load_issues().unwrap();
-        assert_eq!(issues[0].status, IssueStatus::Done);
+    fn live_ids(issues: &[Issue], filter: &ListFilter) -> Vec<String> {
+        issues
+            .iter()
+            .filter(|i| filter.matches(i))
+            .map(|i| i.id.clone())
+            .collect()
     }
 
     #[test]
-    fn test_block_workflow() {
+    fn test_trash_hides_from_views() {
         let (_temp_dir, store) = setup_store();
+        let issues = trash_mix(&store);
 
-        let blocker = Issue::new("mn-block1".to_string(), "Blocker".to_string()).unwrap();
-        store.append_issue(&blocker).unwrap();
+        let filter = ListFilter::new(None, &[]).unwrap();
+        assert_eq!(live_ids(&issues, &filter), vec!["mn-7a6001", "mn-7a6003"]);
+        let trash = ListFilter::new(None, &[]).unwrap().with_trashed(true);
+        assert_eq!(live_ids(&issues, &trash), vec!["mn-7a6002"]);
+        assert_eq!(without_trashed(issues).len(), 2);
+    }
 
-        let mut issue = Issue::new("mn-block2".to_string(), "Blocked Issue".to_string()).unwrap();
-        store.append_issue(&issue).unwrap();
+    #[test]
+    fn test_restore_reveals() {
+        let (_temp_dir, store) = setup_store();
+        let mut oops = trash_mix(&store).remove(1);
 
-        issue.add_blocker("mn-block1".to_string());
-        store.update_issue(&issue).unwrap();
+        oops.restore().unwrap();
+        store.update_issue(&oops).unwrap();
 
+        let filter = ListFilter::new(None, &[]).unwrap();
         let issues = store.load_issues().unwrap();
-        let blocked_issue = issues.iter().find(|i| i.id == "mn-block2").unwrap();
-        assert_eq!(blocked_issue.status, IssueStatus::Blocked);
-        assert!(blocked_issue.blocked_by.contains(&"mn-block1".to_string()));
+        assert_eq!(live_ids(&issues, &filter).len(), 3);
     }
 
     #[test]
-    fn test_unblock_workflow() {
+    fn test_empty_trash_removes() {
         let (_temp_dir, store) = setup_store();
+        trash_mix(&store);
+        let mut waiting = Issue::new("mn-7a6004".to_string(), "Waiting".to_string()).unwrap();
+        waiting.add_blocker("mn-7a6002".to_string());
+        store.append_issue(&waiting).unwrap();
+        let mut issues = store.load_issues().unwrap();
+
+        // Trashed just now, so not older than an hour
+        let cutoff = Utc::now() - chrono::Duration::hours(1);
+        assert!(empty_trash(&mut issues.clone(), Some(cutoff)).0.is_empty());
+
+        let (deleted, stripped) = empty_trash(&mut issues, None);
+        store
+            .lock_issues()
+            .unwrap()
+            .replace_issues(&issues)
+            .unwrap();
+
+        assert_eq!(deleted, vec!["mn-7a6002"]);
+        assert_eq!(stripped.len(), 1);
+        let remaining = store.load_issues().unwrap();
+        assert_eq!(remaining.len(), 3);
+        assert!(remaining.iter().all(|i| i.blocked_by.is_empty()));
+    }
 
-        let blocker = Issue::new("mn-unblk1".to_string(), "Blocker".to_string()).unwrap();
-        store.append_issue(&blocker).unwrap();
+    #[test]
+    fn test_replay_restores_lost_issues_file() {
+        let (_temp_dir, store) = setup_store();
+        let ses = SessionId::new("ses_replay").unwrap();
+
+        let mut base = Issue::new("mn-9e0001".to_string(), "Base".to_string()).unwrap();
+        base.description = Some("Root work".to_string());
+        let mut follow = Issue::new("mn-9e0002".to_string(), "Follow-up".to_string()).unwrap();
+        for issue in [&base, &follow] {
+            store.append_issue(issue).unwrap();
+            record_event(
+                &store,
+                &SessionEvent::create(
+                    ses.clone(),
+                    issue.id.clone(),
+                    serde_json::json!({
+                        "title": issue.title,
+                        "description": issue.description,
+                        "due_at": issue.due_at,
+                    }),
+                ),
+            );
+        }
+        follow.add_blocker(base.id.clone());
+        record_event(
+            &store,
+            &SessionEvent::block(
+                ses.clone(),
+                follow.id.clone(),
+                serde_json::json!({ "blocker_id": base.id }),
+            ),
+        );
+        base.claim(ses.clone()).unwrap();
+        record_event(&store, &SessionEvent::claim(ses.clone(), base.id.clone()));
+        store
+            .lock_issues()
+            .unwrap()
+            .replace_issues(&[base, follow])
+            .unwrap();
+        let before = store.load_issues().unwrap();
+
+        std::fs::remove_file(store.manna_dir().join("issues.jsonl")).unwrap();
+        store.repair().unwrap();
+        let replayed = replay::replay(&store.load_sessions().unwrap());
+        store
+            .lock_issues()
+            .unwrap()
+            .replace_issues(&replayed.issues)
+            .unwrap();
+
+        let after = store.load_issues().unwrap();
+        assert_eq!(replayed.skipped_events, 0);
+        assert_eq!(after.len(), before.len());
+        for (was, now) in before.iter().zip(&after) {
+            assert_eq!(now.id, was.id);
+            assert_eq!(now.title, was.title);
+            assert_eq!(now.description, was.description);
+            assert_eq!(now.status, was.status);
+            assert_eq!(now.claimed_by, was.claimed_by);
+            assert_eq!(now.blocked_by, was.blocked_by);
+        }
+    }
 
-        let mut issue = Issue::new("mn-unblk2".to_string(), "Blocked".to_string()).unwrap();
-        issue.add_blocker("mn-unblk1".to_string());
-        store.append_issue(&issue).unwrap();
+    #[test]
+    fn test_bulk_delete_done_matches_dry_run() {
+        let (_temp_dir, store) = setup_store();
+        let mut done = Issue::new("mn-de1001".to_string(), "Done".to_string()).unwrap();
+        done.claim(SessionId::new("ses_del").unwrap()).unwrap();
+        done.complete().unwrap();
+        let open = Issue::new("mn-de1002".to_string(), "Open".to_string()).unwrap();
+        let mut waiting = Issue::new("mn-de1003".to_string(), "Waiting".to_string()).unwrap();
+        waiting.add_blocker("mn-de1001".to_string());
+        for issue in [&done, &open, &waiting] {
+            store.append_issue(issue).unwrap();
+        }
+
+        let mut preview = store.load_issues().unwrap();
+        let (would_delete, would_strip) = bulk_delete(&mut preview, Some(&IssueStatus::Done), None);
+
+        let mut issues = store.load_issues().unwrap();
+        let (deleted, stripped) = bulk_delete(&mut issues, Some(&IssueStatus::Done), None);
+        store
+            .lock_issues()
+            .unwrap()
+            .replace_issues(&issues)
+            .unwrap();
+
+        assert_eq!(deleted, vec!["mn-de1001".to_string()]);
+        assert_eq!(deleted, would_delete);
+        assert_eq!(stripped.len(), 1);
+        assert_eq!(would_strip.len(), 1);
+
+        let remaining = store.load_issues().unwrap();
+        let ids: Vec<&str> = remaining.iter().map(|i| i.id.as_str()).collect();
+        assert_eq!(ids, vec!["mn-de1002", "mn-de1003"]);
+        assert!(remaining[1].blocked_by.is_empty());
+        assert_eq!(remaining[1].status, IssueStatus::Open);
+    }
 
-        issue.remove_blocker("mn-unblk1");
-        store.update_issue(&issue).unwrap();
+    #[test]
+    fn test_bulk_transition_reopens_done_issues() {
+        let (_temp_dir, store) = setup_store();
 
+        for n in 0..2 {
+            let mut done = Issue::new(format!("mn-bulk0{}", n), "Done".to_string()).unwrap();
+            done.claim(SessionId::new("ses_old").unwrap()).unwrap();
+            done.complete().unwrap();
+            store.append_issue(&done).unwrap();
+        }
+        let open = Issue::new("mn-bulk09".to_string(), "Open".to_string()).unwrap();
+        store.append_issue(&open).unwrap();
+
+        let mut issues = store.load_issues().unwrap();
+        let (transitioned, skipped) = bulk_transition(
+            &mut issues,
+            Some(&IssueStatus::Done),
+            &IssueStatus::Open,
+            &SessionId::new("ses").unwrap(),
+        );
+        store
+            .lock_issues()
+            .unwrap()
+            .replace_issues(&issues)
+            .unwrap();
+
+        assert_eq!(transitioned, vec!["mn-bulk00", "mn-bulk01"]);
+        assert!(skipped.is_empty());
         let issues = store.load_issues().unwrap();
-        let unblocked = issues.iter().find(|i| i.id == "mn-unblk2").unwrap();
-        assert_eq!(unblocked.status, IssueStatus::Open);
-        assert!(unblocked.blocked_by.is_empty());
+        assert!(issues.iter().all(|i| i.status == IssueStatus::Open));
+        assert!(issues.iter().all(|i| i.validate().is_ok()));
+    }
+
+    #[test]
+    fn test_bulk_transition_skips_illegal() {
+        let mut issues = vec![
+            Issue::new("mn-bulk10".to_string(), "Open".to_string()).unwrap(),
+            Issue::new("mn-bulk11".to_string(), "Open".to_string()).unwrap(),
+        ];
+        issues[1].claim(SessionId::new("ses").unwrap()).unwrap();
+
+        // Open issues cannot jump straight to done
+        let (transitioned, skipped) = bulk_transition(
+            &mut issues,
+            None,
+            &IssueStatus::Done,
+            &SessionId::new("ses").unwrap(),
+        );
+
+        assert_eq!(transitioned, vec!["mn-bulk11"]);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].id, "mn-bulk10");
     }
 
     #[test]
     fn test_context_generation() {
-        let issues = vec![
+        let issues = [
             Issue::new("mn-ctx001".to_string(), "Open Issue".to_string()).unwrap(),
             {
                 let mut i = Issue::new("mn-ctx002".to_string(), "In Progress".to_string()).unwrap();
-                i.claim("ses_test".to_string()).unwrap();
+                i.claim(SessionId::new("ses_test").unwrap()).unwrap();
                 i
             },
             {
@@ -952,14 +8411,289 @@ mod tests {
         assert_eq!(blocked.len(), 1);
     }
 
+    #[test]
+    fn test_list_jsonl_round_trip() {
+        let open = Issue::new("mn-a51001".to_string(), "Open".to_string()).unwrap();
+        let mut done = Issue::new("mn-a51002".to_string(), "Done".to_string()).unwrap();
+        done.claim(SessionId::new("ses").unwrap()).unwrap();
+        done.complete().unwrap();
+        let issues = [open, done];
+
+        let filter = ListFilter::new(Some("open"), &[]).unwrap();
+        let matching: Vec<Issue> = issues
+            .iter()
+            .filter(|i| filter.matches(i))
+            .cloned()
+            .collect();
+        let bytes = export::to_jsonl(&matching).unwrap();
+        let text = String::from_utf8(bytes).unwrap();
+
+        let parsed: Vec<Issue> = text
+            .lines()
+            .map(|line| serde_json::from_str::<Issue>(line).unwrap())
+            .collect();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].id, "mn-a51001");
+        assert_eq!(parsed[0].created_at, issues[0].created_at);
+    }
+
+    fn claim_mix(store: &MannaStore) -> Vec<Issue> {
+        let free = Issue::new("mn-c1a001".to_string(), "Free".to_string()).unwrap();
+        let mut held = Issue::new("mn-c1a002".to_string(), "Held".to_string()).unwrap();
+        held.claim(SessionId::new("ses_a").unwrap()).unwrap();
+        let mut also_held = Issue::new("mn-c1a003".to_string(), "Held".to_string()).unwrap();
+        also_held.claim(SessionId::new("ses_b").unwrap()).unwrap();
+        for issue in [&free, &held, &also_held] {
+            store.append_issue(issue).unwrap();
+        }
+        store.load_issues().unwrap()
+    }
+
+    #[test]
+    fn test_list_fields_projects_id_only() {
+        let mut issue = Issue::new("mn-f1e1d0".to_string(), "Projected".to_string()).unwrap();
+        issue.description = Some("Not wanted".to_string());
+        issue.claim(SessionId::new("ses_fields").unwrap()).unwrap();
+
+        let fields = parse_fields(&["id".to_string()]).unwrap();
+        let projected = project_fields(&issue, &fields);
+
+        assert_eq!(projected.len(), 1);
+        assert_eq!(projected["id"], "mn-f1e1d0");
+    }
+
+    #[test]
+    fn test_list_fields_comma_list_and_unknown() {
+        let issue = Issue::new("mn-f1e1d1".to_string(), "Projected".to_string()).unwrap();
+
+        let cli = Cli::try_parse_from(["manna-core", "list", "--fields", "title, status"]).unwrap();
+        let Commands::List(args) = cli.command else {
+            panic!("expected list");
+        };
+        let fields = parse_fields(&args.fields).unwrap();
+        let keys: Vec<String> = project_fields(&issue, &fields)
+            .into_iter()
+            .map(|(k, _)| k)
+            .collect();
+        assert_eq!(keys, vec!["status", "title"]);
+
+        let err = parse_fields(&["id".to_string(), "priority".to_string()]).unwrap_err();
+        assert!(err.contains("Unknown field 'priority'"));
+    }
+
+    #[test]
+    fn test_list_filter_claimed() {
+        let (_temp_dir, store) = setup_store();
+        let issues = claim_mix(&store);
+
+        let filter = ListFilter::new(None, &[]).unwrap().with_claimed(Some(true));
+        let ids: Vec<&str> = issues
+            .iter()
+            .filter(|i| filter.matches(i))
+            .map(|i| i.id.as_str())
+            .collect();
+
+        assert_eq!(ids, vec!["mn-c1a002", "mn-c1a003"]);
+    }
+
+    #[test]
+    fn test_list_filter_unclaimed_with_status() {
+        let (_temp_dir, store) = setup_store();
+        let issues = claim_mix(&store);
+
+        let filter = ListFilter::new(Some("open"), &[])
+            .unwrap()
+            .with_claimed(Some(false));
+        let ids: Vec<&str> = issues
+            .iter()
+            .filter(|i| filter.matches(i))
+            .map(|i| i.id.as_str())
+            .collect();
+
+        assert_eq!(ids, vec!["mn-c1a001"]);
+    }
+
+    #[test]
+    fn test_list_filter_claimed_by_session() {
+        let (_temp_dir, store) = setup_store();
+        let mut issues = claim_mix(&store);
+        let mut second = Issue::new("mn-c1a004".to_string(), "Held".to_string()).unwrap();
+        second.claim(SessionId::new("ses_a").unwrap()).unwrap();
+        second.complete().unwrap();
+        issues.push(second);
+
+        let cli = Cli::try_parse_from(["manna-core", "list", "--claimed-by", "ses_a"]).unwrap();
+        let Commands::List(args) = cli.command else {
+            panic!("expected list");
+        };
+        let filter = ListFilter::new(None, &[])
+            .unwrap()
+            .with_claimed_by(args.claimed_by);
+        let ids: Vec<&str> = issues
+            .iter()
+            .filter(|i| filter.matches(i))
+            .map(|i| i.id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["mn-c1a002", "mn-c1a004"]);
+
+        let filter = ListFilter::new(Some("in_progress"), &[])
+            .unwrap()
+            .with_claimed_by(Some("ses_a".to_string()));
+        let ids: Vec<&str> = issues
+            .iter()
+            .filter(|i| filter.matches(i))
+            .map(|i| i.id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["mn-c1a002"]);
+    }
+
+    /// Two described issues and two bare ones.
+    fn description_mix() -> Vec<Issue> {
+        ["mn-de5001", "mn-de5002", "mn-de5003", "mn-de5004"]
+            .iter()
+            .enumerate()
+            .map(|(n, id)| {
+                let mut issue = Issue::new(id.to_string(), "Groom me".to_string()).unwrap();
+                if n % 2 == 0 {
+                    issue.description = Some("Steps to reproduce".to_string());
+                }
+                issue
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_list_filter_has_description() {
+        let issues = description_mix();
+
+        let filter = ListFilter::new(None, &[])
+            .unwrap()
+            .with_described(Some(true));
+        let ids: Vec<&str> = issues
+            .iter()
+            .filter(|i| filter.matches(i))
+            .map(|i| i.id.as_str())
+            .collect();
+
+        assert_eq!(ids, vec!["mn-de5001", "mn-de5003"]);
+    }
+
+    #[test]
+    fn test_list_filter_no_description() {
+        let issues = description_mix();
+
+        let filter = ListFilter::new(None, &[])
+            .unwrap()
+            .with_described(Some(false));
+        let ids: Vec<&str> = issues
+            .iter()
+            .filter(|i| filter.matches(i))
+            .map(|i| i.id.as_str())
+            .collect();
+
+        assert_eq!(ids, vec!["mn-de5002", "mn-de5004"]);
+    }
+
+    #[test]
+    fn test_list_filter_since() {
+        let (_temp_dir, store) = setup_store();
+        for id in ["mn-5ce001", "mn-5ce002"] {
+            let issue = Issue::new(id.to_string(), "Sync me".to_string()).unwrap();
+            store.append_issue(&issue).unwrap();
+        }
+
+        let cursor = Utc::now();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        let mut issue = store.load_issues().unwrap().remove(1);
+        issue.title = "Synced".to_string();
+        issue.updated_at = Utc::now();
+        store.update_issue(&issue).unwrap();
+
+        let since = parse_since(&cursor.to_rfc3339()).unwrap();
+        let filter = ListFilter::new(None, &[]).unwrap().with_since(Some(since));
+        let ids: Vec<String> = store
+            .load_issues()
+            .unwrap()
+            .into_iter()
+            .filter(|i| filter.matches(i))
+            .map(|i| i.id)
+            .collect();
+
+        assert_eq!(ids, vec!["mn-5ce002"]);
+        assert!(parse_since("yesterday").is_err());
+    }
+
+    #[test]
+    fn test_list_since_cursor_in_unix_time() {
+        let as_of = DateTime::parse_from_rfc3339("2025-01-15T10:00:00.750Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let mut value = serde_json::to_value(SuccessResponse {
+            success: true,
+            data: IssueListData {
+                issues: Vec::new(),
+                truncated: false,
+                as_of: Some(as_of),
+            },
+        })
+        .unwrap();
+        unix_timestamps(&mut value);
+
+        assert_eq!(value["as_of"], 1736935200);
+        // The emitted cursor is accepted back by the next poll, rounded down
+        // so nothing written in that second is skipped
+        let cursor = parse_since(&value["as_of"].to_string()).unwrap();
+        assert_eq!(cursor.timestamp(), as_of.timestamp());
+        assert!(cursor <= as_of);
+    }
+
+    #[test]
+    fn test_list_filter_single_exclusion() {
+        let filter = ListFilter::new(None, &["done".to_string()]).unwrap();
+
+        let open = Issue::new("mn-exc001".to_string(), "Open".to_string()).unwrap();
+        let mut done = Issue::new("mn-exc002".to_string(), "Done".to_string()).unwrap();
+        done.claim(SessionId::new("ses").unwrap()).unwrap();
+        done.complete().unwrap();
+
+        assert!(filter.matches(&open));
+        assert!(!filter.matches(&done));
+    }
+
+    #[test]
+    fn test_list_filter_multiple_exclusions() {
+        let filter = ListFilter::new(None, &["done".to_string(), "blocked".to_string()]).unwrap();
+
+        let open = Issue::new("mn-exc003".to_string(), "Open".to_string()).unwrap();
+        let mut blocked = Issue::new("mn-exc004".to_string(), "Blocked".to_string()).unwrap();
+        blocked.add_blocker("mn-exc003".to_string());
+        let mut in_progress = Issue::new("mn-exc005".to_string(), "Working".to_string()).unwrap();
+        in_progress.claim(SessionId::new("ses").unwrap()).unwrap();
+
+        assert!(filter.matches(&open));
+        assert!(filter.matches(&in_progress));
+        assert!(!filter.matches(&blocked));
+    }
+
+    #[test]
+    fn test_list_filter_include_and_exclude_conflict() {
+        let result = ListFilter::new(Some("open"), &["open".to_string()]);
+        assert!(result.unwrap_err().contains("both included and excluded"));
+
+        // Non-conflicting combination is fine
+        let filter = ListFilter::new(Some("open"), &["done".to_string()]).unwrap();
+        let open = Issue::new("mn-exc006".to_string(), "Open".to_string()).unwrap();
+        assert!(filter.matches(&open));
+    }
+
     #[test]
     fn test_list_filtering() {
-        let issues = vec![
+        let issues = [
             Issue::new("mn-flt001".to_string(), "Open 1".to_string()).unwrap(),
             Issue::new("mn-flt002".to_string(), "Open 2".to_string()).unwrap(),
             {
                 let mut i = Issue::new("mn-flt003".to_string(), "Done".to_string()).unwrap();
-                i.claim("ses".to_string()).unwrap();
+                i.claim(SessionId::new("ses").unwrap()).unwrap();
                 i.complete().unwrap();
                 i
             },
@@ -0,0 +1,637 @@
+//! Rebuild issue state by folding the session log.
+//!
+//! A disaster-recovery path for when `issues.jsonl` is lost but
+//! `sessions.jsonl` survives. Only what the log records can come back:
+//! each issue's ID, title, description and due date from its `create`
+//! event, blockers from `block`/`unblock`, later field changes (title,
+//! description, due date, assignee, rank, parent, attachment paths, trash
+//! state) from `edit`, lifecycle changes from `claim`, `release`, `done`
+//! and `reopen`, and permanent removal from `delete`. Timestamps are taken
+//! from the events.
+//!
+//! The log is only complete if every command that changed an issue logged
+//! it; `diverged` compares a replay against the live issues to tell, and
+//! `events_between` gives the events to log for an arbitrary change.
+
+use chrono::{DateTime, Utc};
+
+use crate::issue::{Attachment, Issue, IssueStatus, SessionEvent, SessionEventType, SessionId};
+
+/// Issues rebuilt from the session log.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    /// Rebuilt issues, in creation order
+    pub issues: Vec<Issue>,
+
+    /// Issue events that could not be applied: no `create` for the issue,
+    /// a malformed change summary, or a transition the issue was not in a
+    /// state to take
+    pub skipped_events: usize,
+}
+
+/// Fold `events` (in timestamp order) into a fresh issue set.
+pub fn replay(events: &[SessionEvent]) -> Replay {
+    let mut ordered: Vec<&SessionEvent> = events.iter().collect();
+    ordered.sort_by_key(|e| e.timestamp);
+
+    let mut issues: Vec<Issue> = Vec::new();
+    let mut skipped_events = 0;
+
+    for event in ordered {
+        let Some(issue_id) = event.issue_id.as_deref() else {
+            continue;
+        };
+        let applied = match issues.iter().position(|i| i.id == issue_id) {
+            None if event.event == SessionEventType::Create => match created(issue_id, event) {
+                Some(issue) => {
+                    issues.push(issue);
+                    true
+                }
+                None => false,
+            },
+            None => false,
+            Some(idx) if event.event == SessionEventType::Delete => {
+                deleted(&mut issues, idx, event.timestamp);
+                true
+            }
+            Some(idx) => apply(&mut issues[idx], event),
+        };
+        if !applied && event.event != SessionEventType::Note {
+            skipped_events += 1;
+        }
+    }
+
+    Replay {
+        issues,
+        skipped_events,
+    }
+}
+
+/// Build an issue from its `create` event.
+fn created(issue_id: &str, event: &SessionEvent) -> Option<Issue> {
+    let context = event.context.as_ref()?;
+    let title = context.get("title")?.as_str()?;
+    let mut issue = Issue::new(issue_id.to_string(), title.to_string()).ok()?;
+    issue.description = string_field(context, "description");
+    issue.due_at = time_field(context, "due_at");
+    issue.created_at = event.timestamp;
+    issue.updated_at = event.timestamp;
+    Some(issue)
+}
+
+/// Remove `issues[idx]` as a permanent delete does: surviving issues lose
+/// their blocker references to it and its children become roots.
+fn deleted(issues: &mut Vec<Issue>, idx: usize, at: DateTime<Utc>) {
+    let gone = issues.remove(idx).id;
+    for issue in issues.iter_mut() {
+        let mut touched = false;
+        if issue.blocked_by.contains(&gone) {
+            issue.remove_blocker(&gone);
+            touched = true;
+        }
+        if issue.parent_id.as_ref() == Some(&gone) {
+            issue.parent_id = None;
+            touched = true;
+        }
+        if touched {
+            issue.updated_at = at;
+        }
+    }
+}
+
+/// IDs of issues whose recoverable state differs between the live
+/// `current` issues and a `replayed` set, including issues present in only
+/// one of them. Fields the log never records (attachment descriptions,
+/// timestamps) are not compared.
+pub fn diverged(current: &[Issue], replayed: &[Issue]) -> Vec<String> {
+    let mut ids = Vec::new();
+    for issue in current {
+        match replayed.iter().find(|r| r.id == issue.id) {
+            Some(rebuilt) if same_recoverable_state(issue, rebuilt) => {}
+            _ => ids.push(issue.id.clone()),
+        }
+    }
+    for rebuilt in replayed {
+        if !current.iter().any(|i| i.id == rebuilt.id) {
+            ids.push(rebuilt.id.clone());
+        }
+    }
+    ids
+}
+
+/// Whether two versions of an issue agree on everything replay restores.
+fn same_recoverable_state(a: &Issue, b: &Issue) -> bool {
+    let sorted = |ids: &[String]| {
+        let mut ids = ids.to_vec();
+        ids.sort();
+        ids
+    };
+    let paths = |issue: &Issue| {
+        let mut paths: Vec<String> = issue.attachments.iter().map(|a| a.path.clone()).collect();
+        paths.sort();
+        paths
+    };
+    a.title == b.title
+        && a.description == b.description
+        && a.due_at == b.due_at
+        && a.assignee == b.assignee
+        && a.status == b.status
+        && a.claimed_by == b.claimed_by
+        && sorted(&a.blocked_by) == sorted(&b.blocked_by)
+        && a.rank == b.rank
+        && a.parent_id == b.parent_id
+        && a.deleted_at == b.deleted_at
+        && paths(a) == paths(b)
+}
+
+/// Apply one event to an existing issue, returning whether it took effect.
+fn apply(issue: &mut Issue, event: &SessionEvent) -> bool {
+    let context = event.context.as_ref();
+    let applied = match event.event {
+        SessionEventType::Claim => match issue.claim(event.session_id.clone()) {
+            Ok(()) => {
+                issue.claimed_at = Some(event.timestamp);
+                true
+            }
+            Err(_) => false,
+        },
+        SessionEventType::Release => issue.release().is_ok(),
+        SessionEventType::Done => issue.complete().is_ok(),
+        SessionEventType::Reopen => issue.reopen().is_ok(),
+        SessionEventType::Block => match context.and_then(|c| string_field(c, "blocker_id")) {
+            Some(blocker_id) => {
+                issue.add_blocker(blocker_id);
+                true
+            }
+            None => false,
+        },
+        SessionEventType::Unblock => match context.and_then(|c| string_field(c, "blocker_id")) {
+            Some(blocker_id) => {
+                issue.remove_blocker(&blocker_id);
+                true
+            }
+            None => false,
+        },
+        SessionEventType::Edit => context.is_some_and(|c| apply_edit(issue, c, event.timestamp)),
+        // Notes carry no state; a second create for the same ID is ignored
+        SessionEventType::Note | SessionEventType::Create => false,
+        // Handled by `replay`, which needs the whole issue set
+        SessionEventType::Delete => false,
+        SessionEventType::Start | SessionEventType::End => false,
+    };
+    if applied {
+        issue.updated_at = event.timestamp;
+    }
+    applied
+}
+
+/// Apply the change summary of an `edit` event.
+fn apply_edit(issue: &mut Issue, context: &serde_json::Value, at: DateTime<Utc>) -> bool {
+    let Some(changes) = context.as_object() else {
+        return false;
+    };
+
+    let mut applied = false;
+    for (key, value) in changes {
+        match key.as_str() {
+            "title" => match value.as_str().filter(|t| !t.trim().is_empty()) {
+                Some(title) => issue.title = title.to_string(),
+                None => continue,
+            },
+            "description" => issue.description = value.as_str().map(str::to_string),
+            "due_at" => issue.due_at = time_field(context, key),
+            "assignee" => issue.assignee = value.as_str().map(str::to_string),
+            "rank" => issue.rank = value.as_f64(),
+            "parent_id" => issue.parent_id = value.as_str().map(str::to_string),
+            "deleted_at" => issue.deleted_at = time_field(context, key),
+            "attached" => match value.as_str() {
+                Some(path) if !issue.attachments.iter().any(|a| a.path == path) => {
+                    issue.attachments.push(Attachment {
+                        path: path.to_string(),
+                        description: None,
+                        at,
+                    });
+                }
+                _ => continue,
+            },
+            "detached" => match value.as_str() {
+                Some(path) => issue.attachments.retain(|a| a.path != path),
+                None => continue,
+            },
+            _ => continue,
+        }
+        applied = true;
+    }
+    applied
+}
+
+/// Where an issue is in its claim lifecycle, ignoring blockers.
+#[derive(PartialEq)]
+enum Lifecycle {
+    Unclaimed,
+    Claimed(String),
+    Done(Option<String>),
+}
+
+fn lifecycle(issue: &Issue) -> Lifecycle {
+    match (&issue.status, &issue.claimed_by) {
+        (IssueStatus::Done, holder) => Lifecycle::Done(holder.clone()),
+        (_, Some(holder)) => Lifecycle::Claimed(holder.clone()),
+        (_, None) => Lifecycle::Unclaimed,
+    }
+}
+
+/// The events that, replayed, take `before` to `after`, or create `after`
+/// when there is no `before`. `session_id` is logged as the actor, except
+/// on claims, which carry the claiming session as replay expects.
+///
+/// Each event is applied to a working copy as it is chosen, so the
+/// sequence is one replay accepts: a lifecycle change first lifts any
+/// blockers and trash that would refuse it. A done issue without a claim
+/// cannot be logged exactly and ends up claimed by `session_id`.
+pub fn events_between(
+    session_id: &SessionId,
+    before: Option<&Issue>,
+    after: &Issue,
+) -> Vec<SessionEvent> {
+    let mut events = Vec::new();
+    let mut cur = match before {
+        Some(issue) => issue.clone(),
+        None => {
+            let create = SessionEvent::create(
+                session_id.clone(),
+                after.id.clone(),
+                serde_json::json!({
+                    "title": after.title,
+                    "description": after.description,
+                    "due_at": after.due_at,
+                }),
+            );
+            let Some(issue) = created(&after.id, &create) else {
+                return events;
+            };
+            events.push(create);
+            issue
+        }
+    };
+    let mut log = |cur: &mut Issue, event: SessionEvent| {
+        apply(cur, &event);
+        events.push(event);
+    };
+    let edit = |changes: serde_json::Value| {
+        SessionEvent::edit(session_id.clone(), after.id.clone(), changes)
+    };
+    let actor = |holder: Option<&String>| {
+        holder
+            .and_then(|h| SessionId::new(h.as_str()).ok())
+            .unwrap_or_else(|| session_id.clone())
+    };
+
+    let target = lifecycle(after);
+    if lifecycle(&cur) != target {
+        for blocker_id in cur.blocked_by.clone() {
+            let unblock = SessionEvent::unblock(
+                session_id.clone(),
+                after.id.clone(),
+                serde_json::json!({ "blocker_id": blocker_id }),
+            );
+            log(&mut cur, unblock);
+        }
+        if cur.deleted_at.is_some() {
+            log(&mut cur, edit(serde_json::json!({ "deleted_at": null })));
+        }
+
+        // Step down to unclaimed, unless only completion is missing
+        let finish_own_claim = matches!(
+            (&lifecycle(&cur), &target),
+            (Lifecycle::Claimed(a), Lifecycle::Done(Some(b))) if a == b
+        );
+        if !finish_own_claim {
+            match lifecycle(&cur) {
+                Lifecycle::Claimed(_) => log(
+                    &mut cur,
+                    SessionEvent::release(session_id.clone(), after.id.clone()),
+                ),
+                Lifecycle::Done(_) => log(
+                    &mut cur,
+                    SessionEvent::reopen(
+                        session_id.clone(),
+                        after.id.clone(),
+                        serde_json::json!({}),
+                    ),
+                ),
+                Lifecycle::Unclaimed => {}
+            }
+            match &target {
+                Lifecycle::Claimed(holder) | Lifecycle::Done(Some(holder)) => log(
+                    &mut cur,
+                    SessionEvent::claim(actor(Some(holder)), after.id.clone()),
+                ),
+                Lifecycle::Done(None) => {
+                    log(&mut cur, SessionEvent::claim(actor(None), after.id.clone()))
+                }
+                Lifecycle::Unclaimed => {}
+            }
+        }
+        if matches!(target, Lifecycle::Done(_)) {
+            log(
+                &mut cur,
+                SessionEvent::done(session_id.clone(), after.id.clone()),
+            );
+        }
+    }
+
+    for blocker_id in cur.blocked_by.clone() {
+        if !after.blocked_by.contains(&blocker_id) {
+            let unblock = SessionEvent::unblock(
+                session_id.clone(),
+                after.id.clone(),
+                serde_json::json!({ "blocker_id": blocker_id }),
+            );
+            log(&mut cur, unblock);
+        }
+    }
+    for blocker_id in &after.blocked_by {
+        if !cur.blocked_by.contains(blocker_id) {
+            let block = SessionEvent::block(
+                session_id.clone(),
+                after.id.clone(),
+                serde_json::json!({ "blocker_id": blocker_id }),
+            );
+            log(&mut cur, block);
+        }
+    }
+
+    let mut changes = serde_json::Map::new();
+    if cur.title != after.title {
+        changes.insert("title".to_string(), after.title.clone().into());
+    }
+    if cur.description != after.description {
+        changes.insert(
+            "description".to_string(),
+            serde_json::json!(after.description),
+        );
+    }
+    if cur.due_at != after.due_at {
+        changes.insert("due_at".to_string(), serde_json::json!(after.due_at));
+    }
+    if cur.assignee != after.assignee {
+        changes.insert("assignee".to_string(), serde_json::json!(after.assignee));
+    }
+    if cur.rank != after.rank {
+        changes.insert("rank".to_string(), serde_json::json!(after.rank));
+    }
+    if cur.parent_id != after.parent_id {
+        changes.insert("parent_id".to_string(), serde_json::json!(after.parent_id));
+    }
+    if cur.deleted_at != after.deleted_at {
+        changes.insert(
+            "deleted_at".to_string(),
+            serde_json::json!(after.deleted_at),
+        );
+    }
+    if !changes.is_empty() {
+        log(&mut cur, edit(serde_json::Value::Object(changes)));
+    }
+
+    for attachment in cur.attachments.clone() {
+        if !after.attachments.iter().any(|a| a.path == attachment.path) {
+            log(
+                &mut cur,
+                edit(serde_json::json!({ "detached": attachment.path })),
+            );
+        }
+    }
+    for attachment in &after.attachments {
+        if !cur.attachments.iter().any(|a| a.path == attachment.path) {
+            log(
+                &mut cur,
+                edit(serde_json::json!({ "attached": attachment.path })),
+            );
+        }
+    }
+
+    events
+}
+
+fn string_field(context: &serde_json::Value, key: &str) -> Option<String> {
+    context.get(key)?.as_str().map(str::to_string)
+}
+
+fn time_field(context: &serde_json::Value, key: &str) -> Option<DateTime<Utc>> {
+    let text = context.get(key)?.as_str()?;
+    DateTime::parse_from_rfc3339(text)
+        .ok()
+        .map(|at| at.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::issue::{IssueStatus, SessionId};
+
+    fn ses() -> SessionId {
+        SessionId::new("ses_replay").unwrap()
+    }
+
+    /// Events stamped one second apart, in the given order.
+    fn sequenced(mut events: Vec<SessionEvent>) -> Vec<SessionEvent> {
+        let start = Utc::now();
+        for (n, event) in events.iter_mut().enumerate() {
+            event.timestamp = start + chrono::Duration::seconds(n as i64);
+        }
+        events
+    }
+
+    #[test]
+    fn test_replay_rebuilds_lifecycle_and_blockers() {
+        let events = sequenced(vec![
+            SessionEvent::create(
+                ses(),
+                "mn-4e0001".to_string(),
+                serde_json::json!({ "title": "Base", "description": "Root work" }),
+            ),
+            SessionEvent::create(
+                ses(),
+                "mn-4e0002".to_string(),
+                serde_json::json!({ "title": "Follow-up", "description": null }),
+            ),
+            SessionEvent::block(
+                ses(),
+                "mn-4e0002".to_string(),
+                serde_json::json!({ "blocker_id": "mn-4e0001" }),
+            ),
+            SessionEvent::claim(ses(), "mn-4e0001".to_string()),
+            SessionEvent::done(ses(), "mn-4e0001".to_string()),
+            SessionEvent::edit(
+                ses(),
+                "mn-4e0002".to_string(),
+                serde_json::json!({ "rank": 2.5, "parent_id": "mn-4e0001" }),
+            ),
+        ]);
+
+        let replayed = replay(&events);
+
+        assert_eq!(replayed.skipped_events, 0);
+        let base = &replayed.issues[0];
+        assert_eq!(base.description.as_deref(), Some("Root work"));
+        assert_eq!(base.status, IssueStatus::Done);
+        assert_eq!(base.claimed_by.as_deref(), Some("ses_replay"));
+        assert_eq!(base.created_at, events[0].timestamp);
+
+        let follow = &replayed.issues[1];
+        assert_eq!(follow.blocked_by, vec!["mn-4e0001"]);
+        assert_eq!(follow.status, IssueStatus::Blocked);
+        assert_eq!(follow.rank, Some(2.5));
+        assert_eq!(follow.parent_id.as_deref(), Some("mn-4e0001"));
+        assert_eq!(follow.updated_at, events[5].timestamp);
+    }
+
+    #[test]
+    fn test_replay_skips_events_without_create() {
+        let events = sequenced(vec![
+            SessionEvent::claim(ses(), "mn-4e0009".to_string()),
+            SessionEvent::create(
+                ses(),
+                "mn-4e0003".to_string(),
+                serde_json::json!({ "title": "Only" }),
+            ),
+            // Done without a claim is not a legal transition
+            SessionEvent::done(ses(), "mn-4e0003".to_string()),
+        ]);
+
+        let replayed = replay(&events);
+
+        assert_eq!(replayed.issues.len(), 1);
+        assert_eq!(replayed.issues[0].status, IssueStatus::Open);
+        assert_eq!(replayed.skipped_events, 2);
+    }
+
+    #[test]
+    fn test_replay_delete_removes_issue_and_references() {
+        let events = sequenced(vec![
+            SessionEvent::create(
+                ses(),
+                "mn-4e0011".to_string(),
+                serde_json::json!({ "title": "Kept" }),
+            ),
+            SessionEvent::create(
+                ses(),
+                "mn-4e0012".to_string(),
+                serde_json::json!({ "title": "Doomed" }),
+            ),
+            SessionEvent::block(
+                ses(),
+                "mn-4e0011".to_string(),
+                serde_json::json!({ "blocker_id": "mn-4e0012" }),
+            ),
+            SessionEvent::edit(
+                ses(),
+                "mn-4e0011".to_string(),
+                serde_json::json!({ "parent_id": "mn-4e0012" }),
+            ),
+            SessionEvent::delete(ses(), "mn-4e0012".to_string()),
+            // Nothing left to delete
+            SessionEvent::delete(ses(), "mn-4e0012".to_string()),
+        ]);
+
+        let replayed = replay(&events);
+
+        assert_eq!(replayed.skipped_events, 1);
+        assert_eq!(replayed.issues.len(), 1);
+        let kept = &replayed.issues[0];
+        assert!(kept.blocked_by.is_empty());
+        assert_eq!(kept.status, IssueStatus::Open);
+        assert_eq!(kept.parent_id, None);
+        assert_eq!(kept.updated_at, events[4].timestamp);
+    }
+
+    #[test]
+    fn test_events_between_round_trips_any_change() {
+        let blocked_by = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect();
+        let mut open = Issue::new("mn-4e0031".to_string(), "Plain".to_string()).unwrap();
+        open.add_blocker("mn-4e00aa".to_string());
+
+        let mut claimed_blocked =
+            Issue::new("mn-4e0031".to_string(), "Renamed".to_string()).unwrap();
+        claimed_blocked
+            .claim(SessionId::new("ses_holder").unwrap())
+            .unwrap();
+        claimed_blocked.blocked_by = blocked_by(&["mn-4e00aa", "mn-4e00bb"]);
+        claimed_blocked.status = IssueStatus::Blocked;
+        claimed_blocked.assignee = Some("ses_holder".to_string());
+        claimed_blocked.rank = Some(1.5);
+        claimed_blocked.attach("log.txt".to_string(), None).unwrap();
+
+        let mut done_trashed = claimed_blocked.clone();
+        done_trashed.blocked_by = blocked_by(&["mn-4e00bb"]);
+        done_trashed.claimed_by = Some("ses_closer".to_string());
+        done_trashed.status = IssueStatus::Done;
+        done_trashed.description = Some("Closed out".to_string());
+        done_trashed.deleted_at = Some(Utc::now());
+        done_trashed.attachments.clear();
+
+        // Every pair, including creation from nothing
+        let states = [open, claimed_blocked, done_trashed];
+        for after in &states {
+            for before in std::iter::once(None).chain(states.iter().map(Some)) {
+                let mut log = match before {
+                    Some(b) => events_between(&ses(), None, b),
+                    None => Vec::new(),
+                };
+                log.extend(events_between(&ses(), before, after));
+                let replayed = replay(&sequenced(log));
+                assert_eq!(replayed.skipped_events, 0);
+                assert!(
+                    diverged(std::slice::from_ref(after), &replayed.issues).is_empty(),
+                    "{:?} -> {:?}",
+                    before.map(|b| &b.status),
+                    after.status
+                );
+            }
+        }
+
+        // An unchanged issue needs nothing
+        assert!(events_between(&ses(), Some(&states[1]), &states[1]).is_empty());
+    }
+
+    #[test]
+    fn test_diverged_flags_unlogged_changes() {
+        let logged_so_far = || {
+            vec![
+                SessionEvent::create(
+                    ses(),
+                    "mn-4e0021".to_string(),
+                    serde_json::json!({ "title": "Claimed" }),
+                ),
+                SessionEvent::create(
+                    ses(),
+                    "mn-4e0022".to_string(),
+                    serde_json::json!({ "title": "Deleted" }),
+                ),
+                SessionEvent::claim(ses(), "mn-4e0021".to_string()),
+            ]
+        };
+        let events = sequenced(logged_so_far());
+        let mut live = replay(&events).issues;
+        assert!(diverged(&live, &replay(&events).issues).is_empty());
+
+        // The delete and a status change were never logged
+        live.retain(|i| i.id != "mn-4e0022");
+        live[0].complete().unwrap();
+        assert_eq!(
+            diverged(&live, &replay(&events).issues),
+            vec!["mn-4e0021", "mn-4e0022"]
+        );
+
+        // Logging them closes the gap; timestamps do not count
+        let mut logged = logged_so_far();
+        logged.extend([
+            SessionEvent::delete(ses(), "mn-4e0022".to_string()),
+            SessionEvent::done(ses(), "mn-4e0021".to_string()),
+        ]);
+        let logged = sequenced(logged);
+        live[0].updated_at = Utc::now();
+        assert!(diverged(&live, &replay(&logged).issues).is_empty());
+    }
+}
@@ -323,6 +323,58 @@ else
     fail "JSONL file has correct line count" "Expected 10 lines, got $lines"
 fi
 
+# ----------------------------------------------------------------------------
+# Test E6b: Concurrent claim-batch
+# ----------------------------------------------------------------------------
+echo ""
+echo "Test E6b: concurrent claim-batch (20 claimers, 5 issues)"
+cd "$TEST_DIR"
+rm -rf .manna
+"$MANNA" init >/dev/null 2>&1
+for i in {1..5}; do
+    "$MANNA" create "Claimable issue $i" >/dev/null 2>&1
+done
+
+for i in {1..20}; do
+    MANNA_SESSION_ID="ses_racer_$i" "$MANNA" claim-batch --count 1 >/dev/null 2>&1 &
+done
+wait
+
+claims=$(grep -c '"claimed_by":"ses_racer_' .manna/issues.jsonl || true)
+events=$(grep -c '"event":"claim"' .manna/sessions.jsonl || true)
+dupes=$(grep -o '"event":"claim"[^}]*"issue_id":"mn-[a-f0-9]*"' .manna/sessions.jsonl \
+    | grep -o 'mn-[a-f0-9]*' | sort | uniq -d | wc -l | tr -d ' ')
+if [[ "$claims" -eq 5 && "$events" -eq 5 && "$dupes" -eq 0 ]]; then
+    pass "each issue claimed by exactly one claimer, with one claim event"
+else
+    fail "each issue claimed by exactly one claimer, with one claim event" \
+        "Got $claims claimed issues, $events claim events, $dupes issues claimed twice"
+fi
+
+# ----------------------------------------------------------------------------
+# Test E6c: Concurrent rank and create
+# ----------------------------------------------------------------------------
+echo ""
+echo "Test E6c: concurrent rank and create (20 of each)"
+cd "$TEST_DIR"
+rm -rf .manna
+"$MANNA" init >/dev/null 2>&1
+first_id=$(extract_id "$("$MANNA" create "Rank A" 2>&1)")
+second_id=$(extract_id "$("$MANNA" create "Rank B" 2>&1)")
+
+for i in {1..20}; do
+    "$MANNA" create "Racing issue $i" >/dev/null 2>&1 &
+    "$MANNA" rank "$first_id" --before "$second_id" >/dev/null 2>&1 &
+done
+wait
+
+lines=$(wc -l < .manna/issues.jsonl | tr -d ' ')
+if [[ "$lines" -eq 22 ]]; then
+    pass "no create lost to a concurrent rank"
+else
+    fail "no create lost to a concurrent rank" "Expected 22 issues, got $lines"
+fi
+
 # ----------------------------------------------------------------------------
 # Test E7: Block with non-existent blocker
 # ----------------------------------------------------------------------------
@@ -342,6 +394,196 @@ echo "Test E8: double init (should be idempotent)"
 output=$("$MANNA" init 2>&1) || true
 check_yaml "$output" "success: true" "second init succeeds"
 
+# ----------------------------------------------------------------------------
+# Test E9: Replay reproduces the store after bulk commands
+# ----------------------------------------------------------------------------
+echo ""
+echo "Test E9: replay reproduces the store after bulk commands"
+cd "$TEST_DIR"
+rm -rf .manna other
+"$MANNA" init >/dev/null 2>&1
+
+# replay refuses when the log does not reproduce the live issues
+check_replay() {
+    local desc="$1"
+    local before after output
+    before=$("$MANNA" list 2>&1 | grep -E '^ *(id|status|claimed_by):' || true)
+    output=$("$MANNA" replay --force 2>&1) || true
+    after=$("$MANNA" list 2>&1 | grep -E '^ *(id|status|claimed_by):' || true)
+    if [[ "$output" == *"success: true"* && "$before" == "$after" ]]; then
+        pass "$desc"
+    else
+        fail "$desc" "replay: $output"
+    fi
+}
+
+for title in Alpha Beta Gamma Delta; do
+    "$MANNA" create "$title" >/dev/null 2>&1
+done
+alpha=$(extract_id "$("$MANNA" list --status open 2>&1)")
+"$MANNA" claim "$alpha" --assign-self >/dev/null 2>&1
+"$MANNA" bulk-transition --status in_progress --to done --force >/dev/null 2>&1
+check_replay "replay matches after bulk-transition"
+
+"$MANNA" unassign-all --assignee "$MANNA_SESSION_ID" --force >/dev/null 2>&1
+check_replay "replay matches after unassign-all"
+
+"$MANNA" bulk-delete --status done --force >/dev/null 2>&1
+check_replay "replay matches after bulk-delete"
+
+beta=$(extract_id "$("$MANNA" list --status open 2>&1)")
+"$MANNA" trash "$beta" >/dev/null 2>&1
+"$MANNA" empty-trash --force >/dev/null 2>&1
+check_replay "replay matches after empty-trash"
+
+now=$(date -u +%Y-%m-%dT%H:%M:%SZ)
+echo "{\"id\":\"mn-1e0001\",\"title\":\"Imported\",\"status\":\"blocked\",\"description\":\"From elsewhere\",\"created_at\":\"$now\",\"updated_at\":\"$now\",\"blocked_by\":[\"mn-1e00ff\"],\"claimed_by\":null,\"claimed_at\":null,\"rank\":2.0}" > import.jsonl
+"$MANNA" import --input import.jsonl >/dev/null 2>&1
+check_replay "replay matches after import"
+
+"$MANNA" relink-blockers --prune >/dev/null 2>&1
+check_replay "replay matches after relink-blockers --prune"
+
+mkdir other
+(
+    cd other
+    "$MANNA" init >/dev/null 2>&1
+    other_id=$(extract_id "$("$MANNA" create "Theirs" 2>&1)")
+    "$MANNA" claim "$other_id" >/dev/null 2>&1
+)
+"$MANNA" merge-stores other >/dev/null 2>&1
+check_replay "replay matches after merge-stores"
+
+gamma=$(extract_id "$("$MANNA" create "Reopened" 2>&1)")
+"$MANNA" claim "$gamma" >/dev/null 2>&1
+"$MANNA" done "$gamma" >/dev/null 2>&1
+"$MANNA" reopen "$gamma" >/dev/null 2>&1
+check_replay "replay matches after reopen"
+if grep -q "\"event\":\"reopen\".*\"issue_id\":\"$gamma\"" .manna/sessions.jsonl; then
+    pass "reopen records a reopen event"
+else
+    fail "reopen records a reopen event" "No reopen event for $gamma"
+fi
+
+# ----------------------------------------------------------------------------
+# Test E10: Several commands through one repl
+# ----------------------------------------------------------------------------
+echo ""
+echo "Test E10: several commands through one repl"
+cd "$TEST_DIR"
+rm -rf .manna
+"$MANNA" init >/dev/null 2>&1
+first_id=$(extract_id "$("$MANNA" create "Made outside" 2>&1)")
+
+output=$(
+    {
+        echo "claim $first_id"
+        echo "create \"Made in the repl\" \"With a description\""
+        echo "show $first_id"
+        echo "done mn-nonexistent"
+        # Give the outside create time to land before the next list
+        sleep 0.5
+        echo "list --status open"
+    } | "$MANNA" repl 2>&1 &
+    sleep 0.25
+    "$MANNA" create "Made during the repl" >/dev/null 2>&1
+    wait
+)
+docs=$(echo "$output" | grep -c '^---$' || true)
+check_yaml "$output" "claimed_by: $MANNA_SESSION_ID" "repl claims an issue"
+check_yaml "$output" "title: Made in the repl" "repl creates an issue"
+check_yaml "$output" "Issue mn-nonexistent not found" "repl reports a failed command and carries on"
+check_yaml "$output" "title: Made during the repl" "repl reloads after an outside write"
+if [[ "$docs" -eq 5 && $(grep -c . .manna/issues.jsonl) -eq 3 ]]; then
+    pass "repl answers every command and writes through the store"
+else
+    fail "repl answers every command and writes through the store" "Got $docs responses: $output"
+fi
+
+# Each command's profile is timed from when the repl read it
+profiles=$(
+    {
+        echo "list"
+        sleep 1
+        echo "list"
+    } | "$MANNA" repl --profile 2>&1 >/dev/null
+)
+slowest=$(echo "$profiles" | grep -o '"total_ms":[0-9.]*' | cut -d: -f2 | sort -n | tail -1)
+if [[ $(echo "$profiles" | grep -c '"profile"') -eq 2 ]] && awk "BEGIN { exit !($slowest < 1000) }"; then
+    pass "repl --profile times each command on its own"
+else
+    fail "repl --profile times each command on its own" "Got: $profiles"
+fi
+
+# ----------------------------------------------------------------------------
+# Test E11: Per-issue files, migrated to and back
+# ----------------------------------------------------------------------------
+echo ""
+echo "Test E11: per-issue files, migrated to and back"
+cd "$TEST_DIR"
+rm -rf .manna
+"$MANNA" init >/dev/null 2>&1
+first_id=$(extract_id "$("$MANNA" create "Rank A" 2>&1)")
+second_id=$(extract_id "$("$MANNA" create "Rank B" 2>&1)")
+
+output=$("$MANNA" migrate-layout 2>&1)
+check_yaml "$output" "changed: false" "migrate-layout leaves issues.jsonl without the config flag"
+
+echo "per_issue_files = true" > .manna/config.toml
+output=$("$MANNA" migrate-layout 2>&1)
+check_yaml "$output" "layout: per_issue_files" "migrate-layout moves issues to per-issue files"
+
+for i in {1..20}; do
+    "$MANNA" create "Racing issue $i" >/dev/null 2>&1 &
+    "$MANNA" rank "$first_id" --before "$second_id" >/dev/null 2>&1 &
+done
+wait
+
+files=$(find .manna/issues -name '*.json' | wc -l | tr -d ' ')
+echo "per_issue_files = false" > .manna/config.toml
+"$MANNA" migrate-layout >/dev/null 2>&1
+lines=$(grep -c . .manna/issues.jsonl || true)
+if [[ "$files" -eq 22 && "$lines" -eq 22 && -f .manna/issues.jsonl.migrated ]]; then
+    pass "per-issue files take concurrent writes and migrate back intact"
+else
+    fail "per-issue files take concurrent writes and migrate back intact" \
+        "Got $files issue files, then $lines issues.jsonl lines"
+fi
+
+# ----------------------------------------------------------------------------
+# Test E12: A repl over per-issue files sees files rewritten in place
+# ----------------------------------------------------------------------------
+echo ""
+echo "Test E12: a repl over per-issue files sees files rewritten in place"
+cd "$TEST_DIR"
+rm -rf .manna
+"$MANNA" init >/dev/null 2>&1
+first_id=$(extract_id "$("$MANNA" create "Title before" 2>&1)")
+echo "per_issue_files = true" > .manna/config.toml
+"$MANNA" migrate-layout >/dev/null 2>&1
+issue_file=".manna/issues/$first_id.json"
+
+output=$(
+    {
+        echo "show $first_id"
+        # Give the outside rewrite time to land before the next show
+        sleep 0.5
+        echo "show $first_id"
+        echo "claim $first_id"
+    } | "$MANNA" repl 2>&1 &
+    sleep 0.25
+    # Truncate and write the same file, so the directory is left untouched
+    rewritten=$(sed 's/Title before/Title after/' "$issue_file")
+    printf '%s\n' "$rewritten" > "$issue_file"
+    wait
+)
+check_yaml "$output" "title: Title after" "repl rereads an issue file rewritten in place"
+if grep -q '"title":"Title after"' "$issue_file" && grep -q '"claimed_by"' "$issue_file"; then
+    pass "repl claim keeps the outside rewrite"
+else
+    fail "repl claim keeps the outside rewrite" "Got: $(cat "$issue_file")"
+fi
+
 # ============================================================================
 # YAML Validation
 # ============================================================================